@@ -0,0 +1,44 @@
+CREATE SEQUENCE public.twofactors_id_seq;
+
+CREATE TABLE public.TwoFactors (
+                Id INTEGER NOT NULL DEFAULT nextval('public.twofactors_id_seq'),
+                UserId INTEGER NOT NULL,
+                Secret VARCHAR(64) NOT NULL,
+                Enabled BOOLEAN NOT NULL DEFAULT FALSE,
+                CreatedAt TIMESTAMP NOT NULL,
+                CONSTRAINT pk_twofactors PRIMARY KEY (Id)
+);
+
+
+ALTER SEQUENCE public.twofactors_id_seq OWNED BY public.TwoFactors.Id;
+
+CREATE UNIQUE INDEX ix_twofactors_user
+ ON public.TwoFactors
+ ( UserId ASC );
+
+CREATE SEQUENCE public.recoverycodes_id_seq;
+
+CREATE TABLE public.RecoveryCodes (
+                Id INTEGER NOT NULL DEFAULT nextval('public.recoverycodes_id_seq'),
+                UserId INTEGER NOT NULL,
+                CodeHash VARCHAR(250) NOT NULL,
+                UsedAt TIMESTAMP,
+                CONSTRAINT pk_recoverycodes PRIMARY KEY (Id)
+);
+
+
+ALTER SEQUENCE public.recoverycodes_id_seq OWNED BY public.RecoveryCodes.Id;
+
+ALTER TABLE public.TwoFactors ADD CONSTRAINT fk_twofactors_users
+FOREIGN KEY (UserId)
+REFERENCES public.Users (Id)
+ON DELETE RESTRICT
+ON UPDATE RESTRICT
+NOT DEFERRABLE;
+
+ALTER TABLE public.RecoveryCodes ADD CONSTRAINT fk_recoverycodes_users
+FOREIGN KEY (UserId)
+REFERENCES public.Users (Id)
+ON DELETE RESTRICT
+ON UPDATE RESTRICT
+NOT DEFERRABLE;
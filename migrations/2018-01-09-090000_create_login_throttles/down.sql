@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+
+drop TABLE public.LockoutEvents;
+
+drop TABLE public.LoginThrottles;
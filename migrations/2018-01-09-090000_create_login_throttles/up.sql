@@ -0,0 +1,38 @@
+CREATE SEQUENCE public.loginthrottles_id_seq;
+
+CREATE TABLE public.LoginThrottles (
+                Id INTEGER NOT NULL DEFAULT nextval('public.loginthrottles_id_seq'),
+                Scope VARCHAR(20) NOT NULL,
+                ThrottleKey VARCHAR(250) NOT NULL,
+                Failures INTEGER NOT NULL DEFAULT 0,
+                LastFailureAt TIMESTAMP,
+                LockedUntil TIMESTAMP,
+                CONSTRAINT pk_loginthrottles PRIMARY KEY (Id)
+);
+
+
+ALTER SEQUENCE public.loginthrottles_id_seq OWNED BY public.LoginThrottles.Id;
+
+CREATE UNIQUE INDEX ix_loginthrottles
+ ON public.LoginThrottles
+ ( Scope ASC, ThrottleKey ASC );
+
+CREATE SEQUENCE public.lockoutevents_id_seq;
+
+CREATE TABLE public.LockoutEvents (
+                Id INTEGER NOT NULL DEFAULT nextval('public.lockoutevents_id_seq'),
+                Scope VARCHAR(20) NOT NULL,
+                ThrottleKey VARCHAR(250) NOT NULL,
+                Event VARCHAR(20) NOT NULL,
+                Failures INTEGER NOT NULL,
+                LockedUntil TIMESTAMP,
+                CreatedAt TIMESTAMP NOT NULL,
+                CONSTRAINT pk_lockoutevents PRIMARY KEY (Id)
+);
+
+
+ALTER SEQUENCE public.lockoutevents_id_seq OWNED BY public.LockoutEvents.Id;
+
+CREATE INDEX ix_lockoutevents_created
+ ON public.LockoutEvents
+ ( CreatedAt DESC );
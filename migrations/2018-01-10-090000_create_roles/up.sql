@@ -0,0 +1,48 @@
+CREATE SEQUENCE public.userroles_id_seq;
+
+CREATE TABLE public.UserRoles (
+                Id INTEGER NOT NULL DEFAULT nextval('public.userroles_id_seq'),
+                UserId INTEGER NOT NULL,
+                Role VARCHAR(20) NOT NULL,
+                GrantedBy INTEGER,
+                GrantedAt TIMESTAMP NOT NULL,
+                CONSTRAINT pk_userroles PRIMARY KEY (Id)
+);
+
+
+ALTER SEQUENCE public.userroles_id_seq OWNED BY public.UserRoles.Id;
+
+CREATE UNIQUE INDEX ix_userroles_user
+ ON public.UserRoles
+ ( UserId ASC );
+
+CREATE SEQUENCE public.pinnedarticles_id_seq;
+
+CREATE TABLE public.PinnedArticles (
+                Id INTEGER NOT NULL DEFAULT nextval('public.pinnedarticles_id_seq'),
+                ArticleId INTEGER NOT NULL,
+                PinnedBy INTEGER NOT NULL,
+                PinnedAt TIMESTAMP NOT NULL,
+                CONSTRAINT pk_pinnedarticles PRIMARY KEY (Id)
+);
+
+
+ALTER SEQUENCE public.pinnedarticles_id_seq OWNED BY public.PinnedArticles.Id;
+
+CREATE UNIQUE INDEX ix_pinnedarticles_article
+ ON public.PinnedArticles
+ ( ArticleId ASC );
+
+ALTER TABLE public.UserRoles ADD CONSTRAINT fk_userroles_users
+FOREIGN KEY (UserId)
+REFERENCES public.Users (Id)
+ON DELETE RESTRICT
+ON UPDATE RESTRICT
+NOT DEFERRABLE;
+
+ALTER TABLE public.PinnedArticles ADD CONSTRAINT fk_pinnedarticles_articles
+FOREIGN KEY (ArticleId)
+REFERENCES public.Articles (Id)
+ON DELETE RESTRICT
+ON UPDATE RESTRICT
+NOT DEFERRABLE;
@@ -0,0 +1,91 @@
+-- This file should undo anything in `up.sql`
+
+ALTER TABLE public.Followings DROP CONSTRAINT fk_followings_users;
+
+ALTER TABLE public.Followings ADD CONSTRAINT fk_followings_users
+FOREIGN KEY (FollowerId)
+REFERENCES public.Users (Id)
+ON DELETE RESTRICT
+ON UPDATE RESTRICT
+NOT DEFERRABLE;
+
+ALTER TABLE public.Followings DROP CONSTRAINT fk_followings_users1;
+
+ALTER TABLE public.Followings ADD CONSTRAINT fk_followings_users1
+FOREIGN KEY (FollowingId)
+REFERENCES public.Users (Id)
+ON DELETE RESTRICT
+ON UPDATE RESTRICT
+NOT DEFERRABLE;
+
+ALTER TABLE public.FavoritedArticles DROP CONSTRAINT fk_favoritedarticles_users;
+
+ALTER TABLE public.FavoritedArticles ADD CONSTRAINT fk_favoritedarticles_users
+FOREIGN KEY (UserId)
+REFERENCES public.Users (Id)
+ON DELETE RESTRICT
+ON UPDATE RESTRICT
+NOT DEFERRABLE;
+
+ALTER TABLE public.FavoritedArticles DROP CONSTRAINT fk_favoritedarticles_articles;
+
+ALTER TABLE public.FavoritedArticles ADD CONSTRAINT fk_favoritedarticles_articles
+FOREIGN KEY (ArticleId)
+REFERENCES public.Articles (Id)
+ON DELETE RESTRICT
+ON UPDATE RESTRICT
+NOT DEFERRABLE;
+
+ALTER TABLE public.ArticleTags DROP CONSTRAINT fk_articletags_articles;
+
+ALTER TABLE public.ArticleTags ADD CONSTRAINT fk_articletags_articles
+FOREIGN KEY (ArticleId)
+REFERENCES public.Articles (Id)
+ON DELETE RESTRICT
+ON UPDATE RESTRICT
+NOT DEFERRABLE;
+
+ALTER TABLE public.Comments DROP CONSTRAINT fk_comments_articles;
+
+ALTER TABLE public.Comments ADD CONSTRAINT fk_comments_articles
+FOREIGN KEY (ArticleId)
+REFERENCES public.Articles (Id)
+ON DELETE RESTRICT
+ON UPDATE RESTRICT
+NOT DEFERRABLE;
+
+ALTER TABLE public.PinnedArticles DROP CONSTRAINT fk_pinnedarticles_articles;
+
+ALTER TABLE public.PinnedArticles ADD CONSTRAINT fk_pinnedarticles_articles
+FOREIGN KEY (ArticleId)
+REFERENCES public.Articles (Id)
+ON DELETE RESTRICT
+ON UPDATE RESTRICT
+NOT DEFERRABLE;
+
+ALTER TABLE public.UserRoles DROP CONSTRAINT fk_userroles_users;
+
+ALTER TABLE public.UserRoles ADD CONSTRAINT fk_userroles_users
+FOREIGN KEY (UserId)
+REFERENCES public.Users (Id)
+ON DELETE RESTRICT
+ON UPDATE RESTRICT
+NOT DEFERRABLE;
+
+ALTER TABLE public.TwoFactors DROP CONSTRAINT fk_twofactors_users;
+
+ALTER TABLE public.TwoFactors ADD CONSTRAINT fk_twofactors_users
+FOREIGN KEY (UserId)
+REFERENCES public.Users (Id)
+ON DELETE RESTRICT
+ON UPDATE RESTRICT
+NOT DEFERRABLE;
+
+ALTER TABLE public.RecoveryCodes DROP CONSTRAINT fk_recoverycodes_users;
+
+ALTER TABLE public.RecoveryCodes ADD CONSTRAINT fk_recoverycodes_users
+FOREIGN KEY (UserId)
+REFERENCES public.Users (Id)
+ON DELETE RESTRICT
+ON UPDATE RESTRICT
+NOT DEFERRABLE;
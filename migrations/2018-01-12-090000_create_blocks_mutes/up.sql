@@ -0,0 +1,61 @@
+CREATE SEQUENCE public.blocks_id_seq;
+
+CREATE TABLE public.Blocks (
+                Id INTEGER NOT NULL DEFAULT nextval('public.blocks_id_seq'),
+                BlockerId INTEGER NOT NULL,
+                BlockedId INTEGER NOT NULL,
+                CreatedAt TIMESTAMP NOT NULL,
+                CONSTRAINT pk_blocks PRIMARY KEY (Id)
+);
+
+
+ALTER SEQUENCE public.blocks_id_seq OWNED BY public.Blocks.Id;
+
+CREATE UNIQUE INDEX ix_blocks
+ ON public.Blocks
+ ( BlockerId ASC, BlockedId ASC );
+
+CREATE SEQUENCE public.mutes_id_seq;
+
+CREATE TABLE public.Mutes (
+                Id INTEGER NOT NULL DEFAULT nextval('public.mutes_id_seq'),
+                MuterId INTEGER NOT NULL,
+                MutedId INTEGER NOT NULL,
+                CreatedAt TIMESTAMP NOT NULL,
+                CONSTRAINT pk_mutes PRIMARY KEY (Id)
+);
+
+
+ALTER SEQUENCE public.mutes_id_seq OWNED BY public.Mutes.Id;
+
+CREATE UNIQUE INDEX ix_mutes
+ ON public.Mutes
+ ( MuterId ASC, MutedId ASC );
+
+ALTER TABLE public.Blocks ADD CONSTRAINT fk_blocks_users
+FOREIGN KEY (BlockerId)
+REFERENCES public.Users (Id)
+ON DELETE CASCADE
+ON UPDATE RESTRICT
+NOT DEFERRABLE;
+
+ALTER TABLE public.Blocks ADD CONSTRAINT fk_blocks_users1
+FOREIGN KEY (BlockedId)
+REFERENCES public.Users (Id)
+ON DELETE CASCADE
+ON UPDATE RESTRICT
+NOT DEFERRABLE;
+
+ALTER TABLE public.Mutes ADD CONSTRAINT fk_mutes_users
+FOREIGN KEY (MuterId)
+REFERENCES public.Users (Id)
+ON DELETE CASCADE
+ON UPDATE RESTRICT
+NOT DEFERRABLE;
+
+ALTER TABLE public.Mutes ADD CONSTRAINT fk_mutes_users1
+FOREIGN KEY (MutedId)
+REFERENCES public.Users (Id)
+ON DELETE CASCADE
+ON UPDATE RESTRICT
+NOT DEFERRABLE;
@@ -0,0 +1,29 @@
+CREATE SEQUENCE public.tagfollowings_id_seq;
+
+CREATE TABLE public.TagFollowings (
+                Id INTEGER NOT NULL DEFAULT nextval('public.tagfollowings_id_seq'),
+                TagId INTEGER NOT NULL,
+                FollowerId INTEGER NOT NULL,
+                CONSTRAINT pk_tagfollowings PRIMARY KEY (Id)
+);
+
+
+ALTER SEQUENCE public.tagfollowings_id_seq OWNED BY public.TagFollowings.Id;
+
+CREATE UNIQUE INDEX ix_tagfollowings
+ ON public.TagFollowings
+ ( TagId ASC, FollowerId ASC );
+
+ALTER TABLE public.TagFollowings ADD CONSTRAINT fk_tagfollowings_tags
+FOREIGN KEY (TagId)
+REFERENCES public.Tags (Id)
+ON DELETE CASCADE
+ON UPDATE RESTRICT
+NOT DEFERRABLE;
+
+ALTER TABLE public.TagFollowings ADD CONSTRAINT fk_tagfollowings_users
+FOREIGN KEY (FollowerId)
+REFERENCES public.Users (Id)
+ON DELETE CASCADE
+ON UPDATE RESTRICT
+NOT DEFERRABLE;
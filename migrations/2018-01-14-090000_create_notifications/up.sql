@@ -0,0 +1,72 @@
+CREATE SEQUENCE public.notifications_id_seq;
+
+CREATE TABLE public.Notifications (
+                Id INTEGER NOT NULL DEFAULT nextval('public.notifications_id_seq'),
+                UserId INTEGER NOT NULL,
+                ActorId INTEGER NOT NULL,
+                Kind VARCHAR(20) NOT NULL,
+                ArticleId INTEGER,
+                CommentId INTEGER,
+                ReadAt TIMESTAMP,
+                CreatedAt TIMESTAMP NOT NULL,
+                CONSTRAINT pk_notifications PRIMARY KEY (Id)
+);
+
+
+ALTER SEQUENCE public.notifications_id_seq OWNED BY public.Notifications.Id;
+
+CREATE INDEX ix_notifications_user
+ ON public.Notifications
+ ( UserId ASC, ReadAt ASC );
+
+CREATE SEQUENCE public.notificationpreferences_id_seq;
+
+CREATE TABLE public.NotificationPreferences (
+                Id INTEGER NOT NULL DEFAULT nextval('public.notificationpreferences_id_seq'),
+                UserId INTEGER NOT NULL,
+                Kind VARCHAR(20) NOT NULL,
+                Enabled BOOLEAN NOT NULL,
+                CONSTRAINT pk_notificationpreferences PRIMARY KEY (Id)
+);
+
+
+ALTER SEQUENCE public.notificationpreferences_id_seq OWNED BY public.NotificationPreferences.Id;
+
+CREATE UNIQUE INDEX ix_notificationpreferences
+ ON public.NotificationPreferences
+ ( UserId ASC, Kind ASC );
+
+ALTER TABLE public.Notifications ADD CONSTRAINT fk_notifications_users
+FOREIGN KEY (UserId)
+REFERENCES public.Users (Id)
+ON DELETE CASCADE
+ON UPDATE RESTRICT
+NOT DEFERRABLE;
+
+ALTER TABLE public.Notifications ADD CONSTRAINT fk_notifications_users1
+FOREIGN KEY (ActorId)
+REFERENCES public.Users (Id)
+ON DELETE CASCADE
+ON UPDATE RESTRICT
+NOT DEFERRABLE;
+
+ALTER TABLE public.Notifications ADD CONSTRAINT fk_notifications_articles
+FOREIGN KEY (ArticleId)
+REFERENCES public.Articles (Id)
+ON DELETE CASCADE
+ON UPDATE RESTRICT
+NOT DEFERRABLE;
+
+ALTER TABLE public.Notifications ADD CONSTRAINT fk_notifications_comments
+FOREIGN KEY (CommentId)
+REFERENCES public.Comments (Id)
+ON DELETE CASCADE
+ON UPDATE RESTRICT
+NOT DEFERRABLE;
+
+ALTER TABLE public.NotificationPreferences ADD CONSTRAINT fk_notificationpreferences_users
+FOREIGN KEY (UserId)
+REFERENCES public.Users (Id)
+ON DELETE CASCADE
+ON UPDATE RESTRICT
+NOT DEFERRABLE;
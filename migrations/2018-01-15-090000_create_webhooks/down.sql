@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+
+drop TABLE public.WebhookAttempts;
+
+drop TABLE public.WebhookDeliveries;
+
+drop TABLE public.Webhooks;
@@ -0,0 +1,74 @@
+CREATE SEQUENCE public.webhooks_id_seq;
+
+CREATE TABLE public.Webhooks (
+                Id INTEGER NOT NULL DEFAULT nextval('public.webhooks_id_seq'),
+                OwnerId INTEGER NOT NULL,
+                Url VARCHAR(2000) NOT NULL,
+                Secret VARCHAR(64) NOT NULL,
+                Events VARCHAR(500) NOT NULL,
+                Global BOOLEAN NOT NULL DEFAULT FALSE,
+                Active BOOLEAN NOT NULL DEFAULT TRUE,
+                CreatedAt TIMESTAMP NOT NULL,
+                CONSTRAINT pk_webhooks PRIMARY KEY (Id)
+);
+
+
+ALTER SEQUENCE public.webhooks_id_seq OWNED BY public.Webhooks.Id;
+
+CREATE SEQUENCE public.webhookdeliveries_id_seq;
+
+CREATE TABLE public.WebhookDeliveries (
+                Id INTEGER NOT NULL DEFAULT nextval('public.webhookdeliveries_id_seq'),
+                WebhookId INTEGER NOT NULL,
+                Event VARCHAR(50) NOT NULL,
+                Payload TEXT NOT NULL,
+                Status VARCHAR(20) NOT NULL,
+                Attempts INTEGER NOT NULL DEFAULT 0,
+                NextAttemptAt TIMESTAMP,
+                CreatedAt TIMESTAMP NOT NULL,
+                DeliveredAt TIMESTAMP,
+                CONSTRAINT pk_webhookdeliveries PRIMARY KEY (Id)
+);
+
+
+ALTER SEQUENCE public.webhookdeliveries_id_seq OWNED BY public.WebhookDeliveries.Id;
+
+CREATE INDEX ix_webhookdeliveries_due
+ ON public.WebhookDeliveries
+ ( Status ASC, NextAttemptAt ASC );
+
+CREATE SEQUENCE public.webhookattempts_id_seq;
+
+CREATE TABLE public.WebhookAttempts (
+                Id INTEGER NOT NULL DEFAULT nextval('public.webhookattempts_id_seq'),
+                DeliveryId INTEGER NOT NULL,
+                AttemptedAt TIMESTAMP NOT NULL,
+                ResponseStatus INTEGER,
+                Error VARCHAR(500),
+                DurationMs INTEGER NOT NULL,
+                CONSTRAINT pk_webhookattempts PRIMARY KEY (Id)
+);
+
+
+ALTER SEQUENCE public.webhookattempts_id_seq OWNED BY public.WebhookAttempts.Id;
+
+ALTER TABLE public.Webhooks ADD CONSTRAINT fk_webhooks_users
+FOREIGN KEY (OwnerId)
+REFERENCES public.Users (Id)
+ON DELETE CASCADE
+ON UPDATE RESTRICT
+NOT DEFERRABLE;
+
+ALTER TABLE public.WebhookDeliveries ADD CONSTRAINT fk_webhookdeliveries_webhooks
+FOREIGN KEY (WebhookId)
+REFERENCES public.Webhooks (Id)
+ON DELETE CASCADE
+ON UPDATE RESTRICT
+NOT DEFERRABLE;
+
+ALTER TABLE public.WebhookAttempts ADD CONSTRAINT fk_webhookattempts_webhookdeliveries
+FOREIGN KEY (DeliveryId)
+REFERENCES public.WebhookDeliveries (Id)
+ON DELETE CASCADE
+ON UPDATE RESTRICT
+NOT DEFERRABLE;
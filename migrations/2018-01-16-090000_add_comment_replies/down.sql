@@ -0,0 +1,9 @@
+-- This file should undo anything in `up.sql`
+
+ALTER TABLE public.Comments DROP CONSTRAINT fk_comments_comments;
+
+DROP INDEX public.ix_comments_parentid;
+
+ALTER TABLE public.Comments DROP COLUMN Deleted;
+
+ALTER TABLE public.Comments DROP COLUMN ParentId;
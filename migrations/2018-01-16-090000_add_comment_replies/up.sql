@@ -0,0 +1,15 @@
+ALTER TABLE public.Comments ADD COLUMN ParentId INTEGER;
+
+-- a tombstoned comment keeps its place in the thread but lost its body
+ALTER TABLE public.Comments ADD COLUMN Deleted BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE INDEX ix_comments_parentid
+ ON public.Comments
+ ( ParentId ASC );
+
+ALTER TABLE public.Comments ADD CONSTRAINT fk_comments_comments
+FOREIGN KEY (ParentId)
+REFERENCES public.Comments (Id)
+ON DELETE CASCADE
+ON UPDATE RESTRICT
+NOT DEFERRABLE;
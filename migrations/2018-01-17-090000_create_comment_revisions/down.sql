@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+
+drop TABLE public.CommentRevisions;
+
+ALTER TABLE public.Comments DROP COLUMN Edited;
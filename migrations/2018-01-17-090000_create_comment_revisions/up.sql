@@ -0,0 +1,34 @@
+ALTER TABLE public.Comments ADD COLUMN Edited BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE SEQUENCE public.commentrevisions_id_seq;
+
+-- every row holds a body a comment had before one of its edits
+CREATE TABLE public.CommentRevisions (
+                Id INTEGER NOT NULL DEFAULT nextval('public.commentrevisions_id_seq'),
+                CommentId INTEGER NOT NULL,
+                Body TEXT NOT NULL,
+                EditorId INTEGER NOT NULL,
+                RevisedAt TIMESTAMP NOT NULL,
+                CONSTRAINT pk_commentrevisions PRIMARY KEY (Id)
+);
+
+
+ALTER SEQUENCE public.commentrevisions_id_seq OWNED BY public.CommentRevisions.Id;
+
+CREATE INDEX ix_commentrevisions_commentid
+ ON public.CommentRevisions
+ ( CommentId ASC );
+
+ALTER TABLE public.CommentRevisions ADD CONSTRAINT fk_commentrevisions_comments
+FOREIGN KEY (CommentId)
+REFERENCES public.Comments (Id)
+ON DELETE CASCADE
+ON UPDATE RESTRICT
+NOT DEFERRABLE;
+
+ALTER TABLE public.CommentRevisions ADD CONSTRAINT fk_commentrevisions_users
+FOREIGN KEY (EditorId)
+REFERENCES public.Users (Id)
+ON DELETE CASCADE
+ON UPDATE RESTRICT
+NOT DEFERRABLE;
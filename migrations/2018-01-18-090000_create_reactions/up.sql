@@ -0,0 +1,47 @@
+CREATE SEQUENCE public.reactions_id_seq;
+
+CREATE TABLE public.Reactions (
+                Id INTEGER NOT NULL DEFAULT nextval('public.reactions_id_seq'),
+                UserId INTEGER NOT NULL,
+                Reaction VARCHAR(32) NOT NULL,
+                CommentId INTEGER,
+                ArticleId INTEGER,
+                CreatedAt TIMESTAMP NOT NULL,
+                CONSTRAINT pk_reactions PRIMARY KEY (Id),
+                -- a reaction is on either a comment or an article
+                CONSTRAINT ck_reactions_target CHECK ((CommentId IS NULL) <> (ArticleId IS NULL))
+);
+
+
+ALTER SEQUENCE public.reactions_id_seq OWNED BY public.Reactions.Id;
+
+CREATE UNIQUE INDEX ix_reactions_comments
+ ON public.Reactions
+ ( CommentId ASC, UserId ASC, Reaction ASC )
+ WHERE CommentId IS NOT NULL;
+
+CREATE UNIQUE INDEX ix_reactions_articles
+ ON public.Reactions
+ ( ArticleId ASC, UserId ASC, Reaction ASC )
+ WHERE ArticleId IS NOT NULL;
+
+ALTER TABLE public.Reactions ADD CONSTRAINT fk_reactions_users
+FOREIGN KEY (UserId)
+REFERENCES public.Users (Id)
+ON DELETE CASCADE
+ON UPDATE RESTRICT
+NOT DEFERRABLE;
+
+ALTER TABLE public.Reactions ADD CONSTRAINT fk_reactions_comments
+FOREIGN KEY (CommentId)
+REFERENCES public.Comments (Id)
+ON DELETE CASCADE
+ON UPDATE RESTRICT
+NOT DEFERRABLE;
+
+ALTER TABLE public.Reactions ADD CONSTRAINT fk_reactions_articles
+FOREIGN KEY (ArticleId)
+REFERENCES public.Articles (Id)
+ON DELETE CASCADE
+ON UPDATE RESTRICT
+NOT DEFERRABLE;
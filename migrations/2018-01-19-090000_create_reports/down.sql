@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+
+drop TABLE public.Reports;
+
+ALTER TABLE public.Comments DROP COLUMN Hidden;
+
+ALTER TABLE public.Articles DROP COLUMN Hidden;
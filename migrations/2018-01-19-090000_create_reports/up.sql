@@ -0,0 +1,65 @@
+-- content is hidden once enough readers reported it, until a moderator reviewed the reports
+ALTER TABLE public.Articles ADD COLUMN Hidden BOOLEAN NOT NULL DEFAULT FALSE;
+
+ALTER TABLE public.Comments ADD COLUMN Hidden BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE SEQUENCE public.reports_id_seq;
+
+CREATE TABLE public.Reports (
+                Id INTEGER NOT NULL DEFAULT nextval('public.reports_id_seq'),
+                ReporterId INTEGER NOT NULL,
+                ArticleId INTEGER NOT NULL,
+                CommentId INTEGER,
+                Reason VARCHAR(20) NOT NULL,
+                Details TEXT,
+                Status VARCHAR(20) NOT NULL,
+                ResolvedBy INTEGER,
+                ResolvedAt TIMESTAMP,
+                CreatedAt TIMESTAMP NOT NULL,
+                CONSTRAINT pk_reports PRIMARY KEY (Id)
+);
+
+
+ALTER SEQUENCE public.reports_id_seq OWNED BY public.Reports.Id;
+
+CREATE UNIQUE INDEX ix_reports_articles
+ ON public.Reports
+ ( ArticleId ASC, ReporterId ASC )
+ WHERE CommentId IS NULL;
+
+CREATE UNIQUE INDEX ix_reports_comments
+ ON public.Reports
+ ( CommentId ASC, ReporterId ASC )
+ WHERE CommentId IS NOT NULL;
+
+CREATE INDEX ix_reports_status
+ ON public.Reports
+ ( Status ASC, CreatedAt ASC );
+
+ALTER TABLE public.Reports ADD CONSTRAINT fk_reports_users
+FOREIGN KEY (ReporterId)
+REFERENCES public.Users (Id)
+ON DELETE CASCADE
+ON UPDATE RESTRICT
+NOT DEFERRABLE;
+
+ALTER TABLE public.Reports ADD CONSTRAINT fk_reports_users1
+FOREIGN KEY (ResolvedBy)
+REFERENCES public.Users (Id)
+ON DELETE SET NULL
+ON UPDATE RESTRICT
+NOT DEFERRABLE;
+
+ALTER TABLE public.Reports ADD CONSTRAINT fk_reports_articles
+FOREIGN KEY (ArticleId)
+REFERENCES public.Articles (Id)
+ON DELETE CASCADE
+ON UPDATE RESTRICT
+NOT DEFERRABLE;
+
+ALTER TABLE public.Reports ADD CONSTRAINT fk_reports_comments
+FOREIGN KEY (CommentId)
+REFERENCES public.Comments (Id)
+ON DELETE CASCADE
+ON UPDATE RESTRICT
+NOT DEFERRABLE;
@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+
+drop TABLE public.FilterExamples;
+
+DELETE FROM public.Reports WHERE ReporterId IS NULL;
+
+ALTER TABLE public.Reports ALTER COLUMN ReporterId SET NOT NULL;
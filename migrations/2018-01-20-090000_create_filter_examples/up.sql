@@ -0,0 +1,23 @@
+-- reports without a reporter were filed by the content filter
+ALTER TABLE public.Reports ALTER COLUMN ReporterId DROP NOT NULL;
+
+CREATE SEQUENCE public.filterexamples_id_seq;
+
+CREATE TABLE public.FilterExamples (
+                Id INTEGER NOT NULL DEFAULT nextval('public.filterexamples_id_seq'),
+                Body TEXT NOT NULL,
+                Spam BOOLEAN NOT NULL,
+                ReportId INTEGER,
+                CreatedAt TIMESTAMP NOT NULL,
+                CONSTRAINT pk_filterexamples PRIMARY KEY (Id)
+);
+
+
+ALTER SEQUENCE public.filterexamples_id_seq OWNED BY public.FilterExamples.Id;
+
+ALTER TABLE public.FilterExamples ADD CONSTRAINT fk_filterexamples_reports
+FOREIGN KEY (ReportId)
+REFERENCES public.Reports (Id)
+ON DELETE SET NULL
+ON UPDATE RESTRICT
+NOT DEFERRABLE;
@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+
+DROP INDEX public.ix_articles_author_status;
+
+ALTER TABLE public.Articles DROP COLUMN PublishedAt;
+
+ALTER TABLE public.Articles DROP COLUMN Status;
@@ -0,0 +1,10 @@
+-- draft, published, unlisted or archived; everything written so far was published when created
+ALTER TABLE public.Articles ADD COLUMN Status VARCHAR(20) NOT NULL DEFAULT 'published';
+
+ALTER TABLE public.Articles ADD COLUMN PublishedAt TIMESTAMP;
+
+UPDATE public.Articles SET PublishedAt = CreatedAt;
+
+CREATE INDEX ix_articles_author_status
+ ON public.Articles
+ ( Author ASC, Status ASC );
@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+
+drop TABLE public.Jobs;
+
+ALTER TABLE public.Articles DROP COLUMN PublishAt;
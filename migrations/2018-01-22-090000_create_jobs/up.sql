@@ -0,0 +1,25 @@
+-- drafts can be scheduled, a job publishes them when the time comes
+ALTER TABLE public.Articles ADD COLUMN PublishAt TIMESTAMP;
+
+CREATE SEQUENCE public.jobs_id_seq;
+
+CREATE TABLE public.Jobs (
+                Id INTEGER NOT NULL DEFAULT nextval('public.jobs_id_seq'),
+                Kind VARCHAR(50) NOT NULL,
+                Payload TEXT NOT NULL,
+                Status VARCHAR(20) NOT NULL,
+                Attempts INTEGER NOT NULL DEFAULT 0,
+                RunAt TIMESTAMP NOT NULL,
+                LockedUntil TIMESTAMP,
+                LastError TEXT,
+                CreatedAt TIMESTAMP NOT NULL,
+                FinishedAt TIMESTAMP,
+                CONSTRAINT pk_jobs PRIMARY KEY (Id)
+);
+
+
+ALTER SEQUENCE public.jobs_id_seq OWNED BY public.Jobs.Id;
+
+CREATE INDEX ix_jobs_status_runat
+ ON public.Jobs
+ ( Status ASC, RunAt ASC );
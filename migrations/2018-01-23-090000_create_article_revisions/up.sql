@@ -0,0 +1,38 @@
+CREATE SEQUENCE public.articlerevisions_id_seq;
+
+-- every row holds the text an article had after one of its saves, the latest one is the current text
+CREATE TABLE public.ArticleRevisions (
+                Id INTEGER NOT NULL DEFAULT nextval('public.articlerevisions_id_seq'),
+                ArticleId INTEGER NOT NULL,
+                Title VARCHAR(250) NOT NULL,
+                Description VARCHAR(250) NOT NULL,
+                Body TEXT NOT NULL,
+                EditorId INTEGER NOT NULL,
+                RevisedAt TIMESTAMP NOT NULL,
+                CONSTRAINT pk_articlerevisions PRIMARY KEY (Id)
+);
+
+
+ALTER SEQUENCE public.articlerevisions_id_seq OWNED BY public.ArticleRevisions.Id;
+
+CREATE INDEX ix_articlerevisions_articleid
+ ON public.ArticleRevisions
+ ( ArticleId ASC, Id ASC );
+
+ALTER TABLE public.ArticleRevisions ADD CONSTRAINT fk_articlerevisions_articles
+FOREIGN KEY (ArticleId)
+REFERENCES public.Articles (Id)
+ON DELETE CASCADE
+ON UPDATE RESTRICT
+NOT DEFERRABLE;
+
+ALTER TABLE public.ArticleRevisions ADD CONSTRAINT fk_articlerevisions_users
+FOREIGN KEY (EditorId)
+REFERENCES public.Users (Id)
+ON DELETE CASCADE
+ON UPDATE RESTRICT
+NOT DEFERRABLE;
+
+-- existing articles start their history with the text they have now
+INSERT INTO public.ArticleRevisions (ArticleId, Title, Description, Body, EditorId, RevisedAt)
+SELECT Id, Title, Description, Body, Author, COALESCE(UpdatedAt, CreatedAt) FROM public.Articles;
@@ -0,0 +1,24 @@
+CREATE SEQUENCE public.slugaliases_id_seq;
+
+-- slugs an article had before a rename, old links keep leading to it
+CREATE TABLE public.SlugAliases (
+                Id INTEGER NOT NULL DEFAULT nextval('public.slugaliases_id_seq'),
+                ArticleId INTEGER NOT NULL,
+                Slug VARCHAR(250) NOT NULL,
+                CreatedAt TIMESTAMP NOT NULL,
+                CONSTRAINT pk_slugaliases PRIMARY KEY (Id)
+);
+
+
+ALTER SEQUENCE public.slugaliases_id_seq OWNED BY public.SlugAliases.Id;
+
+CREATE UNIQUE INDEX ix_slugaliases_slug
+ ON public.SlugAliases
+ ( Slug ASC );
+
+ALTER TABLE public.SlugAliases ADD CONSTRAINT fk_slugaliases_articles
+FOREIGN KEY (ArticleId)
+REFERENCES public.Articles (Id)
+ON DELETE CASCADE
+ON UPDATE RESTRICT
+NOT DEFERRABLE;
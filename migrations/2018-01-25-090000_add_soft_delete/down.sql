@@ -0,0 +1,9 @@
+-- This file should undo anything in `up.sql`
+
+DELETE FROM public.Comments WHERE DeletedAt IS NOT NULL AND Deleted = FALSE;
+DELETE FROM public.Articles WHERE DeletedAt IS NOT NULL;
+
+ALTER TABLE public.Comments DROP COLUMN DeletedBy;
+ALTER TABLE public.Comments DROP COLUMN DeletedAt;
+ALTER TABLE public.Articles DROP COLUMN DeletedBy;
+ALTER TABLE public.Articles DROP COLUMN DeletedAt;
@@ -0,0 +1,30 @@
+-- IsActive, CreatedBy and UpdatedBy of the first migration only exist on the adempiere.conduit_ tables,
+-- so the public tables get their own columns: a deleted row stays in its author's trash until it is purged
+ALTER TABLE public.Articles ADD COLUMN DeletedAt TIMESTAMP;
+ALTER TABLE public.Articles ADD COLUMN DeletedBy INTEGER;
+ALTER TABLE public.Comments ADD COLUMN DeletedAt TIMESTAMP;
+ALTER TABLE public.Comments ADD COLUMN DeletedBy INTEGER;
+
+ALTER TABLE public.Articles ADD CONSTRAINT fk_articles_deletedby
+FOREIGN KEY (DeletedBy)
+REFERENCES public.Users (Id)
+ON DELETE SET NULL
+ON UPDATE RESTRICT
+NOT DEFERRABLE;
+
+ALTER TABLE public.Comments ADD CONSTRAINT fk_comments_deletedby
+FOREIGN KEY (DeletedBy)
+REFERENCES public.Users (Id)
+ON DELETE SET NULL
+ON UPDATE RESTRICT
+NOT DEFERRABLE;
+
+CREATE INDEX ix_articles_trash
+ ON public.Articles
+ ( Author ASC, DeletedAt ASC )
+ WHERE DeletedAt IS NOT NULL;
+
+CREATE INDEX ix_comments_trash
+ ON public.Comments
+ ( Author ASC, DeletedAt ASC )
+ WHERE DeletedAt IS NOT NULL;
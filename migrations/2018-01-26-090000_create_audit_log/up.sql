@@ -0,0 +1,41 @@
+-- the public tables have no CreatedBy/UpdatedBy, see 2018-01-25-090000_add_soft_delete
+-- one row per write with the acting user and the entity before and after as JSON
+CREATE SEQUENCE public.auditlog_id_seq;
+
+CREATE TABLE public.AuditLog (
+                Id INTEGER NOT NULL DEFAULT nextval('public.auditlog_id_seq'),
+                ActorId INTEGER,
+                Action VARCHAR(20) NOT NULL,
+                EntityType VARCHAR(20) NOT NULL,
+                EntityId INTEGER NOT NULL,
+                Before TEXT,
+                After TEXT,
+                CreatedAt TIMESTAMP NOT NULL,
+                CONSTRAINT pk_auditlog PRIMARY KEY (Id)
+);
+
+
+ALTER SEQUENCE public.auditlog_id_seq OWNED BY public.AuditLog.Id;
+
+-- no foreign keys, the log outlives the users and entities it names
+CREATE INDEX ix_auditlog_actor
+ ON public.AuditLog
+ ( ActorId ASC, CreatedAt ASC );
+
+CREATE INDEX ix_auditlog_entity
+ ON public.AuditLog
+ ( EntityType ASC, EntityId ASC, CreatedAt ASC );
+
+CREATE INDEX ix_auditlog_createdat
+ ON public.AuditLog
+ ( CreatedAt ASC );
+
+CREATE FUNCTION public.auditlog_append_only() RETURNS trigger AS $$
+BEGIN
+    RAISE EXCEPTION 'the audit log is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER tr_auditlog_append_only
+ BEFORE UPDATE OR DELETE ON public.AuditLog
+ FOR EACH ROW EXECUTE PROCEDURE public.auditlog_append_only();
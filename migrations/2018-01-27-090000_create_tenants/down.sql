@@ -0,0 +1,19 @@
+-- This file should undo anything in `up.sql`
+
+-- fails while two tenants share an email or username, those accounts have to be merged first
+DROP INDEX public.ix_articles_tenant;
+DROP INDEX public.ix_users_tenant_username;
+DROP INDEX public.ix_users_tenant_email;
+
+CREATE UNIQUE INDEX ix_email
+ ON public.Users
+ ( Email ASC );
+
+CREATE UNIQUE INDEX ix_username
+ ON public.Users
+ ( UserName ASC );
+
+ALTER TABLE public.Articles DROP COLUMN TenantId;
+ALTER TABLE public.Users DROP COLUMN TenantId;
+
+DROP TABLE public.Tenants;
@@ -0,0 +1,48 @@
+-- the public tables have no ad_client_id/ad_org_id, see 2018-01-25-090000_add_soft_delete
+-- users and articles carry their tenant, everything else belongs to a tenant through them.
+-- Ids are the ones of the [[tenants]] sections in conduit.toml, tenant 1 serves every host nobody configured.
+CREATE TABLE public.Tenants (
+                Id INTEGER NOT NULL,
+                Name VARCHAR(150) NOT NULL,
+                CreatedAt TIMESTAMP NOT NULL,
+                CONSTRAINT pk_tenants PRIMARY KEY (Id)
+);
+
+CREATE UNIQUE INDEX ix_tenants_name
+ ON public.Tenants
+ ( Name ASC );
+
+INSERT INTO public.Tenants (Id, Name, CreatedAt) VALUES (1, 'default', now());
+
+ALTER TABLE public.Users ADD COLUMN TenantId INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE public.Articles ADD COLUMN TenantId INTEGER NOT NULL DEFAULT 1;
+
+ALTER TABLE public.Users ADD CONSTRAINT fk_users_tenants
+FOREIGN KEY (TenantId)
+REFERENCES public.Tenants (Id)
+ON DELETE NO ACTION
+ON UPDATE RESTRICT
+NOT DEFERRABLE;
+
+ALTER TABLE public.Articles ADD CONSTRAINT fk_articles_tenants
+FOREIGN KEY (TenantId)
+REFERENCES public.Tenants (Id)
+ON DELETE NO ACTION
+ON UPDATE RESTRICT
+NOT DEFERRABLE;
+
+-- the same person may sign up to several blogs; slugs stay unique across all of them
+DROP INDEX public.ix_email;
+DROP INDEX public.ix_username;
+
+CREATE UNIQUE INDEX ix_users_tenant_email
+ ON public.Users
+ ( TenantId ASC, Email ASC );
+
+CREATE UNIQUE INDEX ix_users_tenant_username
+ ON public.Users
+ ( TenantId ASC, UserName ASC );
+
+CREATE INDEX ix_articles_tenant
+ ON public.Articles
+ ( TenantId ASC );
@@ -0,0 +1,2 @@
+-- the newest accepted time step, a code of that step or an older one is never taken again
+ALTER TABLE public.TwoFactors ADD COLUMN LastUsedStep BIGINT;
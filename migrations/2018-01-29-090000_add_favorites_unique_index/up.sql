@@ -0,0 +1,8 @@
+-- favoriting twice used to add a second row, keep the oldest one
+DELETE FROM public.FavoritedArticles f
+ USING public.FavoritedArticles older
+ WHERE f.ArticleId = older.ArticleId AND f.UserId = older.UserId AND f.Id > older.Id;
+
+CREATE UNIQUE INDEX ix_favoritedarticles
+ ON public.FavoritedArticles
+ ( ArticleId ASC, UserId ASC );
@@ -0,0 +1,14 @@
+-- This file should undo anything in `up.sql`
+
+ALTER TABLE public.CommentRevisions DROP CONSTRAINT fk_commentrevisions_users;
+
+DELETE FROM public.CommentRevisions WHERE EditorId IS NULL;
+
+ALTER TABLE public.CommentRevisions ALTER COLUMN EditorId SET NOT NULL;
+
+ALTER TABLE public.CommentRevisions ADD CONSTRAINT fk_commentrevisions_users
+FOREIGN KEY (EditorId)
+REFERENCES public.Users (Id)
+ON DELETE CASCADE
+ON UPDATE RESTRICT
+NOT DEFERRABLE;
@@ -0,0 +1,11 @@
+-- deleting an account keeps the revisions it edited, without their editor
+ALTER TABLE public.CommentRevisions ALTER COLUMN EditorId DROP NOT NULL;
+
+ALTER TABLE public.CommentRevisions DROP CONSTRAINT fk_commentrevisions_users;
+
+ALTER TABLE public.CommentRevisions ADD CONSTRAINT fk_commentrevisions_users
+FOREIGN KEY (EditorId)
+REFERENCES public.Users (Id)
+ON DELETE SET NULL
+ON UPDATE RESTRICT
+NOT DEFERRABLE;
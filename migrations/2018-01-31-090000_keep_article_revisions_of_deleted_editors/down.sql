@@ -0,0 +1,14 @@
+-- This file should undo anything in `up.sql`
+
+ALTER TABLE public.ArticleRevisions DROP CONSTRAINT fk_articlerevisions_users;
+
+DELETE FROM public.ArticleRevisions WHERE EditorId IS NULL;
+
+ALTER TABLE public.ArticleRevisions ALTER COLUMN EditorId SET NOT NULL;
+
+ALTER TABLE public.ArticleRevisions ADD CONSTRAINT fk_articlerevisions_users
+FOREIGN KEY (EditorId)
+REFERENCES public.Users (Id)
+ON DELETE CASCADE
+ON UPDATE RESTRICT
+NOT DEFERRABLE;
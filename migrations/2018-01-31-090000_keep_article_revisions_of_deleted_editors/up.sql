@@ -0,0 +1,11 @@
+-- deleting an account keeps the revisions it edited, without their editor
+ALTER TABLE public.ArticleRevisions ALTER COLUMN EditorId DROP NOT NULL;
+
+ALTER TABLE public.ArticleRevisions DROP CONSTRAINT fk_articlerevisions_users;
+
+ALTER TABLE public.ArticleRevisions ADD CONSTRAINT fk_articlerevisions_users
+FOREIGN KEY (EditorId)
+REFERENCES public.Users (Id)
+ON DELETE SET NULL
+ON UPDATE RESTRICT
+NOT DEFERRABLE;
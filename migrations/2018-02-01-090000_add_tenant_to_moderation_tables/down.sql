@@ -0,0 +1,13 @@
+-- This file should undo anything in `up.sql`
+
+UPDATE public.LockoutEvents SET ThrottleKey = substring(ThrottleKey FROM position(':' IN ThrottleKey) + 1) WHERE Scope = 'account';
+DELETE FROM public.LoginThrottles WHERE Scope = 'account' AND ThrottleKey NOT LIKE '1:%';
+UPDATE public.LoginThrottles SET ThrottleKey = substring(ThrottleKey FROM 3) WHERE Scope = 'account';
+
+DROP INDEX public.ix_auditlog_tenant;
+DROP INDEX public.ix_reports_tenant;
+
+ALTER TABLE public.AuditLog DROP COLUMN TenantId;
+ALTER TABLE public.FilterExamples DROP COLUMN TenantId;
+ALTER TABLE public.Webhooks DROP COLUMN TenantId;
+ALTER TABLE public.Reports DROP COLUMN TenantId;
@@ -0,0 +1,52 @@
+-- reports, webhooks, filter examples and audit entries are read by a tenant's own moderators and admins only
+ALTER TABLE public.Reports ADD COLUMN TenantId INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE public.Webhooks ADD COLUMN TenantId INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE public.FilterExamples ADD COLUMN TenantId INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE public.AuditLog ADD COLUMN TenantId INTEGER NOT NULL DEFAULT 1;
+
+UPDATE public.Reports SET TenantId = a.TenantId
+  FROM public.Articles a WHERE a.Id = Reports.ArticleId;
+UPDATE public.Webhooks SET TenantId = u.TenantId
+  FROM public.Users u WHERE u.Id = Webhooks.OwnerId;
+UPDATE public.FilterExamples SET TenantId = r.TenantId
+  FROM public.Reports r WHERE r.Id = FilterExamples.ReportId;
+
+-- the log stays append-only for everybody but this migration; entries of deleted users or jobs keep tenant 1
+ALTER TABLE public.AuditLog DISABLE TRIGGER tr_auditlog_append_only;
+UPDATE public.AuditLog SET TenantId = u.TenantId
+  FROM public.Users u WHERE u.Id = AuditLog.ActorId;
+ALTER TABLE public.AuditLog ENABLE TRIGGER tr_auditlog_append_only;
+
+ALTER TABLE public.Reports ADD CONSTRAINT fk_reports_tenants
+FOREIGN KEY (TenantId)
+REFERENCES public.Tenants (Id)
+ON DELETE NO ACTION
+ON UPDATE RESTRICT
+NOT DEFERRABLE;
+
+ALTER TABLE public.Webhooks ADD CONSTRAINT fk_webhooks_tenants
+FOREIGN KEY (TenantId)
+REFERENCES public.Tenants (Id)
+ON DELETE NO ACTION
+ON UPDATE RESTRICT
+NOT DEFERRABLE;
+
+ALTER TABLE public.FilterExamples ADD CONSTRAINT fk_filterexamples_tenants
+FOREIGN KEY (TenantId)
+REFERENCES public.Tenants (Id)
+ON DELETE NO ACTION
+ON UPDATE RESTRICT
+NOT DEFERRABLE;
+
+-- no foreign key on the log, like its other columns
+CREATE INDEX ix_reports_tenant
+ ON public.Reports
+ ( TenantId ASC, Status ASC, CreatedAt ASC );
+
+CREATE INDEX ix_auditlog_tenant
+ ON public.AuditLog
+ ( TenantId ASC, CreatedAt ASC );
+
+-- account lockouts count per tenant, every email can have an account on every blog
+UPDATE public.LoginThrottles SET ThrottleKey = '1:' || ThrottleKey WHERE Scope = 'account';
+UPDATE public.LockoutEvents SET ThrottleKey = '1:' || ThrottleKey WHERE Scope = 'account';
@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+
+CREATE OR REPLACE FUNCTION public.auditlog_append_only() RETURNS trigger AS $$
+BEGIN
+    RAISE EXCEPTION 'the audit log is append-only';
+END;
+$$ LANGUAGE plpgsql;
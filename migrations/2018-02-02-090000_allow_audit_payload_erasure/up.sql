@@ -0,0 +1,17 @@
+-- entries stay forever, but an account deletion has to be able to erase what they say
+-- blanking Before and After is the only change allowed, who did what and when stays
+CREATE OR REPLACE FUNCTION public.auditlog_append_only() RETURNS trigger AS $$
+BEGIN
+    IF TG_OP = 'UPDATE' AND NEW.Before IS NULL AND NEW.After IS NULL
+        AND NEW.Id = OLD.Id
+        AND NEW.ActorId IS NOT DISTINCT FROM OLD.ActorId
+        AND NEW.Action = OLD.Action
+        AND NEW.EntityType = OLD.EntityType
+        AND NEW.EntityId = OLD.EntityId
+        AND NEW.CreatedAt = OLD.CreatedAt
+        AND NEW.TenantId = OLD.TenantId THEN
+        RETURN NEW;
+    END IF;
+    RAISE EXCEPTION 'the audit log is append-only';
+END;
+$$ LANGUAGE plpgsql;
@@ -0,0 +1,434 @@
+extern crate hyper;
+
+extern crate serde;
+extern crate serde_json;
+
+extern crate chrono;
+
+extern crate crypto;
+
+extern crate reroute;
+
+use hyper::status::StatusCode;
+
+use hyper::server::{Request, Response};
+use reroute::Captures;
+
+use super::*;
+
+/// Name of the shared profile that keeps the content of anonymized accounts.
+pub static DELETED_USER_NAME: &'static str = r#"deleted-user"#;
+static DELETED_USER_EMAIL: &'static str = r#"deleted-user@invalid"#;
+// not a PBKDF2 hash, so no password ever matches it and no real account carries it
+static DELETED_USER_TOKEN: &'static str = r#"!"#;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DeletionPolicy {
+    Anonymize,
+    Delete,
+}
+
+impl DeletionPolicy {
+    pub fn from_name(name: &str) -> Option<DeletionPolicy> {
+        match name {
+            "anonymize" => Some(DeletionPolicy::Anonymize),
+            "delete" => Some(DeletionPolicy::Delete),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            DeletionPolicy::Anonymize => "anonymize",
+            DeletionPolicy::Delete => "delete",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+struct DeleteAccountDetail {
+    password: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+struct DeleteAccount {
+    user: DeleteAccountDetail,
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+pub struct DeletedAccount {
+    pub username: String,
+    pub deleted: bool,
+    pub policy: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+pub struct DeletedAccountResult {
+    pub user: DeletedAccount,
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+pub struct ExportedProfile {
+    pub username: String,
+    pub email: String,
+    pub bio: Option<String>,
+    pub image: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+#[allow(non_snake_case)]
+pub struct ExportedComment {
+    pub id: i32,
+    pub createdAt: NaiveDateTime,
+    pub updatedAt: Option<NaiveDateTime>,
+    pub body: String,
+    pub article: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+pub struct ExportedFollows {
+    pub following: Vec<String>,
+    pub followers: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+#[allow(non_snake_case)]
+pub struct AccountExport {
+    pub profile: ExportedProfile,
+    pub articles: Vec<Article>,
+    pub comments: Vec<ExportedComment>,
+    pub favorites: Vec<String>,
+    pub follows: ExportedFollows,
+    pub exportedAt: NaiveDateTime,
+}
+
+#[cfg(feature = "diesel")]
+fn get_deleted_user(connection: &PgConnection) -> QueryResult<User> {
+    use schema::users;
+
+    // found by the token, a user who took the name must not inherit anybody's content
+    let existing: Option<User> = users::table
+        .filter(users::token.eq(DELETED_USER_TOKEN).and(users::tenantid.eq(current_tenant())))
+        .first(connection)
+        .optional()?;
+
+    match existing {
+        Some(user) => Ok(user),
+        None => {
+            let placeholder = NewUser {
+                email: DELETED_USER_EMAIL,
+                token: DELETED_USER_TOKEN,
+                username: DELETED_USER_NAME,
+                tenantid: current_tenant(),
+            };
+            diesel::insert(&placeholder).into(users::table).get_result(connection)
+        }
+    }
+}
+
+#[cfg(feature = "diesel")]
+fn delete_account(params: (User, DeletionPolicy)) -> Option<DeletedAccountResult> {
+    use schema::{articles, comments, users};
+
+    let (user, policy) = params;
+    let connection = establish_connection();
+
+    // follows, favorites, roles and two-factor settings cascade with the user row
+    connection
+        .transaction::<_, diesel::result::Error, _>(|| {
+            let (deleted_articles, deleted_comments): (Vec<i32>, Vec<i32>) = match policy {
+                DeletionPolicy::Anonymize => {
+                    let deleted_user = get_deleted_user(&connection)?;
+                    diesel::update(articles::table.filter(articles::author.eq(user.id)))
+                        .set(articles::author.eq(deleted_user.id))
+                        .execute(&connection)?;
+                    diesel::update(comments::table.filter(comments::author.eq(user.id)))
+                        .set(comments::author.eq(deleted_user.id))
+                        .execute(&connection)?;
+                    (Vec::new(), Vec::new())
+                }
+                DeletionPolicy::Delete => {
+                    use diesel::expression::dsl::any;
+
+                    let article_ids: Vec<i32> = articles::table
+                        .filter(articles::author.eq(user.id))
+                        .select(articles::id)
+                        .load(&connection)?;
+                    let comment_ids: Vec<i32> = comments::table
+                        .filter(comments::author.eq(user.id).or(comments::articleid.eq(any(&article_ids))))
+                        .select(comments::id)
+                        .load(&connection)?;
+                    diesel::delete(comments::table.filter(comments::author.eq(user.id)))
+                        .execute(&connection)?;
+                    // comments of other users on these articles cascade with them
+                    diesel::delete(articles::table.filter(articles::author.eq(user.id)))
+                        .execute(&connection)?;
+                    (article_ids, comment_ids)
+                }
+            };
+
+            forget_account_throttle(&user.email, &connection)?;
+            diesel::delete(users::table.find(user.id)).execute(&connection)?;
+            // the log keeps that things happened, not what the deletion erases
+            erase_audit_payloads(user.id, &deleted_articles, &deleted_comments, &connection)?;
+            record_audit(Some(user.id), "delete", "user", user.id, None, None, &connection)?;
+            Ok(())
+        })
+        .expect("Error deleting account");
+
+    Some(DeletedAccountResult {
+        user: DeletedAccount {
+            username: user.username,
+            deleted: true,
+            policy: policy.name().to_string(),
+        },
+    })
+}
+
+pub fn delete_account_handler(req: Request, res: Response, _: Captures) {
+    let (body, logged_id) = prepare_parameters(req);
+
+    #[cfg(feature = "diesel")]
+    {
+        if logged_id <= 0 {
+            send_denied(res, Denied::Unauthenticated);
+            return;
+        }
+
+        // a stolen token alone must not be enough to erase an account
+        let confirmation: DeleteAccount = serde_json::from_str(&body).unwrap();
+        let user = get_user_by_id(logged_id).unwrap().user;
+        match crypto::pbkdf2::pbkdf2_check(&confirmation.user.password, &user.token) {
+            Ok(true) => process(res, delete_account, (user, *ACCOUNT_DELETION_POLICY)),
+            _ => send_error(res, StatusCode::Unauthorized, "password is invalid"),
+        }
+    }
+}
+
+#[cfg(feature = "diesel")]
+fn get_account_export(user_id: i32) -> Option<AccountExport> {
+    use schema::{articles, comments, favoritedarticles, followings, users};
+    use diesel::expression::dsl::any;
+
+    let connection = establish_connection();
+    let user: User = users::table.find(user_id).first(&connection).unwrap();
+
+    let authored: Vec<Article> = articles::table
+        .filter(articles::author.eq(user_id))
+        .order(articles::createdat.asc())
+        .load(&connection)
+        .expect("Error loading exported articles");
+
+    let commented: Vec<(Comment, Article)> = comments::table
+        .inner_join(articles::table)
+        .filter(comments::author.eq(user_id))
+        .order(comments::createdat.asc())
+        .load(&connection)
+        .expect("Error loading exported comments");
+
+    let favorites: Vec<String> = favoritedarticles::table
+        .inner_join(articles::table)
+        .filter(favoritedarticles::userid.eq(user_id))
+        .select(articles::slug)
+        .load(&connection)
+        .expect("Error loading exported favorites");
+
+    let following_ids: Vec<i32> = followings::table
+        .filter(followings::followerid.eq(user_id))
+        .select(followings::followingid)
+        .load(&connection)
+        .expect("Error loading exported follows");
+    let follower_ids: Vec<i32> = followings::table
+        .filter(followings::followingid.eq(user_id))
+        .select(followings::followerid)
+        .load(&connection)
+        .expect("Error loading exported followers");
+
+    let following: Vec<String> = users::table
+        .filter(users::id.eq(any(&following_ids)))
+        .select(users::username)
+        .load(&connection)
+        .expect("Error loading exported follows");
+    let followers: Vec<String> = users::table
+        .filter(users::id.eq(any(&follower_ids)))
+        .select(users::username)
+        .load(&connection)
+        .expect("Error loading exported followers");
+
+    Some(AccountExport {
+        profile: ExportedProfile {
+            username: user.username,
+            email: user.email,
+            bio: user.bio,
+            image: user.image,
+        },
+        articles: authored,
+        comments: commented
+            .into_iter()
+            .map(|(comment, article)| {
+                ExportedComment {
+                    id: comment.id,
+                    createdAt: comment.createdAt,
+                    updatedAt: comment.updatedAt,
+                    body: comment.body,
+                    article: article.slug,
+                }
+            })
+            .collect(),
+        favorites: favorites,
+        follows: ExportedFollows {
+            following: following,
+            followers: followers,
+        },
+        exportedAt: Utc::now().naive_utc(),
+    })
+}
+
+fn export_archive(export: &AccountExport) -> Vec<u8> {
+    let profile = serde_json::to_vec_pretty(&export.profile).unwrap();
+    let articles = serde_json::to_vec_pretty(&export.articles).unwrap();
+    let comments = serde_json::to_vec_pretty(&export.comments).unwrap();
+    let favorites = serde_json::to_vec_pretty(&export.favorites).unwrap();
+    let follows = serde_json::to_vec_pretty(&export.follows).unwrap();
+
+    archive::zip_stored(&[
+        ("profile.json", &profile[..]),
+        ("articles.json", &articles[..]),
+        ("comments.json", &comments[..]),
+        ("favorites.json", &favorites[..]),
+        ("follows.json", &follows[..]),
+    ])
+}
+
+fn set_attachment_header(res: &mut Response, file_name: &str) {
+    res.headers_mut().set_raw(
+        "Content-Disposition",
+        vec![format!(r#"attachment; filename="{}""#, file_name).into_bytes()],
+    );
+}
+
+pub fn export_account_handler(req: Request, mut res: Response, c: Captures) {
+    let (_, logged_id) = prepare_parameters(req);
+
+    let caps = c.unwrap();
+    let format = get_query_param(&caps[0], "format").unwrap_or("json").to_string();
+
+    #[cfg(feature = "diesel")]
+    {
+        if logged_id <= 0 {
+            send_denied(res, Denied::Unauthenticated);
+            return;
+        }
+
+        match format.as_ref() {
+            "json" => {
+                set_attachment_header(&mut res, "conduit-export.json");
+                process(res, get_account_export, logged_id)
+            }
+            "zip" => {
+                let export = get_account_export(logged_id).unwrap();
+                let archive = export_archive(&export);
+
+                set_attachment_header(&mut res, "conduit-export.zip");
+                res.headers_mut().set(AccessControlAllowOrigin::Any);
+                res.headers_mut().set(ContentType(Mime(
+                    TopLevel::Application,
+                    SubLevel::Ext("zip".to_owned()),
+                    vec![],
+                )));
+                res.send(&archive).unwrap();
+            }
+            _ => send_error(res, StatusCode::UnprocessableEntity, "format must be json or zip"),
+        }
+    }
+}
+
+#[cfg(test)]
+fn delete_account_request(jwt: &str, password: &str) -> (hyper::status::StatusCode, String) {
+    let client = Client::new();
+    let body = format!(r#"{{"user":{{"password": "{}"}}}}"#, password);
+
+    let mut res = client
+        .delete("http://localhost:6767/api/user")
+        .header(Authorization(Bearer { token: jwt.to_owned() }))
+        .body(&body)
+        .send()
+        .unwrap();
+    let mut buffer = String::new();
+    res.read_to_string(&mut buffer).unwrap();
+    (res.status, buffer)
+}
+
+#[cfg(test)]
+#[test]
+fn delete_account_test() {
+    let client = Client::new();
+    let (jwt, slug, user_name) = login_create_article(false);
+    let user_id = get_user_by_name(&user_name).unwrap().id;
+
+    let (status, _) = delete_account_request(&jwt, "not-jakes-password");
+    assert_eq!(status, StatusCode::Unauthorized);
+
+    let (status, buffer) = delete_account_request(&jwt, JACOB_PASSWORD);
+    assert_eq!(status, hyper::Ok);
+    let deleted: DeletedAccountResult = serde_json::from_str(&buffer).unwrap();
+    assert_eq!(deleted.user.username, user_name);
+    assert!(get_user_by_name(&user_name).is_none());
+
+    {
+        use schema::auditlog::dsl::*;
+
+        // the entries stay, what they said about the account is gone
+        let payloads: Vec<(Option<String>, Option<String>)> = auditlog
+            .filter(actorid.eq(user_id))
+            .select((before, after))
+            .load(&establish_connection())
+            .unwrap();
+        assert!(payloads.len() > 1);
+        assert!(payloads.iter().all(|payload| payload.0.is_none() && payload.1.is_none()));
+    }
+
+    // the sample configuration anonymizes, so the article outlives its author
+    let url = format!("http://localhost:6767/api/articles/{}", slug);
+    let res = client.get(&url).send().unwrap();
+    assert_eq!(res.status, hyper::Ok);
+}
+
+#[cfg(test)]
+#[test]
+fn export_account_test() {
+    let client = Client::new();
+    let (jwt, slug, user_name) = login_create_article(false);
+
+    let mut res = client
+        .get("http://localhost:6767/api/user/export")
+        .header(Authorization(Bearer { token: jwt.to_owned() }))
+        .send()
+        .unwrap();
+    let mut buffer = String::new();
+    res.read_to_string(&mut buffer).unwrap();
+    assert_eq!(res.status, hyper::Ok);
+
+    let export: AccountExport = serde_json::from_str(&buffer).unwrap();
+    assert_eq!(export.profile.username, user_name);
+    assert_eq!(export.articles.len(), 1);
+    assert_eq!(export.articles[0].slug, slug);
+
+    let mut res = client
+        .get("http://localhost:6767/api/user/export?format=zip")
+        .header(Authorization(Bearer { token: jwt }))
+        .send()
+        .unwrap();
+    let mut archive = Vec::new();
+    res.read_to_end(&mut archive).unwrap();
+    assert_eq!(res.status, hyper::Ok);
+    assert_eq!(&archive[..4], b"PK\x03\x04");
+}
@@ -0,0 +1,158 @@
+extern crate hyper;
+
+extern crate serde;
+extern crate serde_json;
+
+extern crate reroute;
+
+use hyper::status::StatusCode;
+
+use hyper::server::{Request, Response};
+use reroute::Captures;
+
+use super::*;
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+struct UpdateRole {
+    role: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+pub struct UserRoleDetail {
+    pub username: String,
+    pub role: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+pub struct UserRoleResult {
+    pub user: UserRoleDetail,
+}
+
+#[cfg(feature = "diesel")]
+fn set_user_role(params: (User, Role, i32)) -> Option<UserRoleResult> {
+    let (user, new_role, granted_by) = params;
+
+    grant_role(user.id, new_role, Some(granted_by));
+
+    Some(UserRoleResult {
+        user: UserRoleDetail {
+            username: user.username,
+            role: role_of(user.id).name().to_string(),
+        },
+    })
+}
+
+pub fn update_user_role_handler(req: Request, res: Response, c: Captures) {
+    let (body, logged_id) = prepare_parameters(req);
+
+    let caps = c.unwrap();
+    let user_name = &caps[0].replace("/api/admin/users/", "").replace("/role", "");
+    println!("update_user_role_handler user_name: '{}'", user_name);
+
+    #[cfg(feature = "diesel")]
+    {
+        if let Err(denied) = check(logged_id, Action::ManageUsers, None) {
+            send_denied(res, denied);
+            return;
+        }
+
+        let incoming: UpdateRole = serde_json::from_str(&body).unwrap();
+        let new_role = match Role::from_name(&incoming.role) {
+            Some(new_role) => new_role,
+            None => {
+                send_error(res, StatusCode::UnprocessableEntity, "unknown role");
+                return;
+            }
+        };
+        let user = match get_user_by_name(user_name) {
+            Some(user) => user,
+            None => {
+                send_error(res, StatusCode::NotFound, "user not found");
+                return;
+            }
+        };
+        // keeps the last admin from locking everybody out by accident
+        if user.id == logged_id {
+            send_error(res, StatusCode::UnprocessableEntity, "you cannot change your own role");
+            return;
+        }
+
+        process(res, set_user_role, (user, new_role, logged_id))
+    }
+}
+
+#[cfg(test)]
+pub fn register_admin() -> (std::string::String, std::string::String) {
+    let (user_name, email) = register_jacob();
+    let user = get_user_by_name(&user_name).unwrap();
+    grant_role(user.id, Role::Admin, None);
+
+    let jwt = login_jacob(email, JACOB_PASSWORD.to_string());
+    (user_name, jwt)
+}
+
+#[cfg(test)]
+fn put_role(user_name: &str, jwt: &str, role: &str) -> (hyper::status::StatusCode, String) {
+    let client = Client::new();
+    let url = format!("http://localhost:6767/api/admin/users/{}/role", user_name);
+    let body = format!(r#"{{"role": "{}"}}"#, role);
+
+    let mut res = client
+        .put(&url)
+        .header(Authorization(Bearer { token: jwt.to_owned() }))
+        .body(&body)
+        .send()
+        .unwrap();
+    let mut buffer = String::new();
+    res.read_to_string(&mut buffer).unwrap();
+    (res.status, buffer)
+}
+
+#[cfg(test)]
+#[test]
+fn admin_grants_moderator_test() {
+    let (_, admin_jwt) = register_admin();
+    let (user_name, _) = register_jacob();
+
+    let (status, buffer) = put_role(&user_name, &admin_jwt, "moderator");
+    assert_eq!(status, hyper::Ok);
+
+    let result: UserRoleResult = serde_json::from_str(&buffer).unwrap();
+    assert_eq!(result.user.username, user_name);
+    assert_eq!(result.user.role, "moderator");
+}
+
+#[cfg(test)]
+#[test]
+fn user_cannot_grant_roles_test() {
+    let (_, email) = register_jacob();
+    let jwt = login_jacob(email, JACOB_PASSWORD.to_string());
+    let (user_name, _) = register_jacob();
+
+    let (status, _) = put_role(&user_name, &jwt, "admin");
+    assert_eq!(status, StatusCode::Forbidden);
+}
+
+#[cfg(test)]
+#[test]
+fn moderator_deletes_foreign_article_test() {
+    let client = Client::new();
+    let (_, slug, _) = login_create_article(false);
+
+    let (moderator_name, moderator_email) = register_jacob();
+    let moderator = get_user_by_name(&moderator_name).unwrap();
+    grant_role(moderator.id, Role::Moderator, None);
+    let moderator_jwt = login_jacob(moderator_email, JACOB_PASSWORD.to_string());
+
+    let url = format!("http://localhost:6767/api/articles/{}", slug);
+    let res = client
+        .delete(&url)
+        .header(Authorization(Bearer { token: moderator_jwt }))
+        .body("")
+        .send()
+        .unwrap();
+    assert_eq!(res.status, hyper::Ok);
+}
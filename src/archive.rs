@@ -0,0 +1,102 @@
+// Minimal ZIP writer for data exports: entries are stored uncompressed, which every unzip tool reads.
+
+// 1980-01-01, the earliest date a ZIP entry can carry
+const DOS_DATE: u16 = 0x0021;
+const DOS_TIME: u16 = 0;
+
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffffffffu32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (!(crc & 1)).wrapping_add(1);
+            crc = (crc >> 1) ^ (0xedb88320 & mask);
+        }
+    }
+    !crc
+}
+
+fn push_u16(buffer: &mut Vec<u8>, value: u16) {
+    buffer.push(value as u8);
+    buffer.push((value >> 8) as u8);
+}
+
+fn push_u32(buffer: &mut Vec<u8>, value: u32) {
+    push_u16(buffer, value as u16);
+    push_u16(buffer, (value >> 16) as u16);
+}
+
+pub fn zip_stored(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut archive: Vec<u8> = Vec::new();
+    let mut central_directory: Vec<u8> = Vec::new();
+
+    for &(name, data) in files {
+        let offset = archive.len() as u32;
+        let crc = crc32(data);
+
+        push_u32(&mut archive, 0x04034b50);
+        push_u16(&mut archive, 20);
+        push_u16(&mut archive, 0);
+        push_u16(&mut archive, 0);
+        push_u16(&mut archive, DOS_TIME);
+        push_u16(&mut archive, DOS_DATE);
+        push_u32(&mut archive, crc);
+        push_u32(&mut archive, data.len() as u32);
+        push_u32(&mut archive, data.len() as u32);
+        push_u16(&mut archive, name.len() as u16);
+        push_u16(&mut archive, 0);
+        archive.extend_from_slice(name.as_bytes());
+        archive.extend_from_slice(data);
+
+        push_u32(&mut central_directory, 0x02014b50);
+        push_u16(&mut central_directory, 20);
+        push_u16(&mut central_directory, 20);
+        push_u16(&mut central_directory, 0);
+        push_u16(&mut central_directory, 0);
+        push_u16(&mut central_directory, DOS_TIME);
+        push_u16(&mut central_directory, DOS_DATE);
+        push_u32(&mut central_directory, crc);
+        push_u32(&mut central_directory, data.len() as u32);
+        push_u32(&mut central_directory, data.len() as u32);
+        push_u16(&mut central_directory, name.len() as u16);
+        push_u16(&mut central_directory, 0);
+        push_u16(&mut central_directory, 0);
+        push_u16(&mut central_directory, 0);
+        push_u16(&mut central_directory, 0);
+        push_u32(&mut central_directory, 0);
+        push_u32(&mut central_directory, offset);
+        central_directory.extend_from_slice(name.as_bytes());
+    }
+
+    let central_directory_offset = archive.len() as u32;
+    archive.extend_from_slice(&central_directory);
+
+    push_u32(&mut archive, 0x06054b50);
+    push_u16(&mut archive, 0);
+    push_u16(&mut archive, 0);
+    push_u16(&mut archive, files.len() as u16);
+    push_u16(&mut archive, files.len() as u16);
+    push_u32(&mut archive, central_directory.len() as u32);
+    push_u32(&mut archive, central_directory_offset);
+    push_u16(&mut archive, 0);
+
+    archive
+}
+
+#[cfg(test)]
+#[test]
+fn crc32_test() {
+    assert_eq!(crc32(b"123456789"), 0xcbf43926);
+    assert_eq!(crc32(b""), 0);
+}
+
+#[cfg(test)]
+#[test]
+fn zip_stored_test() {
+    let archive = zip_stored(&[("profile.json", b"{}"), ("articles.json", b"[]")]);
+
+    assert_eq!(&archive[..4], b"PK\x03\x04");
+    let end = archive.len() - 22;
+    assert_eq!(&archive[end..end + 4], b"PK\x05\x06");
+    assert_eq!(archive[end + 10], 2);
+}
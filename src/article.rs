@@ -26,6 +26,16 @@ extern crate futures_state_stream;
 
 extern crate slug;
 
+#[cfg(feature = "tiberius")]
+use futures::Future;
+#[cfg(feature = "tiberius")]
+use tokio_core::reactor::Core;
+
+#[cfg(feature = "tiberius")]
+use tiberius::SqlConnection;
+#[cfg(feature = "tiberius")]
+use tiberius::stmt::ResultStreamExt;
+
 use hyper::server::{Request, Response};
 use reroute::Captures;
 
@@ -37,7 +47,7 @@ use super::*;
 #[derive(Debug)]
 #[allow(non_snake_case)]
 pub struct ArticlesResult {
-    articles: Vec<Article>,
+    pub articles: Vec<Article>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -60,14 +70,73 @@ impl Container<Article> for ArticlesResult {
     }
 }
 
+/// Only published articles are listed; unlisted and archived ones open by link, drafts only for their author.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArticleStatus {
+    Draft,
+    Published,
+    Unlisted,
+    Archived,
+}
+
+impl ArticleStatus {
+    pub fn from_name(name: &str) -> Option<ArticleStatus> {
+        match name {
+            "draft" => Some(ArticleStatus::Draft),
+            "published" => Some(ArticleStatus::Published),
+            "unlisted" => Some(ArticleStatus::Unlisted),
+            "archived" => Some(ArticleStatus::Archived),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            ArticleStatus::Draft => "draft",
+            ArticleStatus::Published => "published",
+            ArticleStatus::Unlisted => "unlisted",
+            ArticleStatus::Archived => "archived",
+        }
+    }
+}
+
+static UNKNOWN_STATUS: &'static str = "status must be one of: draft, published, unlisted, archived";
+
+const MAX_OWN_ARTICLES_LIMIT: i64 = 100;
+
+/// Only drafts can wait for a `publishAt`, and only for a time still to come.
+fn check_schedule(status: ArticleStatus, publish_at: Option<NaiveDateTime>) -> Result<(), &'static str> {
+    match publish_at {
+        Some(_) if status != ArticleStatus::Draft => Err("only drafts can be scheduled"),
+        Some(publish_at) if publish_at <= Utc::now().naive_utc() => Err("publishAt must be in the future"),
+        _ => Ok(()),
+    }
+}
+
+pub fn is_listed(article: &Article) -> bool {
+    article.status == ArticleStatus::Published.name() && article.deletedAt.is_none() &&
+        article.tenantid == current_tenant()
+}
+
 pub fn get_tag_names<'a>(_a: &str) -> Option<TagsResult> {
         use models::Tag;
-        use schema::tags;
+        use schema::{articles, articletags, tags};
+        use diesel::expression::dsl::any;
 
         let conn = establish_connection();
+
+        // only the tags the tenant's own articles use
+        let tag_ids: Vec<i32> = articletags::table
+            .inner_join(articles::table)
+            .filter(articles::tenantid.eq(current_tenant()))
+            .select(articletags::tagid)
+            .distinct()
+            .load(&conn)
+            .expect("Error loading tags");
         
         let tags_result = 
             tags::table
+            .filter(tags::id.eq(any(&tag_ids)))
             .load::<Tag>(&conn)
             .expect(
                 "Error loading tags",
@@ -178,9 +247,10 @@ pub fn create_article_tag<'a>(new_article : AdvancedArticle) {
 }
 
 #[cfg(feature = "diesel")]
-pub fn create_article<'a>(mut article: AdvancedArticle) -> Option<ArticleResult> {
+pub fn create_article<'a>(params: (AdvancedArticle, Option<String>)) -> Option<ArticleResult> {
     use schema::articles;
 
+    let (mut article, held_because) = params;
     //let new_article = new_article.article;
     let connection = establish_connection();
 
@@ -192,23 +262,52 @@ pub fn create_article<'a>(mut article: AdvancedArticle) -> Option<ArticleResult>
         body: &cloned_article.body,
         createdat: cloned_article.createdAt,
         updatedat: cloned_article.updatedAt,
-        author: article.author
+        author: article.author,
+        hidden: held_because.is_some(),
+        status: &cloned_article.status,
+        publishedat: cloned_article.publishedAt,
+        publishat: cloned_article.publishAt,
+        tenantid: current_tenant(),
     };
 
     let article_result: Article = diesel::insert(&new_article)
         .into(articles::table)
         .get_result(&connection)
         .expect("Error saving new post");    
+    record_revision(&article_result, article_result.author, &connection).expect("Error saving article revision");
+    record_audit(Some(article_result.author), "create", "article", article_result.id, None, audit_value(&article_result), &connection)
+        .expect("Error recording audit entry");
 
     article.id = article_result.id;
-    
     let result = article.clone();
     create_article_tag(article);
-    
-    Some(ArticleResult { article: result,} )
+    let result = ArticleResult { article: result,};
+
+    if let Some(publish_at) = result.article.publishAt {
+        schedule_publication(result.article.id, publish_at, &connection);
+    }
+
+    // no mentions, events or webhooks while the article is not public
+    if let Some(reason) = held_because {
+        hold_for_review(result.article.id, None, &reason);
+        return Some(result);
+    }
+
+    if result.article.publishedAt.is_some() {
+        announce_article(&result, "article.created");
+    }
+    Some(result)
 }
 
-pub fn create_article_handler(req: Request, res: Response, _: Captures) {
+/// Tells mentioned users, followers and webhooks about an article that just went public.
+#[cfg(feature = "diesel")]
+fn announce_article(result: &ArticleResult, event: &'static str) {
+    notify_mentions(&result.article.body, result.article.author, Some(result.article.id), None, &[]);
+    publish("article", StreamTopic::Article { author_id: result.article.author }, result);
+    dispatch_webhooks(event, result.article.author, result);
+}
+
+pub fn create_article_handler(req: Request, mut res: Response, _: Captures) {
     let (body, logged_in_user_id) = prepare_parameters(req);
 
     let container: IncomingArticleResult = serde_json::from_str(&body).unwrap();
@@ -217,12 +316,51 @@ pub fn create_article_handler(req: Request, res: Response, _: Captures) {
     let description: String = incoming_article.description;
     let article_body: String = incoming_article.body;
     let tag_list: Vec<String> = incoming_article.tagList.unwrap_or(Vec::new());
+    let status_name: Option<String> = incoming_article.status;
+    let publish_at: Option<NaiveDateTime> = incoming_article.publishAt;
+    #[cfg(feature = "diesel")]
+    let slug: String = unique_slug(&title, None);
+    #[cfg(feature = "tiberius")]
     let slug: String = slugify(&title);
     //let tags: &str = &tag_list.join(",");
 
     #[cfg(feature = "diesel")]
     {
         use chrono::prelude::*;
+
+        if let Err(denied) = check(logged_in_user_id, Action::CreateArticle, None) {
+            send_denied(res, denied);
+            return;
+        }
+        // articles are published right away unless asked otherwise, scheduled ones are drafts until their time
+        let status = match status_name.as_ref().map(|name| ArticleStatus::from_name(name)) {
+            None if publish_at.is_some() => ArticleStatus::Draft,
+            None => ArticleStatus::Published,
+            Some(Some(status)) => status,
+            Some(None) => {
+                send_error(res, StatusCode::UnprocessableEntity, UNKNOWN_STATUS);
+                return;
+            }
+        };
+        if let Err(message) = check_schedule(status, publish_at) {
+            send_error(res, StatusCode::UnprocessableEntity, message);
+            return;
+        }
+
+        let text = article_text(&title, &description, &article_body);
+        let submission = Submission { author: logged_in_user_id, kind: ContentKind::Article, text: &text, edited: None };
+        let held_because = match run_filters(&content_filters(), &submission) {
+            Verdict::Allow => None,
+            Verdict::Hold(reason) => Some(reason),
+            Verdict::Reject(reason) => {
+                send_error(res, StatusCode::UnprocessableEntity, &reason);
+                return;
+            }
+        };
+        if held_because.is_some() {
+            *res.status_mut() = StatusCode::Accepted;
+        }
+
         let utc: DateTime<Utc> = Utc::now();
 
         let article = AdvancedArticle {
@@ -237,8 +375,13 @@ pub fn create_article_handler(req: Request, res: Response, _: Captures) {
             tagList: tag_list,
             favorited: false,
             favoritesCount: 0,
+            commentsCount: 0,
+            pinned: false,
+            status: status.name().to_string(),
+            publishedAt: if status == ArticleStatus::Published { Some(utc.naive_utc()) } else { None },
+            publishAt: publish_at,
         };
-        process(res, create_article, article );
+        process(res, create_article, (article, held_because));
     }
 
     #[cfg(feature = "tiberius")]
@@ -294,19 +437,29 @@ fn unfavorite_article<'a>(article_id: i32, user_id: i32) -> Option<bool> {
         .unwrap();
 
     diesel::delete(favoritedarticles.filter(id.eq(relationship.id))).execute(&connection).expect("Failed to unfavorite article");
+    record_audit(Some(user_id), "delete", "favorite", relationship.id, audit_value(&relationship), None, &connection)
+        .expect("Error recording audit entry");
     None
 }
 
 #[cfg(feature = "diesel")]
-fn favorite_article<'a>(new_relationship: NewArticleUser) {  
+fn favorite_article<'a>(new_relationship: NewArticleUser) -> bool {  
+    use diesel::result::{DatabaseErrorKind, Error};
+
     let connection = establish_connection();
 
     use schema::favoritedarticles;
 
-    let _relationship: ArticleUser = diesel::insert(&new_relationship)
-    .into(favoritedarticles::table)
-    .get_result(&connection)
-    .expect("Error saving new favorited article relationship");    
+    // favoriting twice is a no-op, the ix_favoritedarticles unique index keeps a single row
+    match diesel::insert(&new_relationship).into(favoritedarticles::table).get_result::<ArticleUser>(&connection) {
+        Ok(relationship) => {
+            record_audit(Some(relationship.userid), "create", "favorite", relationship.id, None, audit_value(&relationship), &connection)
+                .expect("Error recording audit entry");
+            true
+        }
+        Err(Error::DatabaseError(DatabaseErrorKind::UniqueViolation, _)) => false,
+        Err(e) => panic!("Error saving new favorited article relationship: {}", e),
+    }
 }
 
 #[cfg(feature = "diesel")]
@@ -332,12 +485,25 @@ pub fn favorite_article_handler(req: Request, res: Response, c: Captures) {
             "/favorite","",
         );
 
-        let article = get_advanced_article(url_slug);
+        if let Err(denied) = check(logged_in_user_id, Action::FavoriteArticle, None) {
+            send_denied(res, denied);
+            return;
+        }
+
+        let article = match find_visible_article(url_slug, logged_in_user_id) {
+            Some(article) => article,
+            None => {
+                send_error(res, StatusCode::NotFound, "article not found");
+                return;
+            }
+        };
         let new_relationship = NewArticleUser {
             userid : logged_in_user_id,
-            articleid : article.unwrap().article.id,
+            articleid : article.id,
     }   ;
-        favorite_article(new_relationship);
+        if favorite_article(new_relationship) {
+            notify(article.author, logged_in_user_id, NotificationKind::Favorite, Some(article.id), None);
+        }
         process(res, get_advanced_article, url_slug );
     };
 
@@ -364,9 +530,21 @@ pub fn unfavorite_article_handler(req: Request, res: Response, c: Captures) {
             "/favorite","",
         );
 
-        let article: ArticleResult = get_advanced_article(url_slug).unwrap();
+        if let Err(denied) = check(logged_in_user_id, Action::FavoriteArticle, None) {
+            send_denied(res, denied);
+            return;
+        }
+
+        // taking a favorite back works on any article still around
+        let article = match find_article(url_slug) {
+            Some(article) => article,
+            None => {
+                send_error(res, StatusCode::NotFound, "article not found");
+                return;
+            }
+        };
 
-        unfavorite_article(article.article.id, logged_in_user_id);
+        unfavorite_article(article.id, logged_in_user_id);
         process(res, get_advanced_article, url_slug);
     };
 
@@ -397,6 +575,7 @@ pub fn feed_handler(req: Request, res: Response, c: Captures) {
 
     let mut limit: i32 = 20;
     let mut offset: i32 = 0;
+    let mut mode = "";
 
     for param in &parsed_params {
         let name_value: Vec<&str> = param.split('=').collect();
@@ -405,6 +584,8 @@ pub fn feed_handler(req: Request, res: Response, c: Captures) {
             offset = name_value[1].parse::<i32>().unwrap();
         } else if name_value[0] == "limit" {
             limit = name_value[1].parse::<i32>().unwrap();
+        } else if name_value[0] == "mode" {
+            mode = name_value[1];
         };
     }
 
@@ -415,8 +596,14 @@ pub fn feed_handler(req: Request, res: Response, c: Captures) {
             favorited: "",
             offset: offset,
             limit: limit,
+            viewer: logged_id,
         };
-        process_container(res, articles_result, get_articles_feed_by_filter, filter);
+        // "combined" adds followed tags to followed authors and says why each article is there
+        if mode == "combined" {
+            process_container(res, feed_articles_result, get_combined_feed, filter);
+        } else {
+            process_container(res, articles_result, get_articles_feed_by_filter, filter);
+        }
     }
 
     #[cfg(feature = "tiberius")]
@@ -445,6 +632,7 @@ pub struct FilterParams<'a> {
     pub favorited: &'a str,
     pub offset: i32,
     pub limit: i32,
+    pub viewer: i32,
 }
 
 fn get_articles_feed_by_filter(params: FilterParams) -> Vec<Article> {
@@ -483,6 +671,10 @@ fn get_articles_feed_by_filter(params: FilterParams) -> Vec<Article> {
         // .into_iter()
         // .map(|v| v.clone())
         // .collect::<Vec<Article>>();
+
+    let hidden = hidden_authors(params.viewer);
+    let mut result = result;
+    result.retain(|a| is_listed(a) && !a.hidden && !hidden.contains(&a.author));
     result
 }
 
@@ -493,6 +685,7 @@ fn get_articles_by_filter(params: FilterParams) -> Vec<Article> {
     use schema::tags;
     use schema::articletags;
     use schema::favoritedarticles;
+    use schema::pinnedarticles;
     use std::collections::HashSet;
 
     let connection = establish_connection();    
@@ -519,10 +712,15 @@ fn get_articles_by_filter(params: FilterParams) -> Vec<Article> {
     }
 
     if params.author != "" {
-        let current_author = users::table
-                    .filter(users::username.eq(params.author))
+        let current_author = match users::table
+                    .filter(users::username.eq(params.author).and(users::tenantid.eq(current_tenant())))
                     .first::<User>(&connection)
-                    .expect("Error loading author for articles");
+                    .optional()
+                    .expect("Error loading author for articles") {
+            Some(author) => author,
+            // nobody by that name writes here
+            None => return Vec::new(),
+        };
 
         with_author = Article::belonging_to(&current_author)
                     .load::<Article>(&connection)
@@ -538,7 +736,7 @@ fn get_articles_by_filter(params: FilterParams) -> Vec<Article> {
     if params.favorited != "" {
         with_favorited_by = favoritedarticles::table
             .inner_join(users::table)
-            .filter(users::username.eq(params.favorited))
+            .filter(users::username.eq(params.favorited).and(users::tenantid.eq(current_tenant())))
             .select(favoritedarticles::articleid)
             .load::<i32>(&connection)
             .expect("Error loading articles with favorited by")
@@ -606,11 +804,23 @@ fn get_articles_by_filter(params: FilterParams) -> Vec<Article> {
                                 .into_iter()
                                 .collect();
 
+    // pinned articles lead the list, the rest keep their order
+    let pinned_ids: HashSet<i32> = pinnedarticles::table
+        .select(pinnedarticles::articleid)
+        .load::<i32>(&connection)
+        .expect("Error loading pinned articles")
+        .into_iter()
+        .collect();
+    let hidden = hidden_authors(params.viewer);
+    let mut result = result;
+    result.retain(|a| is_listed(a) && !a.hidden && !hidden.contains(&a.author));
+    result.sort_by_key(|a| !pinned_ids.contains(&a.id));
+
     result
 }
 
 pub fn list_article_handler(req: Request, res: Response, c: Captures) {
-    let (_, _) = prepare_parameters(req);
+    let (_, logged_id) = prepare_parameters(req);
 
     let caps = c.unwrap();
     let url_params = &caps[0].replace("/api/articles?", "");
@@ -648,6 +858,7 @@ pub fn list_article_handler(req: Request, res: Response, c: Captures) {
         favorited: favorited,
         offset: offset,
         limit: limit,
+        viewer: logged_id,
     };
 
     #[cfg(feature = "diesel")]
@@ -714,22 +925,51 @@ fn get_tags_for_article(article: &Article, conn: PgConnection) -> Vec<String> {
     tag_objs.into_iter().map(|t| t.tag).collect()
 }
 
-pub fn get_article(url_slug: &str) -> Article {
+/// The article with `url_slug`, or the one that had it before a rename.
+pub fn find_article(url_slug: &str) -> Option<Article> {
     use schema::articles::dsl::*;
     let connection = establish_connection();
 
-    let result: Article = articles
-        .filter(slug.eq(url_slug))
+    // trashed articles are gone for everyone but the trash
+    let article = articles
+        .filter(slug.eq(url_slug).and(deletedat.is_null()).and(tenantid.eq(current_tenant())))
         .first(&connection)
+        .optional()
         .unwrap();
+    match article {
+        Some(article) => Some(article),
+        None => find_aliased_article_id(url_slug).and_then(|article_id| {
+            articles
+                .find(article_id)
+                .filter(deletedat.is_null().and(tenantid.eq(current_tenant())))
+                .first(&connection)
+                .optional()
+                .unwrap()
+        }),
+    }
+}
 
-    result
+/// To a blocked reader the blocker's articles do not exist, reported ones wait for a moderator and drafts are their
+/// author's alone.
+#[cfg(feature = "diesel")]
+pub fn is_article_visible(article: &Article, viewer_id: i32) -> bool {
+    !is_blocked(article.author, viewer_id) && is_visible(article, viewer_id) &&
+        (article.status != ArticleStatus::Draft.name() || article.author == viewer_id)
+}
+
+/// `find_article`, but only when `viewer_id` may see the article; everything else answers 404.
+#[cfg(feature = "diesel")]
+pub fn find_visible_article(url_slug: &str, viewer_id: i32) -> Option<Article> {
+    match find_article(url_slug) {
+        Some(article) => if is_article_visible(&article, viewer_id) { Some(article) } else { None },
+        None => None,
+    }
 }
 
 pub fn get_advanced_article(url_slug: &str) -> Option<ArticleResult> {
     let connection = establish_connection();
 
-    let article = get_article(url_slug);
+    let article = find_article(url_slug)?;
 
     let tag_names = get_tags_for_article(&article, connection);
     let favorites_count = get_favorites_count(article.id);
@@ -746,17 +986,37 @@ pub fn get_advanced_article(url_slug: &str) -> Option<ArticleResult> {
         author : article.author,
         favoritesCount: favorites_count,
         favorited: favorites_count > 0,
+        commentsCount: count_comments(article.id),
+        pinned: is_pinned(article.id),
+        status: article.status,
+        publishedAt: article.publishedAt,
+        publishAt: article.publishAt,
     };
 
     Some(ArticleResult { article: result,})
 }
 
 pub fn get_article_handler(req: Request, res: Response, c: Captures) {
-    let (_, _) = prepare_parameters(req);
+    let (_, logged_id) = prepare_parameters(req);
     let caps = c.unwrap();
     let url_slug = &caps[0].replace("/api/articles/", "");
 
-    #[cfg(feature = "diesel")] process(res, get_advanced_article, (url_slug));
+    #[cfg(feature = "diesel")] {
+        match find_visible_article(url_slug, logged_id) {
+            Some(ref article) => {
+                // an old slug from before a rename
+                if article.slug != *url_slug {
+                    send_redirect(res, &format!("/api/articles/{}", article.slug));
+                    return;
+                }
+            }
+            None => {
+                send_error(res, StatusCode::NotFound, "article not found");
+                return;
+            }
+        }
+        process(res, get_advanced_article, (url_slug));
+    }
 
     #[cfg(feature = "tiberius")]
     process_and_return_article(
@@ -770,16 +1030,72 @@ pub fn get_article_handler(req: Request, res: Response, c: Captures) {
 }
 
 #[cfg(feature = "diesel")]
-pub fn update_article<'a>(new_article: UpdatedArticle) -> Option<ArticleResult> {
+pub fn update_article<'a>(params: (UpdatedArticle, i32, bool, bool, Option<String>)) -> Option<ArticleResult> {
+    let (new_article, editor_id, first_publication, rescheduled, held_because) = params;
     let conn = establish_connection();
 
-    let result = new_article.save_changes::<Article>(&conn).unwrap();
+    let (previous_body, result) = conn
+        .transaction::<_, diesel::result::Error, _>(|| {
+            use schema::articles;
+
+            let previous: Article = articles::table.find(new_article.id).first(&conn)?;
+            let saved = new_article.save_changes::<Article>(&conn)?;
+            if saved.slug != previous.slug {
+                record_slug_change(saved.id, &previous.slug, &saved.slug, &conn)?;
+            }
+            record_revision(&saved, editor_id, &conn)?;
+            record_audit(Some(editor_id), "update", "article", saved.id, audit_value(&previous), audit_value(&saved), &conn)?;
+            Ok((previous.body, saved))
+        })
+        .expect("Error updating article");
+    if let (true, Some(publish_at)) = (rescheduled, result.publishAt) {
+        schedule_publication(result.id, publish_at, &conn);
+    }
+    if let Some(reason) = held_because {
+        hold_for_review(result.id, None, &reason);
+    }
+
+    let updated = get_advanced_article(&result.slug);
+    // an article held for review goes public without telling anybody, like a held new one
+    if let (&Some(ref updated), false) = (&updated, result.hidden) {
+        if first_publication {
+            announce_article(updated, "article.published");
+        } else if updated.article.status != ArticleStatus::Draft.name() {
+            dispatch_webhooks("article.updated", updated.article.author, updated);
+            // only the users the edit adds to the body hear about it
+            if updated.article.body != previous_body {
+                let already_mentioned: Vec<i32> = parse_mentions(&previous_body)
+                    .iter()
+                    .filter_map(|name| get_user_by_name(name))
+                    .map(|user| user.id)
+                    .collect();
+                notify_mentions(&updated.article.body, updated.article.author, Some(updated.article.id), None, &already_mentioned);
+            }
+        }
+    }
+    updated
+}
 
-    get_advanced_article(&result.slug)
+#[cfg(feature = "tiberius")]
+fn get_article_author(url_slug: &str) -> Option<i32> {
+    let mut author_id: Option<i32> = None;
+    {
+        let mut sql = Core::new().unwrap();
+        let get_author_cmd = SqlConnection::connect(sql.handle(), CONNECTION_STRING.as_str())
+            .and_then(|conn| {
+                conn.query("SELECT TOP 1 Author FROM [dbo].[Articles] WHERE [Slug] = @P1", &[&url_slug])
+                    .for_each_row(|row| {
+                        author_id = Some(row.get(0));
+                        Ok(())
+                    })
+            });
+        sql.run(get_author_cmd).unwrap();
+    }
+    author_id
 }
 
-pub fn update_article_handler(req: Request, res: Response, c: Captures) {
-    let (request_body, _) = prepare_parameters(req);
+pub fn update_article_handler(req: Request, mut res: Response, c: Captures) {
+    let (request_body, logged_id) = prepare_parameters(req);
 
     let caps = c.unwrap();
     let url_slug = &caps[0].replace("/api/articles/", "");
@@ -789,14 +1105,53 @@ pub fn update_article_handler(req: Request, res: Response, c: Captures) {
         use models::UpdatedArticle;
 
         let incoming_article: UpdateArticle = serde_json::from_str(&request_body).unwrap();
-        
+
+        let stored = match find_article(url_slug) {
+            Some(article) => article,
+            None => {
+                send_error(res, StatusCode::NotFound, "article not found");
+                return;
+            }
+        };
+        if let Err(denied) = check(logged_id, Action::UpdateArticle, Some(stored.author)) {
+            send_denied(res, denied);
+            return;
+        }
+
         let article_result : ArticleResult = get_advanced_article(url_slug).unwrap();
         let original = article_result.article;
+
         let old_id = original.id;
         let old_author = original.author;
         let old_created = original.createdAt;
         let old_updated = original.updatedAt;
 
+        let new_status = match incoming_article.article.status.as_ref().map(|name| ArticleStatus::from_name(name)) {
+            None => ArticleStatus::from_name(&original.status).unwrap_or(ArticleStatus::Published),
+            Some(Some(status)) => status,
+            Some(None) => {
+                send_error(res, StatusCode::UnprocessableEntity, UNKNOWN_STATUS);
+                return;
+            }
+        };
+        // published the first time it becomes published, later status changes keep the date
+        let published_at = original.publishedAt.or(if new_status == ArticleStatus::Published {
+            Some(Utc::now().naive_utc())
+        } else {
+            None
+        });
+        let requested_publish_at = incoming_article.article.publishAt;
+        if let Err(message) = check_schedule(new_status, requested_publish_at) {
+            send_error(res, StatusCode::UnprocessableEntity, message);
+            return;
+        }
+        // leaving the drafts drops the schedule
+        let publish_at = if new_status == ArticleStatus::Draft {
+            requested_publish_at.or(original.publishAt)
+        } else {
+            None
+        };
+
 
         let new_title: &str = incoming_article
             .article
@@ -816,61 +1171,136 @@ pub fn update_article_handler(req: Request, res: Response, c: Captures) {
             .as_ref()
             .map(|x| &**x)
             .unwrap_or(&original.description);
-        let new_slug: &str = &slugify(new_title);
+        // the filters see what an edit changes, a status change alone keeps what moderators decided
+        let mut held_because = None;
+        let text = article_text(new_title, new_description, new_body);
+        if text != article_text(&original.title, &original.description, &original.body) {
+            let submission = Submission { author: old_author, kind: ContentKind::Article, text: &text, edited: Some(old_id) };
+            match run_filters(&content_filters(), &submission) {
+                Verdict::Allow => {}
+                Verdict::Hold(reason) => held_because = Some(reason),
+                Verdict::Reject(reason) => {
+                    send_error(res, StatusCode::UnprocessableEntity, &reason);
+                    return;
+                }
+            }
+        }
+        if held_because.is_some() {
+            *res.status_mut() = StatusCode::Accepted;
+        }
+
+        // links keep their slug until the title changes
+        let new_slug = if new_title == original.title {
+            original.slug.clone()
+        } else {
+            unique_slug(new_title, Some(old_id))
+        };
 
         let new_article = UpdatedArticle {
             id : old_id,
-            slug : new_slug,
+            slug : &new_slug,
             title : new_title,
             description : new_description,
             body : new_body,
             author : old_author,
             createdat : old_created,
             updatedat : old_updated,
+            status : new_status.name(),
+            publishedat : published_at,
+            publishat : publish_at,
+            hidden : stored.hidden || held_because.is_some(),
         };
 
-        process(res, update_article, new_article )
+        let first_publication = original.publishedAt.is_none() && published_at.is_some();
+        let rescheduled = requested_publish_at.is_some() && publish_at != original.publishAt;
+        process(res, update_article, (new_article, logged_id, first_publication, rescheduled, held_because))
     }
 
     #[cfg(feature = "tiberius")]
-    process(
-        res,
-        r#"
-        declare @id int; select TOP(1) @id = id from Articles where Slug = @P1; 
-        DECLARE @logged int = @P5;
-        UPDATE TOP(1) [dbo].[Articles] SET 
-        [Title]=CASE WHEN(LEN(@P2)=0) THEN Title ELSE @P2 END,
-        [Description]=CASE WHEN(LEN(@P3)=0) THEN Description ELSE @P3 END,
-        [Body]=CASE WHEN(LEN(@P4)=0) THEN Description ELSE @P4 END,
-        [Slug]=CASE WHEN(LEN(@P2)=0) THEN [Slug] ELSE @P6 END
-        WHERE [Id] = @id AND Author = @logged; 
-        "#,
-        ARTICLE_SELECT,
-        get_article_from_row,
-        &[
-            &(slug.as_str()),
-            &title,
-            &description,
-            &body,
-            &logged_id,
-            &new_slug,
-        ],
-    );
+    {
+        // no roles without diesel, only the author edits
+        match get_article_author(url_slug) {
+            None => {
+                send_error(res, StatusCode::NotFound, "article not found");
+                return;
+            }
+            Some(author_id) if author_id != logged_id => {
+                send_denied(res, if logged_id <= 0 { Denied::Unauthenticated } else { Denied::Forbidden });
+                return;
+            }
+            Some(_) => {}
+        }
+
+        let incoming_article: UpdateArticle = serde_json::from_str(&request_body).unwrap();
+        let title = incoming_article.article.title.unwrap_or_default();
+        let description = incoming_article.article.description.unwrap_or_default();
+        let body = incoming_article.article.body.unwrap_or_default();
+        let new_slug = slugify(&title);
+
+        process(
+            res,
+            r#"
+            declare @id int; select TOP(1) @id = id from Articles where Slug = @P1; 
+            DECLARE @logged int = @P5;
+            UPDATE TOP(1) [dbo].[Articles] SET 
+            [Title]=CASE WHEN(LEN(@P2)=0) THEN Title ELSE @P2 END,
+            [Description]=CASE WHEN(LEN(@P3)=0) THEN Description ELSE @P3 END,
+            [Body]=CASE WHEN(LEN(@P4)=0) THEN Description ELSE @P4 END,
+            [Slug]=CASE WHEN(LEN(@P2)=0) THEN [Slug] ELSE @P6 END
+            WHERE [Id] = @id AND Author = @logged; 
+            "#,
+            ARTICLE_SELECT,
+            get_article_from_row,
+            &[
+                &(url_slug.as_str()),
+                &title,
+                &description,
+                &body,
+                &logged_id,
+                &new_slug,
+            ],
+        );
+    }
 }
 
+/// Moves the article to the trash, tags and comments stay with it for a restore.
 #[cfg(feature = "diesel")]
-fn delete_article (url_slug: String) -> Option<bool> {
+fn delete_article(params: (Article, i32)) -> Option<bool> {
     use schema::articles::dsl::*;
-    let connection = establish_connection();
 
-    let ar = get_article(&url_slug);
-    delete_tags_for_article(ar);
+    let (article, deleted_by) = params;
+    let connection = establish_connection();
+    let now = Utc::now().naive_utc();
 
-    diesel::delete(articles.filter(slug.eq(url_slug)))
-        .execute(&connection).expect("Failed to delete an article");
+    let trashed: Article = diesel::update(articles.find(article.id))
+        .set((deletedat.eq(Some(now)), deletedby.eq(Some(deleted_by))))
+        .get_result(&connection)
+        .expect("Failed to delete an article");
+    record_audit(Some(deleted_by), "delete", "article", trashed.id, audit_value(&article), audit_value(&trashed), &connection)
+        .expect("Error recording audit entry");
+    unpin_article(trashed.id);
+    schedule_purge(PURGE_ARTICLE, trashed.id, now, &connection);
+
+    let deleted = DeletedArticleResult { article: trashed };
+    dispatch_webhooks("article.deleted", deleted.article.author, &deleted);
     None
 }
 
+/// Deletes a trashed article for good, its comments cascade with it.
+#[cfg(feature = "diesel")]
+pub fn purge_article(article: Article) {
+    use schema::articles::dsl::*;
+
+    let connection = establish_connection();
+    let article_id = article.id;
+    let before = audit_value(&article);
+    delete_tags_for_article(article);
+    diesel::delete(articles.find(article_id))
+        .execute(&connection).expect("Failed to purge an article");
+    record_audit(None, "purge", "article", article_id, before, None, &connection)
+        .expect("Error recording audit entry");
+}
+
 pub fn delete_article_handler(req: Request, res: Response, c: Captures) {
     let (_, logged_id) = prepare_parameters(req);
 
@@ -880,7 +1310,19 @@ pub fn delete_article_handler(req: Request, res: Response, c: Captures) {
 
     #[cfg(feature = "diesel")] 
     {
-        process(res, delete_article, slug.to_owned());
+        let article = match find_article(slug) {
+            Some(article) => article,
+            None => {
+                send_error(res, StatusCode::NotFound, "article not found");
+                return;
+            }
+        };
+        if let Err(denied) = check(logged_id, Action::DeleteArticle, Some(article.author)) {
+            send_denied(res, denied);
+            return;
+        }
+
+        process(res, delete_article, (article, logged_id));
     };
 
     #[cfg(feature = "tiberius")]
@@ -897,6 +1339,194 @@ pub fn delete_article_handler(req: Request, res: Response, c: Captures) {
     );
 }
 
+#[cfg(feature = "diesel")]
+pub fn publish_article_handler(req: Request, res: Response, c: Captures) {
+    let (_, logged_id) = prepare_parameters(req);
+
+    let caps = c.unwrap();
+    let url_slug = caps[0].replace("/api/articles/", "").replace("/publish", "");
+    println!("publish_article_handler slug: '{}'", url_slug);
+
+    let article = match find_article(&url_slug) {
+        Some(article) => article,
+        None => {
+            send_error(res, StatusCode::NotFound, "article not found");
+            return;
+        }
+    };
+    if let Err(denied) = check(logged_id, Action::UpdateArticle, Some(article.author)) {
+        send_denied(res, denied);
+        return;
+    }
+    process(res, publish_now, article)
+}
+
+/// Publishes the article, also for the scheduled publishing job; publishing twice keeps the first date.
+#[cfg(feature = "diesel")]
+pub fn publish_now(article: Article) -> Option<ArticleResult> {
+    use models::UpdatedArticle;
+
+    let published = UpdatedArticle {
+        id: article.id,
+        slug: &article.slug,
+        title: &article.title,
+        description: &article.description,
+        body: &article.body,
+        author: article.author,
+        createdat: article.createdAt,
+        updatedat: article.updatedAt,
+        status: ArticleStatus::Published.name(),
+        publishedat: Some(article.publishedAt.unwrap_or_else(|| Utc::now().naive_utc())),
+        publishat: None,
+        hidden: article.hidden,
+    };
+    update_article((published, article.author, article.publishedAt.is_none(), false, None))
+}
+
+#[cfg(feature = "diesel")]
+fn get_own_articles(params: (i32, Option<ArticleStatus>, i64, i64)) -> Vec<Article> {
+    use schema::articles::dsl::*;
+
+    let (author_id, wanted_status, limit, offset) = params;
+    let connection = establish_connection();
+
+    let own = articles.filter(author.eq(author_id).and(deletedat.is_null()));
+    match wanted_status {
+        Some(wanted_status) => own.filter(status.eq(wanted_status.name()))
+            .order(createdat.desc())
+            .limit(limit)
+            .offset(offset)
+            .load::<Article>(&connection),
+        None => own.order(createdat.desc()).limit(limit).offset(offset).load::<Article>(&connection),
+    }.expect("Error loading own articles")
+}
+
+/// The logged in user's articles in any status, `?status=draft` for the drafts only.
+#[cfg(feature = "diesel")]
+pub fn user_articles_handler(req: Request, res: Response, c: Captures) {
+    let (_, logged_id) = prepare_parameters(req);
+
+    let caps = c.unwrap();
+    let url = &caps[0];
+    let limit: i64 = get_query_param(url, "limit").and_then(|v| v.parse().ok()).unwrap_or(20);
+    let offset: i64 = get_query_param(url, "offset").and_then(|v| v.parse().ok()).unwrap_or(0);
+
+    if logged_id <= 0 {
+        send_denied(res, Denied::Unauthenticated);
+        return;
+    }
+    let wanted_status = match get_query_param(url, "status") {
+        None => None,
+        Some(name) => match ArticleStatus::from_name(name) {
+            Some(wanted_status) => Some(wanted_status),
+            None => {
+                send_error(res, StatusCode::UnprocessableEntity, UNKNOWN_STATUS);
+                return;
+            }
+        },
+    };
+
+    process_container(
+        res,
+        articles_result,
+        get_own_articles,
+        (logged_id, wanted_status, limit.max(1).min(MAX_OWN_ARTICLES_LIMIT), offset.max(0)),
+    )
+}
+
+#[cfg(feature = "diesel")]
+fn is_pinned(article_id: i32) -> bool {
+    use schema::pinnedarticles::dsl::*;
+
+    let connection = establish_connection();
+
+    let pinned_count: i64 = pinnedarticles
+        .filter(articleid.eq(article_id))
+        .count()
+        .get_result(&connection)
+        .unwrap();
+    pinned_count > 0
+}
+
+#[cfg(feature = "diesel")]
+fn pin_article(params: (i32, i32)) {
+    use schema::pinnedarticles;
+    use chrono::prelude::*;
+
+    let (article_id, pinned_by) = params;
+    if is_pinned(article_id) {
+        return;
+    }
+
+    let connection = establish_connection();
+    let utc: DateTime<Utc> = Utc::now();
+    let pin = NewPinnedArticle {
+        articleid: article_id,
+        pinnedby: pinned_by,
+        pinnedat: utc.naive_utc(),
+    };
+    diesel::insert(&pin)
+        .into(pinnedarticles::table)
+        .execute(&connection)
+        .expect("Error pinning article");
+}
+
+#[cfg(feature = "diesel")]
+fn unpin_article(article_id: i32) {
+    use schema::pinnedarticles::dsl::*;
+
+    let connection = establish_connection();
+    diesel::delete(pinnedarticles.filter(articleid.eq(article_id)))
+        .execute(&connection)
+        .expect("Error unpinning article");
+}
+
+#[cfg(feature = "diesel")]
+pub fn pin_article_handler(req: Request, res: Response, c: Captures) {
+    let (_, logged_id) = prepare_parameters(req);
+
+    let caps = c.unwrap();
+    let url_slug = &caps[0].replace("/api/articles/", "").replace("/pin", "");
+
+    if let Err(denied) = check(logged_id, Action::PinContent, None) {
+        send_denied(res, denied);
+        return;
+    }
+
+    let article = match find_article(url_slug) {
+        Some(article) => article,
+        None => {
+            send_error(res, StatusCode::NotFound, "article not found");
+            return;
+        }
+    };
+    pin_article((article.id, logged_id));
+    process(res, get_advanced_article, url_slug);
+}
+
+#[cfg(feature = "diesel")]
+pub fn unpin_article_handler(req: Request, res: Response, c: Captures) {
+    let (_, logged_id) = prepare_parameters(req);
+
+    let caps = c.unwrap();
+    let url_slug = &caps[0].replace("/api/articles/", "").replace("/pin", "");
+
+    if let Err(denied) = check(logged_id, Action::PinContent, None) {
+        send_denied(res, denied);
+        return;
+    }
+
+    let article = match find_article(url_slug) {
+        Some(article) => article,
+        None => {
+            send_error(res, StatusCode::NotFound, "article not found");
+            return;
+        }
+    };
+    unpin_article(article.id);
+    process(res, get_advanced_article, url_slug);
+}
+
 #[cfg(test)]
 use rand::Rng;
 
@@ -959,22 +1589,25 @@ fn favorite_article_test() {
     let (jwt, slug, user_name) = login_create_article(false);
     let url = format!("http://localhost:6767/api/articles/{}/favorite", slug);
 
-    let mut res = client
-        .post(&url)
-        .header(Authorization(Bearer { token: jwt }))
-        .send()
-        .unwrap();
-    let mut buffer = String::new();
-    res.read_to_string(&mut buffer).unwrap();
-
-    let create_result: ArticleResult = serde_json::from_str(&buffer).unwrap();
-    let article = create_result.article;
-    assert_eq!(article.slug, slug);
-    assert_eq!(article.favorited, true);
-    assert_eq!(article.favoritesCount, 1);
-    //assert_eq!(article.author.username, user_name);
-
-    assert_eq!(res.status, hyper::Ok);
+    // the second favorite changes nothing
+    for _ in 0..2 {
+        let mut res = client
+            .post(&url)
+            .header(Authorization(Bearer { token: jwt.to_owned() }))
+            .send()
+            .unwrap();
+        let mut buffer = String::new();
+        res.read_to_string(&mut buffer).unwrap();
+
+        let create_result: ArticleResult = serde_json::from_str(&buffer).unwrap();
+        let article = create_result.article;
+        assert_eq!(article.slug, slug);
+        assert_eq!(article.favorited, true);
+        assert_eq!(article.favoritesCount, 1);
+        //assert_eq!(article.author.username, user_name);
+
+        assert_eq!(res.status, hyper::Ok);
+    }
 }
 
 #[cfg(test)]
@@ -1045,6 +1678,92 @@ fn get_article_test() {
     assert_eq!(res.status, hyper::Ok);
 }
 
+#[cfg(test)]
+#[test]
+fn draft_article_test() {
+    let client = Client::new();
+
+    let (user_name, email) = register_jacob();
+    let jwt = login_jacob(email, JACOB_PASSWORD.to_string());
+    let title = format!("Dragon eggs, unfinished {}", since_the_epoch());
+    let body = format!(
+        r#"{{"article": {{"title": "{}", "description": "Not yet", "body": "Soon.", "status": "draft"}}}}"#,
+        title
+    );
+
+    let mut res = client
+        .post("http://localhost:6767/api/articles")
+        .header(Authorization(Bearer { token: jwt.to_owned() }))
+        .body(&body)
+        .send()
+        .unwrap();
+    let mut buffer = String::new();
+    res.read_to_string(&mut buffer).unwrap();
+    assert_eq!(res.status, hyper::Ok);
+    let draft = serde_json::from_str::<ArticleResult>(&buffer).unwrap().article;
+    assert_eq!(draft.status, "draft");
+    assert!(draft.publishedAt.is_none());
+
+    let url = format!("http://localhost:6767/api/articles/{}", draft.slug);
+    let res = client.get(&url).send().unwrap();
+    assert_eq!(res.status, StatusCode::NotFound);
+
+    // nor can anybody else reach it through its comments, favorites or reactions
+    let (_, reader_email) = register_jacob();
+    let reader_jwt = login_jacob(reader_email, JACOB_PASSWORD.to_string());
+    let res = client.get(&format!("{}/comments", url)).send().unwrap();
+    assert_eq!(res.status, StatusCode::NotFound);
+    for (path, body) in vec![
+        ("comments", r#"{"comment": {"body": "First!"}}"#),
+        ("favorite", ""),
+        ("reactions/heart", ""),
+    ] {
+        let res = client
+            .post(&format!("{}/{}", url, path))
+            .header(Authorization(Bearer { token: reader_jwt.to_owned() }))
+            .body(body)
+            .send()
+            .unwrap();
+        assert_eq!(res.status, StatusCode::NotFound, "{}", path);
+    }
+
+    let mut res = client
+        .get(&format!("http://localhost:6767/api/articles?author={}", user_name))
+        .send()
+        .unwrap();
+    let mut buffer = String::new();
+    res.read_to_string(&mut buffer).unwrap();
+    let listed: ArticlesResult = serde_json::from_str(&buffer).unwrap();
+    assert!(listed.articles.is_empty());
+
+    let mut res = client
+        .get("http://localhost:6767/api/user/articles?status=draft")
+        .header(Authorization(Bearer { token: jwt.to_owned() }))
+        .send()
+        .unwrap();
+    let mut buffer = String::new();
+    res.read_to_string(&mut buffer).unwrap();
+    let drafts: ArticlesResult = serde_json::from_str(&buffer).unwrap();
+    assert_eq!(drafts.articles.len(), 1);
+    assert_eq!(drafts.articles[0].slug, draft.slug);
+
+    let mut res = client
+        .post(&format!("{}/publish", url))
+        .header(Authorization(Bearer { token: jwt }))
+        .body("")
+        .send()
+        .unwrap();
+    let mut buffer = String::new();
+    res.read_to_string(&mut buffer).unwrap();
+    assert_eq!(res.status, hyper::Ok);
+    let published = serde_json::from_str::<ArticleResult>(&buffer).unwrap().article;
+    assert_eq!(published.status, "published");
+    assert!(published.publishedAt.is_some());
+
+    let res = client.get(&url).send().unwrap();
+    assert_eq!(res.status, hyper::Ok);
+}
+
 #[cfg(test)]
 #[test]
 fn list_article_test() {
@@ -1160,3 +1879,56 @@ fn delete_article_test() {
         .unwrap();
     assert_eq!(res.status, hyper::Ok);
 }
+
+#[cfg(test)]
+pub fn login_other_jacob() -> std::string::String {
+    let (_, email) = register_jacob();
+    login_jacob(email, user::JACOB_PASSWORD.to_string())
+}
+
+#[cfg(test)]
+#[test]
+fn update_foreign_article_test() {
+    let client = Client::new();
+
+    let (_, slug, _) = login_create_article(false);
+    let other_jwt = login_other_jacob();
+    let url = format!("http://localhost:6767/api/articles/{}", slug);
+
+    let res = client
+        .put(&url)
+        .header(Authorization(Bearer { token: other_jwt }))
+        .body(r#"{"article": {"title": "Hijacked", "body": "HIJACKED"}}"#)
+        .send()
+        .unwrap();
+    assert_eq!(res.status, StatusCode::Forbidden);
+
+    let mut res = client.get(&url).send().unwrap();
+    let mut buffer = String::new();
+    res.read_to_string(&mut buffer).unwrap();
+
+    let article = serde_json::from_str::<ArticleResult>(&buffer).unwrap().article;
+    assert_eq!(article.slug, slug);
+    assert_eq!(article.body, "You have to believe");
+}
+
+#[cfg(test)]
+#[test]
+fn delete_foreign_article_test() {
+    let client = Client::new();
+
+    let (_, slug, _) = login_create_article(false);
+    let other_jwt = login_other_jacob();
+    let url = format!("http://localhost:6767/api/articles/{}", slug);
+
+    let res = client
+        .delete(&url)
+        .header(Authorization(Bearer { token: other_jwt }))
+        .body("")
+        .send()
+        .unwrap();
+    assert_eq!(res.status, StatusCode::Forbidden);
+
+    let res = client.get(&url).send().unwrap();
+    assert_eq!(res.status, hyper::Ok);
+}
@@ -0,0 +1,288 @@
+extern crate hyper;
+
+extern crate serde;
+extern crate serde_json;
+
+extern crate chrono;
+
+extern crate reroute;
+
+use hyper::status::StatusCode;
+
+use hyper::server::{Request, Response};
+use reroute::Captures;
+
+use super::*;
+
+/// Fields never written to the audit log, whatever entity they belong to.
+static AUDIT_REDACTED: &'static [&'static str] = &["token", "password"];
+
+const MAX_AUDIT_LIMIT: i64 = 100;
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+#[allow(non_snake_case)]
+pub struct AuditEntryDTO {
+    pub id: i32,
+    pub actorId: Option<i32>,
+    pub action: String,
+    pub entityType: String,
+    pub entityId: i32,
+    pub before: Option<serde_json::Value>,
+    pub after: Option<serde_json::Value>,
+    pub createdAt: NaiveDateTime,
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+pub struct AuditLogResult {
+    pub entries: Vec<AuditEntryDTO>,
+}
+
+impl Container<AuditEntryDTO> for AuditLogResult {
+    fn create_new_with_items(entries: Vec<AuditEntryDTO>) -> AuditLogResult {
+        AuditLogResult { entries: entries }
+    }
+}
+
+#[derive(Debug)]
+pub struct AuditQuery {
+    pub actor_id: Option<i32>,
+    pub entity_type: Option<String>,
+    pub entity_id: Option<i32>,
+    pub from: Option<NaiveDateTime>,
+    pub to: Option<NaiveDateTime>,
+    pub limit: i64,
+    pub offset: i64,
+}
+
+/// `value` as the audit log keeps it, without secrets such as password hashes.
+pub fn audit_value<T: serde::Serialize>(value: &T) -> Option<serde_json::Value> {
+    let mut value = serde_json::to_value(value).ok()?;
+    if let Some(fields) = value.as_object_mut() {
+        for name in AUDIT_REDACTED {
+            fields.remove(*name);
+        }
+    }
+    Some(value)
+}
+
+/// Appends who did `action` to which entity; `actor_id` is `None` for the server's own jobs.
+#[cfg(feature = "diesel")]
+pub fn record_audit(
+    actor_id: Option<i32>,
+    action: &str,
+    entity_type: &str,
+    entity_id: i32,
+    before: Option<serde_json::Value>,
+    after: Option<serde_json::Value>,
+    connection: &PgConnection,
+) -> QueryResult<()> {
+    use schema::auditlog;
+
+    let before = before.map(|value| value.to_string());
+    let after = after.map(|value| value.to_string());
+    let entry = NewAuditEntry {
+        actorid: actor_id,
+        action: action,
+        entitytype: entity_type,
+        entityid: entity_id,
+        before: before.as_ref().map(|value| &**value),
+        after: after.as_ref().map(|value| &**value),
+        createdat: Utc::now().naive_utc(),
+        tenantid: current_tenant(),
+    };
+    diesel::insert(&entry)
+        .into(auditlog::table)
+        .execute(connection)?;
+    Ok(())
+}
+
+/// Blanks the before and after of the entries by or about a deleted account and of its deleted content.
+#[cfg(feature = "diesel")]
+pub fn erase_audit_payloads(
+    user_id: i32,
+    article_ids: &[i32],
+    comment_ids: &[i32],
+    connection: &PgConnection,
+) -> QueryResult<usize> {
+    use diesel::expression::dsl::any;
+    use schema::auditlog::dsl::*;
+
+    // the trigger lets this one update through, see 2018-02-02-090000_allow_audit_payload_erasure
+    diesel::update(auditlog.filter(
+        actorid.eq(user_id)
+            .or(entitytype.eq("user").and(entityid.eq(user_id)))
+            .or(entitytype.eq("article").and(entityid.eq(any(article_ids))))
+            .or(entitytype.eq("comment").and(entityid.eq(any(comment_ids)))),
+    )).set((before.eq(None::<String>), after.eq(None::<String>)))
+        .execute(connection)
+}
+
+#[cfg(feature = "diesel")]
+fn get_audit_entries(query: AuditQuery) -> Vec<AuditEntryDTO> {
+    use schema::auditlog::dsl::*;
+
+    let connection = establish_connection();
+
+    let mut entries = auditlog.filter(tenantid.eq(current_tenant())).into_boxed();
+    if let Some(actor_id) = query.actor_id {
+        entries = entries.filter(actorid.eq(actor_id));
+    }
+    if let Some(ref entity_type) = query.entity_type {
+        entries = entries.filter(entitytype.eq(entity_type.to_owned()));
+    }
+    if let Some(entity_id) = query.entity_id {
+        entries = entries.filter(entityid.eq(entity_id));
+    }
+    if let Some(from) = query.from {
+        entries = entries.filter(createdat.ge(from));
+    }
+    if let Some(to) = query.to {
+        entries = entries.filter(createdat.lt(to));
+    }
+
+    entries
+        .order(id.desc())
+        .limit(query.limit)
+        .offset(query.offset)
+        .load::<AuditEntry>(&connection)
+        .expect("Error loading audit log")
+        .into_iter()
+        .map(|entry| {
+            AuditEntryDTO {
+                id: entry.id,
+                actorId: entry.actorid,
+                action: entry.action,
+                entityType: entry.entitytype,
+                entityId: entry.entityid,
+                before: entry.before.and_then(|value| serde_json::from_str(&value).ok()),
+                after: entry.after.and_then(|value| serde_json::from_str(&value).ok()),
+                createdAt: entry.createdat,
+            }
+        })
+        .collect()
+}
+
+fn audit_log_result(_: AuditLogResult) {}
+
+/// `?actor=<username>&entity=<type>&entityId=<id>&from=<time>&to=<time>`, every filter optional, newest first.
+#[cfg(feature = "diesel")]
+pub fn audit_log_handler(req: Request, res: Response, c: Captures) {
+    let (_, logged_id) = prepare_parameters(req);
+
+    let caps = c.unwrap();
+    let url = &caps[0];
+    println!("audit_log_handler url: '{}'", url);
+
+    if let Err(denied) = check(logged_id, Action::ManageUsers, None) {
+        send_denied(res, denied);
+        return;
+    }
+
+    let actor_id = match get_query_param(url, "actor") {
+        Some(user_name) => match get_user_by_name(user_name) {
+            Some(user) => Some(user.id),
+            None => {
+                send_error(res, StatusCode::NotFound, "actor not found");
+                return;
+            }
+        },
+        None => None,
+    };
+    let entity_id = match get_query_param(url, "entityId").map(|v| v.parse::<i32>()) {
+        Some(Ok(entity_id)) => Some(entity_id),
+        Some(Err(_)) => {
+            send_error(res, StatusCode::UnprocessableEntity, "entityId must be a number");
+            return;
+        }
+        None => None,
+    };
+    let mut range = Vec::new();
+    for name in &["from", "to"] {
+        match get_query_param(url, name).map(|v| v.parse::<NaiveDateTime>()) {
+            Some(Ok(time)) => range.push(Some(time)),
+            Some(Err(_)) => {
+                let message = format!("{} must be a time such as 2018-01-26T09:00:00", name);
+                send_error(res, StatusCode::UnprocessableEntity, &message);
+                return;
+            }
+            None => range.push(None),
+        }
+    }
+
+    let limit: i64 = get_query_param(url, "limit").and_then(|v| v.parse().ok()).unwrap_or(20);
+    let offset: i64 = get_query_param(url, "offset").and_then(|v| v.parse().ok()).unwrap_or(0);
+
+    let query = AuditQuery {
+        actor_id: actor_id,
+        entity_type: get_query_param(url, "entity").map(|v| v.to_string()),
+        entity_id: entity_id,
+        from: range[0],
+        to: range[1],
+        limit: limit.max(1).min(MAX_AUDIT_LIMIT),
+        offset: offset.max(0),
+    };
+    process_container(res, audit_log_result, get_audit_entries, query)
+}
+
+#[cfg(test)]
+#[test]
+fn audit_value_test() {
+    let user = User {
+        id: 1,
+        email: "jacob@example.com".to_string(),
+        token: "pbkdf2 hash".to_string(),
+        username: "jacob".to_string(),
+        bio: None,
+        image: None,
+        tenantid: DEFAULT_TENANT_ID,
+    };
+
+    let value = audit_value(&user).unwrap();
+    assert_eq!(value["username"], "jacob");
+    assert!(value.get("token").is_none());
+}
+
+#[cfg(test)]
+#[test]
+fn audit_log_test() {
+    let client = Client::new();
+
+    let (_, admin_jwt) = register_admin();
+    let (jwt, slug, user_name) = login_create_article(false);
+
+    let res = client
+        .put(&format!("http://localhost:6767/api/articles/{}", slug))
+        .header(Authorization(Bearer { token: jwt.to_owned() }))
+        .body(r#"{"article": {"body": "Audited."}}"#)
+        .send()
+        .unwrap();
+    assert_eq!(res.status, hyper::Ok);
+
+    let url = format!("http://localhost:6767/api/admin/audit?actor={}&entity=article", user_name);
+    let res = client
+        .get(&url)
+        .header(Authorization(Bearer { token: jwt }))
+        .send()
+        .unwrap();
+    assert_eq!(res.status, StatusCode::Forbidden);
+
+    let mut res = client
+        .get(&url)
+        .header(Authorization(Bearer { token: admin_jwt }))
+        .send()
+        .unwrap();
+    let mut buffer = String::new();
+    res.read_to_string(&mut buffer).unwrap();
+    assert_eq!(res.status, hyper::Ok);
+    let log: AuditLogResult = serde_json::from_str(&buffer).unwrap();
+
+    // newest first
+    let actions: Vec<&str> = log.entries.iter().map(|entry| &*entry.action).collect();
+    assert_eq!(actions, vec!["update", "create"]);
+    let update = &log.entries[0];
+    assert_eq!(update.before.as_ref().unwrap()["body"], "You have to believe");
+    assert_eq!(update.after.as_ref().unwrap()["body"], "Audited.");
+    assert!(log.entries[1].before.is_none());
+}
@@ -0,0 +1,306 @@
+extern crate hyper;
+
+extern crate serde;
+extern crate serde_json;
+
+extern crate chrono;
+
+extern crate reroute;
+
+use hyper::status::StatusCode;
+
+use hyper::server::{Request, Response};
+use reroute::Captures;
+
+use std::collections::HashSet;
+
+use super::*;
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+pub struct Relation {
+    pub username: String,
+    pub blocked: bool,
+    pub muted: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+pub struct RelationResult {
+    pub relation: Relation,
+}
+
+#[cfg(feature = "diesel")]
+pub fn is_blocked(blocker_id: i32, blocked_id: i32) -> bool {
+    use schema::blocks::dsl::*;
+
+    let connection = establish_connection();
+    let count: i64 = blocks
+        .filter(blockerid.eq(blocker_id).and(blockedid.eq(blocked_id)))
+        .count()
+        .get_result(&connection)
+        .expect("Error loading block");
+    count > 0
+}
+
+#[cfg(feature = "diesel")]
+fn is_muted(muter_id: i32, muted_id: i32) -> bool {
+    use schema::mutes::dsl::*;
+
+    let connection = establish_connection();
+    let count: i64 = mutes
+        .filter(muterid.eq(muter_id).and(mutedid.eq(muted_id)))
+        .count()
+        .get_result(&connection)
+        .expect("Error loading mute");
+    count > 0
+}
+
+/// Authors whose content `viewer_id` should not see at all: those who blocked the viewer and those the viewer muted.
+#[cfg(feature = "diesel")]
+pub fn hidden_authors(viewer_id: i32) -> HashSet<i32> {
+    use schema::{blocks, mutes};
+
+    if viewer_id <= 0 {
+        return HashSet::new();
+    }
+
+    let connection = establish_connection();
+    let blockers: Vec<i32> = blocks::table
+        .filter(blocks::blockedid.eq(viewer_id))
+        .select(blocks::blockerid)
+        .load(&connection)
+        .expect("Error loading blocks");
+    let muted: Vec<i32> = mutes::table
+        .filter(mutes::muterid.eq(viewer_id))
+        .select(mutes::mutedid)
+        .load(&connection)
+        .expect("Error loading mutes");
+
+    blockers.into_iter().chain(muted.into_iter()).collect()
+}
+
+/// Authors whose comments `viewer_id` muted.
+#[cfg(feature = "diesel")]
+pub fn muted_authors(viewer_id: i32) -> HashSet<i32> {
+    use schema::mutes::dsl::*;
+
+    if viewer_id <= 0 {
+        return HashSet::new();
+    }
+
+    let connection = establish_connection();
+    mutes
+        .filter(muterid.eq(viewer_id))
+        .select(mutedid)
+        .load::<i32>(&connection)
+        .expect("Error loading mutes")
+        .into_iter()
+        .collect()
+}
+
+#[cfg(feature = "diesel")]
+fn block_user(blocker_id: i32, blocked_id: i32) {
+    use schema::{blocks, followings};
+    use diesel::result::{DatabaseErrorKind, Error};
+
+    let connection = establish_connection();
+
+    // a block ends the relationship in both directions
+    diesel::delete(followings::table.filter(
+        followings::followerid.eq(blocker_id).and(followings::followingid.eq(blocked_id)).or(
+            followings::followerid.eq(blocked_id).and(followings::followingid.eq(blocker_id)),
+        ),
+    )).execute(&connection)
+        .expect("Error removing follows of a blocked user");
+
+    let block = NewBlock {
+        blockerid: blocker_id,
+        blockedid: blocked_id,
+        createdat: Utc::now().naive_utc(),
+    };
+    match diesel::insert(&block).into(blocks::table).execute(&connection) {
+        Ok(_) | Err(Error::DatabaseError(DatabaseErrorKind::UniqueViolation, _)) => {}
+        Err(e) => panic!("Error saving block: {}", e),
+    }
+}
+
+#[cfg(feature = "diesel")]
+fn unblock_user(blocker_id: i32, blocked_id: i32) {
+    use schema::blocks::dsl::*;
+
+    let connection = establish_connection();
+    diesel::delete(blocks.filter(blockerid.eq(blocker_id).and(blockedid.eq(blocked_id))))
+        .execute(&connection)
+        .expect("Error removing block");
+}
+
+#[cfg(feature = "diesel")]
+fn mute_user(muter_id: i32, muted_id: i32) {
+    use schema::mutes;
+    use diesel::result::{DatabaseErrorKind, Error};
+
+    let connection = establish_connection();
+    let mute = NewMute {
+        muterid: muter_id,
+        mutedid: muted_id,
+        createdat: Utc::now().naive_utc(),
+    };
+    match diesel::insert(&mute).into(mutes::table).execute(&connection) {
+        Ok(_) | Err(Error::DatabaseError(DatabaseErrorKind::UniqueViolation, _)) => {}
+        Err(e) => panic!("Error saving mute: {}", e),
+    }
+}
+
+#[cfg(feature = "diesel")]
+fn unmute_user(muter_id: i32, muted_id: i32) {
+    use schema::mutes::dsl::*;
+
+    let connection = establish_connection();
+    diesel::delete(mutes.filter(muterid.eq(muter_id).and(mutedid.eq(muted_id))))
+        .execute(&connection)
+        .expect("Error removing mute");
+}
+
+#[cfg(feature = "diesel")]
+fn get_relation_result(params: (User, i32)) -> Option<RelationResult> {
+    let (user, viewer_id) = params;
+
+    Some(RelationResult {
+        relation: Relation {
+            blocked: is_blocked(viewer_id, user.id),
+            muted: is_muted(viewer_id, user.id),
+            username: user.username,
+        },
+    })
+}
+
+/// Blocks and mutes only exist with the diesel backend, a tiberius build has no routes for them and filters nothing.
+#[cfg(feature = "diesel")]
+fn relation_handler(req: Request, res: Response, c: Captures, suffix: &str, change: fn(i32, i32)) {
+    let (_, logged_id) = prepare_parameters(req);
+
+    let caps = c.unwrap();
+    let profile = &caps[0].replace("/api/profiles/", "").replace(suffix, "");
+    println!("relation_handler profile: '{}'", profile);
+
+    if logged_id <= 0 {
+        send_denied(res, Denied::Unauthenticated);
+        return;
+    }
+    let user = match get_user_by_name(profile) {
+        Some(user) => user,
+        None => {
+            send_error(res, StatusCode::NotFound, "profile not found");
+            return;
+        }
+    };
+    if user.id == logged_id {
+        send_error(res, StatusCode::UnprocessableEntity, "you cannot block or mute yourself");
+        return;
+    }
+
+    change(logged_id, user.id);
+    process(res, get_relation_result, (user, logged_id))
+}
+
+#[cfg(feature = "diesel")]
+pub fn block_handler(req: Request, res: Response, c: Captures) {
+    relation_handler(req, res, c, "/block", block_user)
+}
+
+#[cfg(feature = "diesel")]
+pub fn unblock_handler(req: Request, res: Response, c: Captures) {
+    relation_handler(req, res, c, "/block", unblock_user)
+}
+
+#[cfg(feature = "diesel")]
+pub fn mute_handler(req: Request, res: Response, c: Captures) {
+    relation_handler(req, res, c, "/mute", mute_user)
+}
+
+#[cfg(feature = "diesel")]
+pub fn unmute_handler(req: Request, res: Response, c: Captures) {
+    relation_handler(req, res, c, "/mute", unmute_user)
+}
+
+#[cfg(test)]
+fn post_relation(url: &str, jwt: &str) -> RelationResult {
+    let client = Client::new();
+
+    let mut res = client
+        .post(url)
+        .header(Authorization(Bearer { token: jwt.to_owned() }))
+        .body("")
+        .send()
+        .unwrap();
+    let mut buffer = String::new();
+    res.read_to_string(&mut buffer).unwrap();
+
+    assert_eq!(res.status, hyper::Ok);
+    serde_json::from_str(&buffer).unwrap()
+}
+
+#[cfg(test)]
+#[test]
+fn block_test() {
+    let client = Client::new();
+    let (author_jwt, slug, author_name) = login_create_article(false);
+    let (blocked_name, blocked_email) = register_jacob();
+    let blocked_jwt = login_jacob(blocked_email, JACOB_PASSWORD.to_string());
+
+    let url = format!("http://localhost:6767/api/profiles/{}/block", blocked_name);
+    let result = post_relation(&url, &author_jwt);
+    assert_eq!(result.relation.blocked, true);
+
+    let url = format!("http://localhost:6767/api/profiles/{}/follow", author_name);
+    let res = client
+        .post(&url)
+        .header(Authorization(Bearer { token: blocked_jwt.to_owned() }))
+        .send()
+        .unwrap();
+    assert_eq!(res.status, StatusCode::Forbidden);
+
+    let url = format!("http://localhost:6767/api/articles/{}/comments", slug);
+    let res = client
+        .post(&url)
+        .header(Authorization(Bearer { token: blocked_jwt.to_owned() }))
+        .body(r#"{"comment": {"body": "Let me in."}}"#)
+        .send()
+        .unwrap();
+    assert_eq!(res.status, StatusCode::Forbidden);
+
+    let url = format!("http://localhost:6767/api/articles/{}", slug);
+    let res = client
+        .get(&url)
+        .header(Authorization(Bearer { token: blocked_jwt }))
+        .send()
+        .unwrap();
+    assert_eq!(res.status, StatusCode::NotFound);
+}
+
+#[cfg(test)]
+#[test]
+fn mute_test() {
+    let client = Client::new();
+    let (_, _, author_name) = login_create_article(false);
+    let (_, muter_email) = register_jacob();
+    let muter_jwt = login_jacob(muter_email, JACOB_PASSWORD.to_string());
+
+    let url = format!("http://localhost:6767/api/profiles/{}/mute", author_name);
+    let result = post_relation(&url, &muter_jwt);
+    assert_eq!(result.relation.muted, true);
+    assert_eq!(result.relation.blocked, false);
+
+    let url = format!("http://localhost:6767/api/articles?author={}", author_name);
+    let mut res = client
+        .get(&url)
+        .header(Authorization(Bearer { token: muter_jwt }))
+        .send()
+        .unwrap();
+    let mut buffer = String::new();
+    res.read_to_string(&mut buffer).unwrap();
+
+    let articles: ArticlesResult = serde_json::from_str(&buffer).unwrap();
+    assert_eq!(articles.articles.len(), 0);
+}
@@ -28,8 +28,206 @@ extern crate slug;
 
 use reroute::Captures;
 
+use std::collections::{HashMap, HashSet};
+
 use super::*;
 
+const MAX_COMMENTS_LIMIT: i64 = 100;
+
+/// What deleting a comment that has replies does: keep an empty placeholder or take the replies along.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CommentDeletePolicy {
+    Tombstone,
+    Cascade,
+}
+
+impl CommentDeletePolicy {
+    pub fn from_name(name: &str) -> Option<CommentDeletePolicy> {
+        match name {
+            "tombstone" => Some(CommentDeletePolicy::Tombstone),
+            "cascade" => Some(CommentDeletePolicy::Cascade),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            CommentDeletePolicy::Tombstone => "tombstone",
+            CommentDeletePolicy::Cascade => "cascade",
+        }
+    }
+}
+
+/// Order of the top level comments; replies always follow the conversation, oldest first.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CommentSort {
+    Oldest,
+    Newest,
+    Top,
+}
+
+impl CommentSort {
+    pub fn from_name(name: &str) -> Option<CommentSort> {
+        match name {
+            "oldest" => Some(CommentSort::Oldest),
+            "newest" => Some(CommentSort::Newest),
+            "top" => Some(CommentSort::Top),
+            _ => None,
+        }
+    }
+}
+
+/// A comment placed in its thread; `depth` is 0 for comments on the article itself.
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+#[allow(non_snake_case)]
+pub struct ThreadedComment {
+    pub id: i32,
+    pub createdAt: NaiveDateTime,
+    pub updatedAt: Option<NaiveDateTime>,
+    pub body: String,
+    pub author: Profile,
+    #[serde(skip_serializing)]
+    #[serde(skip_deserializing)]
+    pub authorId: i32,
+    pub parentId: Option<i32>,
+    pub depth: i32,
+    pub deleted: bool,
+    pub edited: bool,
+    pub reactions: Vec<ReactionCount>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub replies: Vec<ThreadedComment>,
+}
+
+/// A single comment with its author, as answered when it is created or edited.
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+#[allow(non_snake_case)]
+pub struct CommentDTO {
+    pub id: i32,
+    pub createdAt: NaiveDateTime,
+    pub updatedAt: Option<NaiveDateTime>,
+    pub body: String,
+    pub author: Profile,
+    pub parentId: Option<i32>,
+    pub deleted: bool,
+    pub edited: bool,
+    pub reactions: Vec<ReactionCount>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+struct UpdateCommentDetail {
+    body: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+struct UpdateComment {
+    comment: UpdateCommentDetail,
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+#[allow(non_snake_case)]
+pub struct CommentRevisionDTO {
+    pub id: i32,
+    pub body: String,
+    /// `None` once the editor deleted their account.
+    pub editedBy: Option<i32>,
+    pub revisedAt: NaiveDateTime,
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+pub struct CommentRevisionsResult {
+    pub revisions: Vec<CommentRevisionDTO>,
+}
+
+impl Container<CommentRevisionDTO> for CommentRevisionsResult {
+    fn create_new_with_items(revisions: Vec<CommentRevisionDTO>) -> CommentRevisionsResult {
+        CommentRevisionsResult { revisions: revisions }
+    }
+}
+
+fn build_thread(
+    parent_id: Option<i32>,
+    depth: i32,
+    children: &mut HashMap<Option<i32>, Vec<Comment>>,
+    profiles: &HashMap<i32, Profile>,
+    reactions: &HashMap<i32, Vec<ReactionCount>>,
+) -> Vec<ThreadedComment> {
+    let siblings = children.remove(&parent_id).unwrap_or_default();
+    siblings
+        .into_iter()
+        .map(|comment| {
+            let replies = build_thread(Some(comment.id), depth + 1, children, profiles, reactions);
+            ThreadedComment {
+                id: comment.id,
+                createdAt: comment.createdAt,
+                updatedAt: comment.updatedAt,
+                body: if comment.deleted { String::new() } else { comment.body },
+                author: profiles[&comment.author].clone(),
+                authorId: comment.author,
+                parentId: comment.parentId,
+                depth: depth,
+                deleted: comment.deleted,
+                edited: comment.edited,
+                reactions: reactions.get(&comment.id).cloned().unwrap_or_default(),
+                replies: replies,
+            }
+        })
+        .collect()
+}
+
+/// Arranges `comments` into reply trees, siblings keep the order they come in; `profiles` has every author.
+pub fn thread_comments(
+    comments: Vec<Comment>,
+    profiles: &HashMap<i32, Profile>,
+    reactions: &HashMap<i32, Vec<ReactionCount>>,
+) -> Vec<ThreadedComment> {
+    let ids: HashSet<i32> = comments.iter().map(|c| c.id).collect();
+    let mut children: HashMap<Option<i32>, Vec<Comment>> = HashMap::new();
+    for comment in comments {
+        // a reply whose parent is not in the list is shown at the top level
+        let parent_id = match comment.parentId {
+            Some(parent_id) if ids.contains(&parent_id) => Some(parent_id),
+            _ => None,
+        };
+        children.entry(parent_id).or_insert_with(Vec::new).push(comment);
+    }
+    build_thread(None, 0, &mut children, profiles, reactions)
+}
+
+/// The threads in reading order, every comment followed by its replies.
+pub fn flatten_thread(thread: Vec<ThreadedComment>) -> Vec<ThreadedComment> {
+    let mut flat = Vec::new();
+    for mut comment in thread {
+        let replies = std::mem::replace(&mut comment.replies, Vec::new());
+        flat.push(comment);
+        flat.extend(flatten_thread(replies));
+    }
+    flat
+}
+
+/// Drops comments of `hidden` authors together with their replies, and tombstones left without replies.
+fn prune_thread(thread: Vec<ThreadedComment>, hidden: &HashSet<i32>) -> Vec<ThreadedComment> {
+    thread
+        .into_iter()
+        .filter(|comment| !hidden.contains(&comment.authorId) || comment.deleted)
+        .filter_map(|mut comment| {
+            let replies = std::mem::replace(&mut comment.replies, Vec::new());
+            comment.replies = prune_thread(replies, hidden);
+            if comment.deleted && comment.replies.is_empty() {
+                None
+            } else {
+                Some(comment)
+            }
+        })
+        .collect()
+}
+
 static COMMENT_SELECT: &'static str = r#"
   select Comments.Id, createdAt, body,  Users.UserName, Users.Bio, Users.[Image], 
   (SELECT COUNT(*) FROM Followings WHERE FollowerId=@logged AND Author=FollowingId) as [Following]
@@ -72,19 +270,102 @@ fn get_comment_from_row(row: tiberius::query::QueryRow) -> Option<CommentResult>
 }
 
 #[cfg(feature = "diesel")]
-fn add_comment(comment: NewComment) -> Option<CommentResult> {
-    use schema::comments;
+pub fn get_comment_result(comment: Comment, viewer_id: i32) -> CommentResult {
+    let author = get_profiles(&[comment.author], viewer_id)
+        .remove(&comment.author)
+        .expect("Error loading comment author");
+    let reactions = get_comment_reactions(&[comment.id], viewer_id)
+        .remove(&comment.id)
+        .unwrap_or_default();
+
+    CommentResult {
+        comment: CommentDTO {
+            id: comment.id,
+            createdAt: comment.createdAt,
+            updatedAt: comment.updatedAt,
+            body: if comment.deleted { String::new() } else { comment.body },
+            author: author,
+            parentId: comment.parentId,
+            deleted: comment.deleted,
+            edited: comment.edited,
+            reactions: reactions,
+        },
+    }
+}
+
+#[cfg(feature = "diesel")]
+fn add_comment(params: (NewComment, Option<String>)) -> Option<CommentResult> {
+    use schema::{articles, comments};
+
+    let (comment, held_because) = params;
     let connection = establish_connection();
 
     let comment_result: Comment = diesel::insert(&comment)
         .into(comments::table)
         .get_result(&connection)
         .expect("Error saving new post");    
+    record_audit(Some(comment_result.author), "create", "comment", comment_result.id, None, audit_value(&comment_result), &connection)
+        .expect("Error recording audit entry");
+
+    // held comments reach nobody until a moderator lets them through
+    if let Some(reason) = held_because {
+        hold_for_review(comment_result.articleid, Some(comment_result.id), &reason);
+        let author_id = comment_result.author;
+        return Some(get_comment_result(comment_result, author_id));
+    }
+
+    let (article_author, article_slug): (i32, String) = articles::table
+        .find(comment_result.articleid)
+        .select((articles::author, articles::slug))
+        .first(&connection)
+        .expect("Error loading commented article");
+    notify(article_author, comment_result.author, NotificationKind::Comment, Some(comment_result.articleid), Some(comment_result.id));
+    notify_mentions(&comment_result.body, comment_result.author, Some(comment_result.articleid), Some(comment_result.id), &[article_author]);
+
+    let topic = StreamTopic::Comment {
+        article_slug: article_slug,
+        article_id: comment_result.articleid,
+        article_author: article_author,
+        commenter_id: comment_result.author,
+    };
+    let author_id = comment_result.author;
+    let result = get_comment_result(comment_result, author_id);
+    publish("comment", topic, &result);
+    dispatch_webhooks("comment.created", article_author, &result);
 
-    Some(CommentResult { comment: comment_result,} )
+    Some(result)
 }
 
-pub fn add_comment_handler(req: Request, res: Response, c: Captures) {
+/// The comment `parent_id` on the article, with its depth in the thread.
+#[cfg(feature = "diesel")]
+fn find_parent_comment(parent_id: i32, article_id: i32) -> Option<(Comment, i32)> {
+    use schema::comments::dsl::*;
+    let connection = establish_connection();
+
+    let parent: Comment = match comments
+        .filter(id.eq(parent_id).and(articleid.eq(article_id)))
+        .filter(deletedat.is_null().or(deleted.eq(true)))
+        .first(&connection)
+        .optional()
+        .expect("Error loading parent comment") {
+        Some(parent) => parent,
+        None => return None,
+    };
+
+    let mut depth = 0;
+    let mut ancestor = parent.parentId;
+    while let Some(ancestor_id) = ancestor {
+        depth += 1;
+        ancestor = comments
+            .find(ancestor_id)
+            .select(parentid)
+            .first(&connection)
+            .expect("Error loading parent comment");
+    }
+    Some((parent, depth))
+}
+
+pub fn add_comment_handler(req: Request, mut res: Response, c: Captures) {
     let (body, logged_id) = prepare_parameters(req);
 
     let raw_comment: AddComment = serde_json::from_str(&body).unwrap();
@@ -100,19 +381,75 @@ pub fn add_comment_handler(req: Request, res: Response, c: Captures) {
 
      #[cfg(feature = "diesel")] {
          use chrono::prelude::*;
+
+         if let Err(denied) = check(logged_id, Action::CreateComment, None) {
+             send_denied(res, denied);
+             return;
+         }
+
          let utc: DateTime<Utc> = Utc::now();
 
-         let article = get_advanced_article(slug).unwrap().article;
-         
+         let article = match find_article(slug) {
+             Some(article) => article,
+             None => {
+                 send_error(res, StatusCode::NotFound, "article not found");
+                 return;
+             }
+         };
+         if is_blocked(article.author, logged_id) {
+             send_error(res, StatusCode::Forbidden, "the author has blocked you");
+             return;
+         }
+         if !is_article_visible(&article, logged_id) {
+             send_error(res, StatusCode::NotFound, "article not found");
+             return;
+         }
+
+         let parent_id = raw_comment.comment.parentId;
+         if let Some(parent_id) = parent_id {
+             match find_parent_comment(parent_id, article.id) {
+                 // a reported comment waiting for review takes no new replies
+                 None | Some((Comment { hidden: true, .. }, _)) => {
+                     send_error(res, StatusCode::UnprocessableEntity, "parent comment not found on this article");
+                     return;
+                 }
+                 Some((ref parent, _)) if parent.deleted => {
+                     send_error(res, StatusCode::UnprocessableEntity, "cannot reply to a deleted comment");
+                     return;
+                 }
+                 Some((_, depth)) if depth + 1 > comment_max_depth() => {
+                     let message = format!("replies cannot be nested deeper than {} levels", comment_max_depth());
+                     send_error(res, StatusCode::UnprocessableEntity, &message);
+                     return;
+                 }
+                 Some(_) => {}
+             }
+         }
+
+         let submission = Submission { author: logged_id, kind: ContentKind::Comment, text: comment_body, edited: None };
+         let held_because = match run_filters(&content_filters(), &submission) {
+             Verdict::Allow => None,
+             Verdict::Hold(reason) => Some(reason),
+             Verdict::Reject(reason) => {
+                 send_error(res, StatusCode::UnprocessableEntity, &reason);
+                 return;
+             }
+         };
+         if held_because.is_some() {
+             *res.status_mut() = StatusCode::Accepted;
+         }
+
          let comment = NewComment {
              createdat : utc.naive_utc(),
              updatedat: None,
              body : &comment_body,
              articleid : article.id,
              author : logged_id,
+             parentid : parent_id,
+             hidden: held_because.is_some(),
          };
 
-         process(res, add_comment, comment)
+         process(res, add_comment, (comment, held_because))
      }
 
     #[cfg(feature = "tiberius")]
@@ -129,15 +466,89 @@ pub fn add_comment_handler(req: Request, res: Response, c: Captures) {
     );
 }
 
-fn delete_comment(comment_to_del: Comment) -> Option<bool> {
+/// Replies still in the thread, trashed ones do not count.
+fn count_replies(comment_id: i32, connection: &PgConnection) -> i64 {
     use schema::comments::dsl::*;
-    let connection = establish_connection();
 
-    diesel::delete(comments.filter(id.eq(comment_to_del.id)))
-        .execute(&connection).expect("Failed to delete a comment");
+    comments
+        .filter(parentid.eq(comment_id).and(deletedat.is_null()))
+        .count()
+        .get_result(connection)
+        .expect("Error counting replies")
+}
+
+/// Moves the comment to the trash; under the tombstone policy one with replies stays in the thread as a tombstone.
+#[cfg(feature = "diesel")]
+fn delete_comment(params: (Comment, i32)) -> Option<bool> {
+    use schema::comments::dsl::*;
+    use diesel::expression::dsl::any;
+
+    let (comment_to_del, deleted_by) = params;
+    let connection = establish_connection();
+    let now = Utc::now().naive_utc();
+
+    if *COMMENT_DELETE_POLICY == CommentDeletePolicy::Tombstone && count_replies(comment_to_del.id, &connection) > 0 {
+        // the body is kept for a restore, readers get an empty one
+        diesel::update(comments.find(comment_to_del.id))
+            .set((deleted.eq(true), deletedat.eq(Some(now)), deletedby.eq(Some(deleted_by))))
+            .execute(&connection).expect("Failed to tombstone a comment");
+    } else {
+        // replies go to the trash with the comment and come back with it
+        let mut trashed_ids = vec![comment_to_del.id];
+        let mut parent_ids = trashed_ids.clone();
+        while !parent_ids.is_empty() {
+            let replies: Vec<i32> = comments
+                .filter(parentid.eq(any(&parent_ids)).and(deletedat.is_null()))
+                .select(id)
+                .load(&connection)
+                .expect("Error loading replies");
+            trashed_ids.extend(replies.iter().cloned());
+            parent_ids = replies;
+        }
+        diesel::update(comments.filter(id.eq(any(&trashed_ids))))
+            .set((deletedat.eq(Some(now)), deletedby.eq(Some(deleted_by))))
+            .execute(&connection).expect("Failed to delete a comment");
+    }
+    record_audit(Some(deleted_by), "delete", "comment", comment_to_del.id, audit_value(&comment_to_del), None, &connection)
+        .expect("Error recording audit entry");
+    schedule_purge(PURGE_COMMENT, comment_to_del.id, now, &connection);
     None
 }
 
+/// Deletes a trashed comment for good, a tombstone only once its replies are gone.
+#[cfg(feature = "diesel")]
+pub fn purge_comment(comment: Comment) {
+    use schema::comments::dsl::*;
+
+    let connection = establish_connection();
+    let now = Utc::now().naive_utc();
+
+    let mut next = Some(comment);
+    while let Some(trashed) = next {
+        if count_replies(trashed.id, &connection) > 0 {
+            break;
+        }
+        // trashed replies go with it, see fk_comments_comments
+        diesel::delete(comments.find(trashed.id))
+            .execute(&connection).expect("Failed to delete a comment");
+        record_audit(None, "purge", "comment", trashed.id, audit_value(&trashed), None, &connection)
+            .expect("Error recording audit entry");
+
+        // a tombstone whose own purge came while it still had replies goes with the last of them
+        let parent: Option<Comment> = match trashed.parentId {
+            Some(parent_id) => comments
+                .filter(id.eq(parent_id).and(deleted.eq(true)))
+                .first(&connection)
+                .optional()
+                .expect("Error loading parent comment"),
+            None => None,
+        };
+        next = parent.filter(|parent| {
+            parent.deletedAt.map_or(false, |deleted_at| purge_time(deleted_at) <= now)
+        });
+    }
+}
+
 
 pub fn delete_comment_handler(req: Request, res: Response, c: Captures) {
     let (_, logged_id) = prepare_parameters(req);
@@ -145,20 +556,27 @@ pub fn delete_comment_handler(req: Request, res: Response, c: Captures) {
     let caps = c.unwrap();
     let url_params = &caps[0];
     let comment_id = url_params.split("/").last().unwrap();
+    let url_slug = url_params.replace("/api/articles/", "");
+    let url_slug = url_slug.split("/comments/").next().unwrap();
     println!("delete_comment_handler url_params: {}", url_params);
     println!("id: {}", comment_id);
 
     #[cfg(feature = "diesel")] {
-        use schema::comments::dsl::*;
-
-        let connection = establish_connection();
-
-        let comment_to_del: Comment = comments
-            .filter(id.eq(comment_id.parse::<i32>().unwrap()).and(author.eq(logged_id)))
-            .first(&connection)
-            .unwrap();
-
-        process(res, delete_comment, comment_to_del)
+        // the comment has to belong to the article named in the URL
+        let comment_to_del = match find_article_comment(url_slug, comment_id) {
+            Some(comment_to_del) if !comment_to_del.deleted => comment_to_del,
+            _ => {
+                send_error(res, StatusCode::NotFound, "comment not found");
+                return;
+            }
+        };
+
+        if let Err(denied) = check(logged_id, Action::DeleteComment, Some(comment_to_del.author)) {
+            send_denied(res, denied);
+            return;
+        }
+
+        process(res, delete_comment, (comment_to_del, logged_id))
     }
 
     #[cfg(feature = "tiberius")]
@@ -174,33 +592,310 @@ pub fn delete_comment_handler(req: Request, res: Response, c: Captures) {
     return;
 }
 
+/// The comment named by a `/api/articles/:slug/comments/:id...` URL, if it belongs to that article.
+#[cfg(feature = "diesel")]
+pub fn find_article_comment(url_slug: &str, comment_id: &str) -> Option<Comment> {
+    use schema::comments::dsl::*;
+
+    let connection = establish_connection();
+    match (find_article(url_slug), comment_id.parse::<i32>()) {
+        (Some(article), Ok(parsed_id)) => comments
+            .filter(id.eq(parsed_id).and(articleid.eq(article.id)))
+            .filter(deletedat.is_null().or(deleted.eq(true)))
+            .first(&connection)
+            .optional()
+            .unwrap(),
+        _ => None,
+    }
+}
+
+#[cfg(feature = "diesel")]
+fn edit_comment(params: (Comment, String, i32, Option<String>)) -> Option<CommentResult> {
+    use schema::{commentrevisions, comments};
+
+    let (comment, new_body, editor_id, held_because) = params;
+    let connection = establish_connection();
+    let now = Utc::now().naive_utc();
+
+    let edited_comment: Comment = connection
+        .transaction::<_, diesel::result::Error, _>(|| {
+            let revision = NewCommentRevision {
+                commentid: comment.id,
+                body: &comment.body,
+                editorid: editor_id,
+                revisedat: now,
+            };
+            diesel::insert(&revision)
+                .into(commentrevisions::table)
+                .execute(&connection)?;
+
+            let edited: Comment = diesel::update(comments::table.find(comment.id))
+                .set((
+                    comments::body.eq(&new_body),
+                    comments::updatedat.eq(Some(now)),
+                    comments::edited.eq(true),
+                    comments::hidden.eq(comment.hidden || held_because.is_some()),
+                ))
+                .get_result(&connection)?;
+            record_audit(Some(editor_id), "update", "comment", edited.id, audit_value(&comment), audit_value(&edited), &connection)?;
+            Ok(edited)
+        })
+        .expect("Error editing comment");
+    if let Some(reason) = held_because {
+        hold_for_review(edited_comment.articleid, Some(edited_comment.id), &reason);
+    }
+
+    Some(get_comment_result(edited_comment, editor_id))
+}
+
+#[cfg(feature = "diesel")]
+pub fn update_comment_handler(req: Request, mut res: Response, c: Captures) {
+    let (body, logged_id) = prepare_parameters(req);
+
+    let caps = c.unwrap();
+    let url_params = &caps[0];
+    let comment_id = url_params.split("/").last().unwrap();
+    let url_slug = url_params.replace("/api/articles/", "");
+    let url_slug = url_slug.split("/comments/").next().unwrap();
+    println!("update_comment_handler url_params: {}", url_params);
+
+    let incoming: UpdateComment = serde_json::from_str(&body).unwrap();
+
+    let comment = match find_article_comment(url_slug, comment_id) {
+        Some(comment) => comment,
+        None => {
+            send_error(res, StatusCode::NotFound, "comment not found");
+            return;
+        }
+    };
+    if let Err(denied) = check(logged_id, Action::UpdateComment, Some(comment.author)) {
+        send_denied(res, denied);
+        return;
+    }
+    if comment.deleted {
+        send_error(res, StatusCode::UnprocessableEntity, "a deleted comment cannot be edited");
+        return;
+    }
+    // the window binds authors, an admin may still correct a comment later
+    let edited_until = comment.createdAt + chrono::Duration::seconds(*COMMENT_EDIT_WINDOW_SECONDS);
+    if comment.author == logged_id && Utc::now().naive_utc() > edited_until {
+        send_error(res, StatusCode::Forbidden, "the time for editing this comment is over");
+        return;
+    }
+
+    let mut held_because = None;
+    if incoming.comment.body != comment.body {
+        let submission = Submission {
+            author: comment.author,
+            kind: ContentKind::Comment,
+            text: &incoming.comment.body,
+            edited: Some(comment.id),
+        };
+        match run_filters(&content_filters(), &submission) {
+            Verdict::Allow => {}
+            Verdict::Hold(reason) => held_because = Some(reason),
+            Verdict::Reject(reason) => {
+                send_error(res, StatusCode::UnprocessableEntity, &reason);
+                return;
+            }
+        }
+    }
+    if held_because.is_some() {
+        *res.status_mut() = StatusCode::Accepted;
+    }
+
+    process(res, edit_comment, (comment, incoming.comment.body, logged_id, held_because))
+}
+
+#[cfg(feature = "diesel")]
+fn get_comment_revisions(comment_id: i32) -> Vec<CommentRevisionDTO> {
+    use schema::commentrevisions::dsl::*;
+
+    let connection = establish_connection();
+    commentrevisions
+        .filter(commentid.eq(comment_id))
+        .order(id.asc())
+        .load::<CommentRevision>(&connection)
+        .expect("Error loading comment revisions")
+        .into_iter()
+        .map(|revision| {
+            CommentRevisionDTO {
+                id: revision.id,
+                body: revision.body,
+                editedBy: revision.editorid,
+                revisedAt: revision.revisedat,
+            }
+        })
+        .collect()
+}
+
+fn comment_revisions_result(_: CommentRevisionsResult) {}
+
+#[cfg(feature = "diesel")]
+pub fn comment_revisions_handler(req: Request, res: Response, c: Captures) {
+    let (_, logged_id) = prepare_parameters(req);
+
+    let caps = c.unwrap();
+    let url_params = caps[0].replace("/revisions", "");
+    let comment_id = url_params.split("/").last().unwrap();
+    let url_slug = url_params.replace("/api/articles/", "");
+    let url_slug = url_slug.split("/comments/").next().unwrap();
+
+    if let Err(denied) = check(logged_id, Action::ViewCommentRevisions, None) {
+        send_denied(res, denied);
+        return;
+    }
+    match find_article_comment(url_slug, comment_id) {
+        Some(comment) => process_container(res, comment_revisions_result, get_comment_revisions, comment.id),
+        None => send_error(res, StatusCode::NotFound, "comment not found"),
+    }
+}
+
 fn comments_result(_: CommentsResult) {}
 
+#[derive(Debug)]
+pub struct CommentListParams<'a> {
+    pub slug: &'a str,
+    pub viewer: i32,
+    pub as_tree: bool,
+    pub sort: CommentSort,
+    pub offset: i64,
+    pub limit: i64,
+}
+
+/// Comments shown on the article, tombstones of deleted comments do not count.
 #[cfg(feature = "diesel")]
-fn get_comments(url_slug: &str) -> Option<CommentsResult> {
+pub fn count_comments(article_id: i32) -> i64 {
+    use schema::comments::dsl::*;
+
     let connection = establish_connection();
+    comments
+        .filter(articleid.eq(article_id).and(deleted.eq(false)).and(hidden.eq(false)))
+        .filter(deletedat.is_null())
+        .count()
+        .get_result(&connection)
+        .expect("Error counting comments")
+}
+
+/// One page of threads: `limit` and `offset` count top level comments, each comes with all of its replies.
+#[cfg(feature = "diesel")]
+fn get_comments(params: CommentListParams) -> Option<CommentsResult> {
+    use schema::comments;
+    use diesel::expression::dsl::{all, any};
 
-    let article: Article = get_article(url_slug);
+    let connection = establish_connection();
 
-    let result : Vec<Comment> = <Comment as BelongingToDsl<&Article>>::belonging_to(&article)
-        .load::<Comment>(&connection)
-        .expect("Error loading comments");
+    let article = find_visible_article(params.slug, params.viewer)?;
+    let muted = muted_authors(params.viewer);
+    let muted_ids: Vec<i32> = muted.iter().cloned().collect();
+
+    let roots = comments::table
+        .filter(comments::articleid.eq(article.id).and(comments::parentid.is_null()))
+        .filter(comments::author.ne(all(&muted_ids)).or(comments::deleted.eq(true)))
+        .filter(comments::hidden.eq(false))
+        .filter(comments::deletedat.is_null().or(comments::deleted.eq(true)));
+    let mut result: Vec<Comment> = match params.sort {
+        CommentSort::Oldest => {
+            roots.order(comments::id.asc()).limit(params.limit).offset(params.offset).load::<Comment>(&connection)
+        }
+        CommentSort::Newest => {
+            roots.order(comments::id.desc()).limit(params.limit).offset(params.offset).load::<Comment>(&connection)
+        }
+        CommentSort::Top => {
+            // ranked here, the counts come from another table
+            let root_ids: Vec<i32> = roots
+                .order(comments::id.asc())
+                .select(comments::id)
+                .load(&connection)
+                .expect("Error loading comments");
+            let reacted: HashMap<i32, i64> = get_comment_reactions(&root_ids, params.viewer)
+                .into_iter()
+                .map(|(comment_id, counts)| (comment_id, counts.iter().map(|r| r.count).sum()))
+                .collect();
+            let mut ranked = root_ids;
+            ranked.sort_by_key(|comment_id| -reacted.get(comment_id).cloned().unwrap_or(0));
+            let page_ids: Vec<i32> = ranked
+                .into_iter()
+                .skip(params.offset as usize)
+                .take(params.limit as usize)
+                .collect();
+
+            comments::table
+                .filter(comments::id.eq(any(&page_ids)))
+                .load::<Comment>(&connection)
+                .map(|mut page| {
+                    page.sort_by_key(|c| page_ids.iter().position(|&page_id| page_id == c.id));
+                    page
+                })
+        }
+    }.expect("Error loading comments");
+
+    // one query per level of the threads, the depth is limited by comment_max_depth()
+    let mut parent_ids: Vec<i32> = result.iter().map(|c| c.id).collect();
+    while !parent_ids.is_empty() {
+        let replies: Vec<Comment> = comments::table
+            .filter(comments::parentid.eq(any(&parent_ids)).and(comments::hidden.eq(false)))
+            .filter(comments::deletedat.is_null().or(comments::deleted.eq(true)))
+            .order(comments::id.asc())
+            .load(&connection)
+            .expect("Error loading replies");
+        parent_ids = replies.iter().map(|c| c.id).collect();
+        result.extend(replies);
+    }
 
-    Some(CommentsResult { comments: result,})
+    let mut author_ids: Vec<i32> = result.iter().map(|c| c.author).collect();
+    author_ids.sort();
+    author_ids.dedup();
+    let profiles = get_profiles(&author_ids, params.viewer);
+    let comment_ids: Vec<i32> = result.iter().map(|c| c.id).collect();
+    let reactions = get_comment_reactions(&comment_ids, params.viewer);
+
+    // replies to a muted comment lose their context, so they go with it
+    let thread = prune_thread(thread_comments(result, &profiles, &reactions), &muted);
+
+    let result = if params.as_tree { thread } else { flatten_thread(thread) };
+    Some(CommentsResult {
+        comments: result,
+        commentsCount: count_comments(article.id),
+    })
 }
 
 pub fn get_comments_handler(req: Request, res: Response, c: Captures) {
     let (_, logged_id) = prepare_parameters(req);
 
     let caps = c.unwrap();
-    let slug = &caps[0].replace("/api/articles/", "").replace(
+    let url = &caps[0];
+    let slug = &url.split('?').next().unwrap().replace("/api/articles/", "").replace(
         "/comments",
         "",
     );
+    let as_tree = get_query_param(url, "view") == Some("tree");
+    let limit: i64 = get_query_param(url, "limit").and_then(|v| v.parse().ok()).unwrap_or(20);
+    let offset: i64 = get_query_param(url, "offset").and_then(|v| v.parse().ok()).unwrap_or(0);
+    let sort = CommentSort::from_name(get_query_param(url, "sort").unwrap_or("oldest"));
     println!("get_comments_handler slug: '{}'", slug);
 
     #[cfg(feature = "diesel")] {
-        process(res, get_comments, &slug)
+        let sort = match sort {
+            Some(sort) => sort,
+            None => {
+                send_error(res, StatusCode::UnprocessableEntity, "sort must be oldest, newest or top");
+                return;
+            }
+        };
+        if find_visible_article(slug, logged_id).is_none() {
+            send_error(res, StatusCode::NotFound, "article not found");
+            return;
+        }
+        let params = CommentListParams {
+            slug: slug,
+            viewer: logged_id,
+            as_tree: as_tree,
+            sort: sort,
+            offset: std::cmp::max(offset, 0),
+            limit: std::cmp::max(std::cmp::min(limit, MAX_COMMENTS_LIMIT), 0),
+        };
+        process(res, get_comments, params)
     }
 
     #[cfg(feature = "tiberius")]
@@ -248,7 +943,7 @@ fn add_comment_test() {
     let create_result: CommentResult = serde_json::from_str(&buffer).unwrap();
     let comment = create_result.comment;
     assert_eq!(comment.body, comment_body);
-    //assert_eq!(comment.author.username, user_name);
+    assert_eq!(comment.author.username, user_name);
 
     assert_eq!(res.status, hyper::Ok);
 
@@ -311,3 +1006,251 @@ fn delete_comment_test() {
     let comments: CommentsResult = serde_json::from_str(&buffer).unwrap();
     assert_eq!(comments.comments.len(), 0);
 }
+
+#[cfg(test)]
+#[test]
+fn delete_foreign_comment_test() {
+    let client = Client::new();
+
+    let (jwt, slug, _) = login_create_article(false);
+    let url = format!("http://localhost:6767/api/articles/{}/comments", slug);
+
+    let mut res = client
+        .post(&url)
+        .header(Authorization(Bearer { token: jwt }))
+        .body(r#"{"comment": {"body": "Only I may remove this."}}"#)
+        .send()
+        .unwrap();
+    let mut buffer = String::new();
+    res.read_to_string(&mut buffer).unwrap();
+    let comment_result: CommentResult = serde_json::from_str(&buffer).unwrap();
+
+    let other_jwt = login_other_jacob();
+    let url2 = format!("{}/{}", url, comment_result.comment.id);
+    let res = client
+        .delete(&url2)
+        .header(Authorization(Bearer { token: other_jwt }))
+        .body("")
+        .send()
+        .unwrap();
+    assert_eq!(res.status, StatusCode::Forbidden);
+
+    let mut res = client.get(&url).send().unwrap();
+    let mut buffer = String::new();
+    res.read_to_string(&mut buffer).unwrap();
+
+    let comments: CommentsResult = serde_json::from_str(&buffer).unwrap();
+    assert_eq!(comments.comments.len(), 1);
+}
+
+#[cfg(test)]
+#[test]
+fn thread_comments_test() {
+    let comment = |id: i32, parent_id: Option<i32>| Comment {
+        id: id,
+        createdAt: NaiveDate::from_ymd(2018, 1, 16).and_hms(9, 0, id as u32),
+        updatedAt: None,
+        body: format!("comment {}", id),
+        author: 1,
+        articleid: 1,
+        parentId: parent_id,
+        deleted: false,
+        edited: false,
+        hidden: false,
+        deletedAt: None,
+        deletedBy: None,
+    };
+
+    let mut profiles = HashMap::new();
+    profiles.insert(1, Profile {
+        username: "jacob".to_string(),
+        bio: None,
+        image: None,
+        following: false,
+    });
+
+    let thread = thread_comments(vec![
+        comment(1, None),
+        comment(2, Some(1)),
+        comment(3, None),
+        comment(4, Some(2)),
+        comment(5, Some(1)),
+    ], &profiles, &HashMap::new());
+    assert_eq!(thread.len(), 2);
+    assert_eq!(thread[0].replies.len(), 2);
+    assert_eq!(thread[0].replies[0].replies[0].depth, 2);
+    assert_eq!(thread[0].author.username, "jacob");
+
+    let flat: Vec<(i32, i32)> = flatten_thread(thread).iter().map(|c| (c.id, c.depth)).collect();
+    assert_eq!(flat, vec![(1, 0), (2, 1), (4, 2), (5, 1), (3, 0)]);
+}
+
+#[cfg(test)]
+#[test]
+fn reply_thread_test() {
+    let client = Client::new();
+
+    let (jwt, slug, _) = login_create_article(false);
+    let url = format!("http://localhost:6767/api/articles/{}/comments", slug);
+
+    let mut res = client
+        .post(&url)
+        .header(Authorization(Bearer { token: jwt.to_owned() }))
+        .body(r#"{"comment": {"body": "Where do dragons sleep?"}}"#)
+        .send()
+        .unwrap();
+    let mut buffer = String::new();
+    res.read_to_string(&mut buffer).unwrap();
+    let question: CommentResult = serde_json::from_str(&buffer).unwrap();
+
+    let body = format!(
+        r#"{{"comment": {{"body": "In caves.", "parentId": {}}}}}"#,
+        question.comment.id
+    );
+    let mut res = client
+        .post(&url)
+        .header(Authorization(Bearer { token: jwt.to_owned() }))
+        .body(&body)
+        .send()
+        .unwrap();
+    let mut buffer = String::new();
+    res.read_to_string(&mut buffer).unwrap();
+    assert_eq!(res.status, hyper::Ok);
+    let answer: CommentResult = serde_json::from_str(&buffer).unwrap();
+    assert_eq!(answer.comment.parentId, Some(question.comment.id));
+
+    let mut res = client.get(&format!("{}?view=tree", url)).send().unwrap();
+    let mut buffer = String::new();
+    res.read_to_string(&mut buffer).unwrap();
+    let tree: CommentsResult = serde_json::from_str(&buffer).unwrap();
+    assert_eq!(tree.comments.len(), 1);
+    assert_eq!(tree.comments[0].replies[0].id, answer.comment.id);
+    assert_eq!(tree.comments[0].replies[0].depth, 1);
+
+    // with the default tombstone policy the answer stays readable
+    let res = client
+        .delete(&format!("{}/{}", url, question.comment.id))
+        .header(Authorization(Bearer { token: jwt }))
+        .body("")
+        .send()
+        .unwrap();
+    assert_eq!(res.status, hyper::Ok);
+
+    let mut res = client.get(&url).send().unwrap();
+    let mut buffer = String::new();
+    res.read_to_string(&mut buffer).unwrap();
+    let flat: CommentsResult = serde_json::from_str(&buffer).unwrap();
+    assert_eq!(flat.comments.len(), 2);
+    assert!(flat.comments[0].deleted);
+    assert_eq!(flat.comments[0].body, "");
+    assert_eq!(flat.comments[1].depth, 1);
+}
+
+#[cfg(test)]
+#[test]
+fn edit_comment_test() {
+    let client = Client::new();
+
+    let (jwt, slug, _) = login_create_article(false);
+    let url = format!("http://localhost:6767/api/articles/{}/comments", slug);
+
+    let mut res = client
+        .post(&url)
+        .header(Authorization(Bearer { token: jwt.to_owned() }))
+        .body(r#"{"comment": {"body": "Dragons sleep in trees."}}"#)
+        .send()
+        .unwrap();
+    let mut buffer = String::new();
+    res.read_to_string(&mut buffer).unwrap();
+    let created: CommentResult = serde_json::from_str(&buffer).unwrap();
+    assert_eq!(created.comment.edited, false);
+
+    let comment_url = format!("{}/{}", url, created.comment.id);
+    let mut res = client
+        .put(&comment_url)
+        .header(Authorization(Bearer { token: jwt.to_owned() }))
+        .body(r#"{"comment": {"body": "Dragons sleep in caves."}}"#)
+        .send()
+        .unwrap();
+    let mut buffer = String::new();
+    res.read_to_string(&mut buffer).unwrap();
+    assert_eq!(res.status, hyper::Ok);
+    let edited: CommentResult = serde_json::from_str(&buffer).unwrap();
+    assert_eq!(edited.comment.edited, true);
+    assert_eq!(edited.comment.body, "Dragons sleep in caves.");
+
+    let revisions_url = format!("{}/revisions", comment_url);
+    let res = client
+        .get(&revisions_url)
+        .header(Authorization(Bearer { token: jwt }))
+        .send()
+        .unwrap();
+    assert_eq!(res.status, StatusCode::Forbidden);
+
+    let (_, admin_jwt) = register_admin();
+    let mut res = client
+        .get(&revisions_url)
+        .header(Authorization(Bearer { token: admin_jwt }))
+        .send()
+        .unwrap();
+    let mut buffer = String::new();
+    res.read_to_string(&mut buffer).unwrap();
+    assert_eq!(res.status, hyper::Ok);
+    let history: CommentRevisionsResult = serde_json::from_str(&buffer).unwrap();
+    assert_eq!(history.revisions.len(), 1);
+    assert_eq!(history.revisions[0].body, "Dragons sleep in trees.");
+}
+
+#[cfg(test)]
+#[test]
+fn comments_paging_test() {
+    let client = Client::new();
+
+    let (jwt, slug, _) = login_create_article(false);
+    let url = format!("http://localhost:6767/api/articles/{}/comments", slug);
+
+    let mut ids = Vec::new();
+    for body in &["First!", "Second.", "Third."] {
+        let mut res = client
+            .post(&url)
+            .header(Authorization(Bearer { token: jwt.to_owned() }))
+            .body(&format!(r#"{{"comment": {{"body": "{}"}}}}"#, body))
+            .send()
+            .unwrap();
+        let mut buffer = String::new();
+        res.read_to_string(&mut buffer).unwrap();
+        let created: CommentResult = serde_json::from_str(&buffer).unwrap();
+        ids.push(created.comment.id);
+    }
+
+    let mut res = client
+        .get(&format!("{}?sort=newest&limit=2", url))
+        .send()
+        .unwrap();
+    let mut buffer = String::new();
+    res.read_to_string(&mut buffer).unwrap();
+    assert_eq!(res.status, hyper::Ok);
+    let page: CommentsResult = serde_json::from_str(&buffer).unwrap();
+    assert_eq!(page.commentsCount, 3);
+    let page_ids: Vec<i32> = page.comments.iter().map(|c| c.id).collect();
+    assert_eq!(page_ids, vec![ids[2], ids[1]]);
+
+    let mut res = client
+        .get(&format!("{}?sort=newest&limit=2&offset=2", url))
+        .send()
+        .unwrap();
+    let mut buffer = String::new();
+    res.read_to_string(&mut buffer).unwrap();
+    let page: CommentsResult = serde_json::from_str(&buffer).unwrap();
+    assert_eq!(page.comments.len(), 1);
+    assert_eq!(page.comments[0].id, ids[0]);
+
+    let mut res = client
+        .get(&format!("http://localhost:6767/api/articles/{}", slug))
+        .send()
+        .unwrap();
+    let mut buffer = String::new();
+    res.read_to_string(&mut buffer).unwrap();
+    let article: ArticleResult = serde_json::from_str(&buffer).unwrap();
+    assert_eq!(article.article.commentsCount, 3);
+}
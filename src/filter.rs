@@ -0,0 +1,465 @@
+extern crate hyper;
+
+extern crate serde;
+extern crate serde_json;
+
+extern crate chrono;
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use super::*;
+
+/// What a filter thinks of a submission; held content is saved hidden and waits in the moderation queue.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Verdict {
+    Allow,
+    Hold(String),
+    Reject(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ContentKind {
+    Article,
+    Comment,
+}
+
+/// An article or comment about to be saved; for articles `text` is title, description and body, see `article_text`.
+/// `edited` is the id of the article or comment an edit changes, `None` for new content.
+#[derive(Debug)]
+pub struct Submission<'a> {
+    pub author: i32,
+    pub kind: ContentKind,
+    pub text: &'a str,
+    pub edited: Option<i32>,
+}
+
+/// Looks at a submission before it is saved.
+pub trait ContentFilter {
+    fn name(&self) -> &'static str;
+    fn check(&self, submission: &Submission) -> Verdict;
+}
+
+pub fn article_text(title: &str, description: &str, body: &str) -> String {
+    format!("{}\n{}\n{}", title, description, body)
+}
+
+/// Lowercase words, everything that is not a letter or digit separates them.
+pub fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_lowercase())
+        .collect()
+}
+
+pub fn count_links(text: &str) -> usize {
+    let with_scheme = text.matches("http://").count() + text.matches("https://").count();
+    let without_scheme = text.split_whitespace()
+        .filter(|word| word.trim_left_matches(|c: char| !c.is_alphanumeric()).starts_with("www."))
+        .count();
+    with_scheme + without_scheme
+}
+
+/// Runs every filter, the strictest verdict wins; the first rejection ends the run.
+pub fn run_filters(filters: &[Box<ContentFilter>], submission: &Submission) -> Verdict {
+    let mut verdict = Verdict::Allow;
+    for filter in filters {
+        match filter.check(submission) {
+            Verdict::Allow => {}
+            Verdict::Hold(reason) => {
+                println!("content filter '{}' holds: {}", filter.name(), reason);
+                if verdict == Verdict::Allow {
+                    verdict = Verdict::Hold(reason);
+                }
+            }
+            Verdict::Reject(reason) => {
+                println!("content filter '{}' rejects: {}", filter.name(), reason);
+                return Verdict::Reject(reason);
+            }
+        }
+    }
+    verdict
+}
+
+/// Holds submissions with `hold_at` links or more and rejects those with `reject_at` or more; 0 turns a limit off.
+pub struct LinkFilter {
+    pub hold_at: usize,
+    pub reject_at: usize,
+}
+
+impl ContentFilter for LinkFilter {
+    fn name(&self) -> &'static str {
+        "links"
+    }
+
+    fn check(&self, submission: &Submission) -> Verdict {
+        let links = count_links(submission.text);
+        if self.reject_at > 0 && links >= self.reject_at {
+            Verdict::Reject(format!("at most {} links are allowed", self.reject_at - 1))
+        } else if self.hold_at > 0 && links >= self.hold_at {
+            Verdict::Hold(format!("{} links", links))
+        } else {
+            Verdict::Allow
+        }
+    }
+}
+
+/// Rejects submissions with a banned word and holds those with a word moderators want to see first.
+pub struct WordFilter {
+    pub held: Vec<String>,
+    pub banned: Vec<String>,
+}
+
+impl ContentFilter for WordFilter {
+    fn name(&self) -> &'static str {
+        "words"
+    }
+
+    fn check(&self, submission: &Submission) -> Verdict {
+        let words = tokenize(submission.text);
+        let listed = |list: &[String]| list.iter().find(|w| words.contains(&w.to_lowercase())).cloned();
+
+        if listed(&self.banned[..]).is_some() {
+            Verdict::Reject("contains a banned word".to_string())
+        } else if let Some(word) = listed(&self.held[..]) {
+            Verdict::Hold(format!("contains '{}'", word))
+        } else {
+            Verdict::Allow
+        }
+    }
+}
+
+/// Rejects what the same author already posted within the last `window_seconds`, ignoring case and punctuation.
+pub struct DuplicateFilter {
+    pub window_seconds: i64,
+}
+
+#[cfg(feature = "diesel")]
+impl ContentFilter for DuplicateFilter {
+    fn name(&self) -> &'static str {
+        "duplicates"
+    }
+
+    fn check(&self, submission: &Submission) -> Verdict {
+        use schema::{articles, comments};
+
+        if self.window_seconds <= 0 {
+            return Verdict::Allow;
+        }
+        let connection = establish_connection();
+        let since = Utc::now().naive_utc() - chrono::Duration::seconds(self.window_seconds);
+        // an edit is no duplicate of what it replaces, ids start at 1
+        let edited_id = submission.edited.unwrap_or(0);
+
+        let recent: Vec<String> = match submission.kind {
+            ContentKind::Comment => comments::table
+                .inner_join(articles::table)
+                .filter(comments::author.eq(submission.author).and(comments::createdat.gt(since)))
+                .filter(comments::id.ne(edited_id))
+                .filter(articles::tenantid.eq(current_tenant()))
+                .select(comments::body)
+                .load(&connection)
+                .expect("Error loading recent comments"),
+            ContentKind::Article => articles::table
+                .filter(articles::author.eq(submission.author).and(articles::createdat.gt(since)))
+                .filter(articles::id.ne(edited_id))
+                .filter(articles::tenantid.eq(current_tenant()))
+                .select((articles::title, articles::description, articles::body))
+                .load::<(String, String, String)>(&connection)
+                .expect("Error loading recent articles")
+                .into_iter()
+                .map(|(title, description, body)| article_text(&title, &description, &body))
+                .collect(),
+        };
+
+        let words = tokenize(submission.text);
+        if recent.iter().any(|text| tokenize(text) == words) {
+            Verdict::Reject("you posted this already".to_string())
+        } else {
+            Verdict::Allow
+        }
+    }
+}
+
+/// Word counts of spam and of legitimate content, a naive Bayes classifier.
+#[derive(Debug, Default)]
+pub struct SpamModel {
+    spam_examples: usize,
+    ham_examples: usize,
+    spam_words: HashMap<String, usize>,
+    ham_words: HashMap<String, usize>,
+    spam_total: usize,
+    ham_total: usize,
+}
+
+impl SpamModel {
+    pub fn train(&mut self, text: &str, spam: bool) {
+        let (words, total) = if spam {
+            self.spam_examples += 1;
+            (&mut self.spam_words, &mut self.spam_total)
+        } else {
+            self.ham_examples += 1;
+            (&mut self.ham_words, &mut self.ham_total)
+        };
+        for word in tokenize(text) {
+            *words.entry(word).or_insert(0) += 1;
+            *total += 1;
+        }
+    }
+
+    /// Spam and legitimate examples the model was trained with.
+    pub fn examples(&self) -> (usize, usize) {
+        (self.spam_examples, self.ham_examples)
+    }
+
+    pub fn spam_probability(&self, text: &str) -> f64 {
+        if self.spam_examples == 0 || self.ham_examples == 0 {
+            return 0.5;
+        }
+        let vocabulary = self.spam_words.len() +
+            self.ham_words.keys().filter(|w| !self.spam_words.contains_key(*w)).count();
+        let examples = (self.spam_examples + self.ham_examples) as f64;
+
+        // sums of logarithms, a product of many small probabilities would underflow
+        let mut spam_log = (self.spam_examples as f64 / examples).ln();
+        let mut ham_log = (self.ham_examples as f64 / examples).ln();
+        for word in tokenize(text) {
+            let in_spam = self.spam_words.get(&word).cloned().unwrap_or(0);
+            let in_ham = self.ham_words.get(&word).cloned().unwrap_or(0);
+            spam_log += ((in_spam + 1) as f64 / (self.spam_total + vocabulary) as f64).ln();
+            ham_log += ((in_ham + 1) as f64 / (self.ham_total + vocabulary) as f64).ln();
+        }
+        1.0 / (1.0 + (ham_log - spam_log).exp())
+    }
+}
+
+lazy_static! {
+    // one per tenant, each blog's moderators teach their own
+    static ref SPAM_MODELS: Mutex<HashMap<i32, SpamModel>> = Mutex::new(HashMap::new());
+}
+
+/// Classifies with what moderators decided so far; allows everything until it saw `min_examples` of both kinds.
+pub struct BayesFilter {
+    pub hold_at: f64,
+    pub reject_at: f64,
+    pub min_examples: usize,
+}
+
+#[cfg(feature = "diesel")]
+impl ContentFilter for BayesFilter {
+    fn name(&self) -> &'static str {
+        "bayes"
+    }
+
+    fn check(&self, submission: &Submission) -> Verdict {
+        let mut models = SPAM_MODELS.lock().unwrap();
+        let tenant_id = current_tenant();
+        let model = models.entry(tenant_id).or_insert_with(|| load_spam_model(tenant_id));
+
+        let (spam_examples, ham_examples) = model.examples();
+        if spam_examples < self.min_examples || ham_examples < self.min_examples {
+            return Verdict::Allow;
+        }
+        let probability = model.spam_probability(submission.text);
+        if probability >= self.reject_at {
+            Verdict::Reject("this looks like spam".to_string())
+        } else if probability >= self.hold_at {
+            Verdict::Hold(format!("spam probability {:.2}", probability))
+        } else {
+            Verdict::Allow
+        }
+    }
+}
+
+#[cfg(feature = "diesel")]
+fn load_spam_model(tenant_id: i32) -> SpamModel {
+    use schema::filterexamples::dsl::*;
+
+    let connection = establish_connection();
+    let examples: Vec<(String, bool)> = filterexamples
+        .filter(tenantid.eq(tenant_id))
+        .select((body, spam))
+        .load(&connection)
+        .expect("Error loading filter examples");
+
+    let mut model = SpamModel::default();
+    for &(ref text, is_spam) in &examples {
+        model.train(text, is_spam);
+    }
+    model
+}
+
+/// Moderator decisions teach the classifier: upheld spam reports are spam, dismissed reports are legitimate content.
+#[cfg(feature = "diesel")]
+pub fn learn_from_review(report: &Report, action: ReviewAction, connection: &PgConnection) {
+    use schema::{articles, comments, filterexamples};
+
+    let is_spam = match action {
+        ReviewAction::Resolve if report.reason == "spam" => true,
+        ReviewAction::Dismiss => false,
+        ReviewAction::Resolve => return,
+    };
+    let text: String = match report.commentid {
+        Some(comment_id) => comments::table
+            .find(comment_id)
+            .select(comments::body)
+            .first(connection)
+            .expect("Error loading reported comment"),
+        None => {
+            let (title, description, body): (String, String, String) = articles::table
+                .find(report.articleid)
+                .select((articles::title, articles::description, articles::body))
+                .first(connection)
+                .expect("Error loading reported article");
+            article_text(&title, &description, &body)
+        }
+    };
+
+    let example = NewFilterExample {
+        body: &text,
+        spam: is_spam,
+        reportid: Some(report.id),
+        createdat: Utc::now().naive_utc(),
+        tenantid: report.tenantid,
+    };
+    diesel::insert(&example)
+        .into(filterexamples::table)
+        .execute(connection)
+        .expect("Error saving filter example");
+
+    // a model not loaded yet reads the example from the table
+    if let Some(model) = SPAM_MODELS.lock().unwrap().get_mut(&report.tenantid) {
+        model.train(&text, is_spam);
+    }
+}
+
+/// The filters configured in the `[filter]` section, cheapest first.
+#[cfg(feature = "diesel")]
+pub fn content_filters() -> Vec<Box<ContentFilter>> {
+    let mut filters: Vec<Box<ContentFilter>> = Vec::new();
+    if !*FILTER_ENABLED {
+        return filters;
+    }
+    filters.push(Box::new(LinkFilter {
+        hold_at: *FILTER_HOLD_LINKS,
+        reject_at: *FILTER_REJECT_LINKS,
+    }));
+    filters.push(Box::new(WordFilter {
+        held: FILTER_HELD_WORDS.clone(),
+        banned: FILTER_BANNED_WORDS.clone(),
+    }));
+    filters.push(Box::new(DuplicateFilter { window_seconds: *FILTER_DUPLICATE_WINDOW_SECONDS }));
+    filters.push(Box::new(BayesFilter {
+        hold_at: *FILTER_SPAM_HOLD,
+        reject_at: *FILTER_SPAM_REJECT,
+        min_examples: *FILTER_MIN_EXAMPLES,
+    }));
+    filters
+}
+
+#[cfg(test)]
+#[test]
+fn run_filters_test() {
+    let filters: Vec<Box<ContentFilter>> = vec![
+        Box::new(LinkFilter { hold_at: 2, reject_at: 4 }),
+        Box::new(WordFilter {
+            held: vec!["casino".to_string()],
+            banned: vec!["Viagra".to_string()],
+        }),
+    ];
+    let verdict = |text: &str| {
+        run_filters(&filters, &Submission { author: 1, kind: ContentKind::Comment, text: text, edited: None })
+    };
+
+    assert_eq!(verdict("See http://example.com for dragons."), Verdict::Allow);
+    assert_eq!(verdict("http://a.example and www.b.example"), Verdict::Hold("2 links".to_string()));
+    assert_eq!(
+        verdict("http://a https://b (www.c) http://d"),
+        Verdict::Reject("at most 3 links are allowed".to_string())
+    );
+    assert_eq!(verdict("Win at the Casino!"), Verdict::Hold("contains 'casino'".to_string()));
+    assert_eq!(verdict("casino viagra"), Verdict::Reject("contains a banned word".to_string()));
+    assert_eq!(verdict("casinos"), Verdict::Allow);
+}
+
+#[cfg(test)]
+#[test]
+fn spam_model_test() {
+    let mut model = SpamModel::default();
+    assert_eq!(model.spam_probability("cheap pills"), 0.5);
+
+    model.train("Cheap pills, buy now!", true);
+    model.train("Buy cheap watches now", true);
+    model.train("How do dragons sleep?", false);
+    model.train("Dragons sleep in caves, I think.", false);
+    assert_eq!(model.examples(), (2, 2));
+
+    assert!(model.spam_probability("buy cheap pills") > 0.9);
+    assert!(model.spam_probability("where do dragons sleep") < 0.1);
+}
+
+#[cfg(test)]
+#[test]
+fn filtered_comment_test() {
+    let client = Client::new();
+    let (jwt, slug, _) = login_create_article(false);
+    let url = format!("http://localhost:6767/api/articles/{}/comments", slug);
+
+    let links = |count: usize| {
+        (0..count).map(|i| format!("http://dragons.example/{}", i)).collect::<Vec<String>>().join(" ")
+    };
+
+    let res = client
+        .post(&url)
+        .header(Authorization(Bearer { token: jwt.to_owned() }))
+        .body(&format!(r#"{{"comment": {{"body": "{}"}}}}"#, links(*FILTER_REJECT_LINKS)))
+        .send()
+        .unwrap();
+    assert_eq!(res.status, hyper::status::StatusCode::UnprocessableEntity);
+
+    let res = client
+        .post(&url)
+        .header(Authorization(Bearer { token: jwt.to_owned() }))
+        .body(&format!(r#"{{"comment": {{"body": "{}"}}}}"#, links(*FILTER_HOLD_LINKS)))
+        .send()
+        .unwrap();
+    assert_eq!(res.status, hyper::status::StatusCode::Accepted);
+
+    for expected in &[hyper::Ok, hyper::status::StatusCode::UnprocessableEntity] {
+        let res = client
+            .post(&url)
+            .header(Authorization(Bearer { token: jwt.to_owned() }))
+            .body(r#"{"comment": {"body": "Only once, please."}}"#)
+            .send()
+            .unwrap();
+        assert_eq!(res.status, *expected);
+    }
+
+    // the held comment waits for a moderator
+    let mut res = client.get(&url).send().unwrap();
+    let mut buffer = String::new();
+    res.read_to_string(&mut buffer).unwrap();
+    let comments: CommentsResult = serde_json::from_str(&buffer).unwrap();
+    assert_eq!(comments.comments.len(), 1);
+
+    // an edit passes the same filters
+    let comment_url = format!("{}/{}", url, comments.comments[0].id);
+    for &(count, expected) in &[
+        (*FILTER_REJECT_LINKS, hyper::status::StatusCode::UnprocessableEntity),
+        (*FILTER_HOLD_LINKS, hyper::status::StatusCode::Accepted),
+    ] {
+        let res = client
+            .put(&comment_url)
+            .header(Authorization(Bearer { token: jwt.to_owned() }))
+            .body(&format!(r#"{{"comment": {{"body": "{}"}}}}"#, links(count)))
+            .send()
+            .unwrap();
+        assert_eq!(res.status, expected);
+    }
+
+    let mut res = client.get(&url).send().unwrap();
+    let mut buffer = String::new();
+    res.read_to_string(&mut buffer).unwrap();
+    let comments: CommentsResult = serde_json::from_str(&buffer).unwrap();
+    assert!(comments.comments.is_empty());
+}
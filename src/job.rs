@@ -0,0 +1,306 @@
+extern crate hyper;
+
+extern crate serde;
+extern crate serde_json;
+
+extern crate chrono;
+
+use std::time::Duration;
+
+use chrono::Duration as ChronoDuration;
+
+use super::*;
+
+static PENDING: &'static str = "pending";
+static DONE: &'static str = "done";
+static FAILED: &'static str = "failed";
+
+pub static PUBLISH_ARTICLE: &'static str = "publish_article";
+pub static PURGE_ARTICLE: &'static str = "purge_article";
+pub static PURGE_COMMENT: &'static str = "purge_comment";
+
+const JOB_BATCH_SIZE: i64 = 20;
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+#[allow(non_snake_case)]
+struct PublishArticleJob {
+    articleId: i32,
+    publishAt: NaiveDateTime,
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+#[allow(non_snake_case)]
+struct PurgeJob {
+    id: i32,
+    deletedAt: NaiveDateTime,
+}
+
+pub fn job_retry_delay_seconds(attempts: i32) -> i64 {
+    *JOB_RETRY_SECONDS * std::cmp::max(attempts, 1) as i64
+}
+
+/// Stores a job to run at `run_at`; it runs at least once, so running it twice must do no harm.
+#[cfg(feature = "diesel")]
+pub fn enqueue_job<T>(kind: &'static str, payload: &T, run_at: NaiveDateTime, connection: &PgConnection)
+where
+    T: serde::Serialize,
+{
+    use schema::jobs;
+
+    let payload = serde_json::to_string(payload).unwrap();
+    let job = NewJob {
+        kind: kind,
+        payload: &payload,
+        status: PENDING,
+        runat: run_at,
+        createdat: Utc::now().naive_utc(),
+    };
+    diesel::insert(&job)
+        .into(jobs::table)
+        .execute(connection)
+        .expect("Error saving job");
+}
+
+/// Publishes the draft at `publish_at`, unless it is rescheduled or published before.
+#[cfg(feature = "diesel")]
+pub fn schedule_publication(article_id: i32, publish_at: NaiveDateTime, connection: &PgConnection) {
+    let payload = PublishArticleJob {
+        articleId: article_id,
+        publishAt: publish_at,
+    };
+    enqueue_job(PUBLISH_ARTICLE, &payload, publish_at, connection);
+}
+
+/// Purges the article or comment deleted at `deleted_at` once the trash retention is over, unless it was restored.
+#[cfg(feature = "diesel")]
+pub fn schedule_purge(kind: &'static str, id: i32, deleted_at: NaiveDateTime, connection: &PgConnection) {
+    let payload = PurgeJob {
+        id: id,
+        deletedAt: deleted_at,
+    };
+    enqueue_job(kind, &payload, purge_time(deleted_at), connection);
+}
+
+#[cfg(feature = "diesel")]
+fn publish_scheduled_article(payload: &str) -> Result<(), String> {
+    use schema::articles::dsl::*;
+
+    let job: PublishArticleJob = serde_json::from_str(payload).map_err(|e| e.to_string())?;
+    let connection = establish_connection();
+    let article: Option<Article> = articles
+        .find(job.articleId)
+        .first(&connection)
+        .optional()
+        .map_err(|e| e.to_string())?;
+
+    // a moved schedule left this job behind, the job for the new time does the work
+    match article {
+        Some(article) => {
+            if article.status == ArticleStatus::Draft.name() && article.publishAt == Some(job.publishAt) &&
+                article.deletedAt.is_none() {
+                // the runner serves every tenant, the article is looked up again within its own
+                set_current_tenant(article.tenantid);
+                publish_now(article);
+            }
+            Ok(())
+        }
+        None => Ok(()),
+    }
+}
+
+// a restore, or a later delete that started a new retention period, left this job behind
+#[cfg(feature = "diesel")]
+fn purge_trashed_article(payload: &str) -> Result<(), String> {
+    use schema::articles::dsl::*;
+
+    let job: PurgeJob = serde_json::from_str(payload).map_err(|e| e.to_string())?;
+    let connection = establish_connection();
+    let article: Option<Article> = articles
+        .find(job.id)
+        .first(&connection)
+        .optional()
+        .map_err(|e| e.to_string())?;
+
+    match article {
+        Some(article) => {
+            if article.deletedAt == Some(job.deletedAt) {
+                // the audit entry of the purge belongs to the article's tenant
+                set_current_tenant(article.tenantid);
+                purge_article(article);
+            }
+            Ok(())
+        }
+        None => Ok(()),
+    }
+}
+
+#[cfg(feature = "diesel")]
+fn purge_trashed_comment(payload: &str) -> Result<(), String> {
+    use schema::comments::dsl::*;
+
+    let job: PurgeJob = serde_json::from_str(payload).map_err(|e| e.to_string())?;
+    let connection = establish_connection();
+    let comment: Option<Comment> = comments
+        .find(job.id)
+        .first(&connection)
+        .optional()
+        .map_err(|e| e.to_string())?;
+
+    match comment {
+        Some(comment) => {
+            if comment.deletedAt == Some(job.deletedAt) {
+                use schema::articles;
+
+                let article_tenant: i32 = articles::table
+                    .find(comment.articleid)
+                    .select(articles::tenantid)
+                    .first(&connection)
+                    .map_err(|e| e.to_string())?;
+                set_current_tenant(article_tenant);
+                purge_comment(comment);
+            }
+            Ok(())
+        }
+        None => Ok(()),
+    }
+}
+
+#[cfg(feature = "diesel")]
+fn run_job(kind: &str, payload: &str) -> Result<(), String> {
+    match kind {
+        "publish_article" => publish_scheduled_article(payload),
+        "purge_article" => purge_trashed_article(payload),
+        "purge_comment" => purge_trashed_comment(payload),
+        _ => Err(format!("unknown job kind '{}'", kind)),
+    }
+}
+
+#[cfg(feature = "diesel")]
+fn finish_job(job: &Job, attempts: i32, outcome: Result<(), String>, connection: &PgConnection) {
+    use schema::jobs;
+
+    let now = Utc::now().naive_utc();
+    let (status, run_at, finished_at, last_error) = match outcome {
+        Ok(()) => (DONE, job.runat, Some(now), None),
+        Err(error) => {
+            println!("job {} ({}) failed: {}", job.id, job.kind, error);
+            if attempts >= *JOB_MAX_ATTEMPTS {
+                (FAILED, job.runat, Some(now), Some(error))
+            } else {
+                (PENDING, now + ChronoDuration::seconds(job_retry_delay_seconds(attempts)), None, Some(error))
+            }
+        }
+    };
+    diesel::update(jobs::table.find(job.id))
+        .set((
+            jobs::status.eq(status),
+            jobs::runat.eq(run_at),
+            jobs::lockeduntil.eq(None::<NaiveDateTime>),
+            jobs::finishedat.eq(finished_at),
+            jobs::lasterror.eq(last_error),
+        ))
+        .execute(connection)
+        .expect("Error updating job");
+}
+
+#[cfg(feature = "diesel")]
+fn run_due_jobs() {
+    use schema::jobs;
+    use std::panic;
+
+    let connection = establish_connection();
+    let now = Utc::now().naive_utc();
+
+    let due: Vec<Job> = jobs::table
+        .filter(jobs::status.eq(PENDING).and(jobs::runat.le(now)))
+        .filter(jobs::lockeduntil.is_null().or(jobs::lockeduntil.le(now)))
+        .order(jobs::runat.asc())
+        .limit(JOB_BATCH_SIZE)
+        .load(&connection)
+        .expect("Error loading due jobs");
+
+    for job in due {
+        // the lock is a lease: a server that dies mid-job leaves it to be picked up once the lease ran out
+        let lease = Utc::now().naive_utc() + ChronoDuration::seconds(*JOB_LEASE_SECONDS);
+        let attempts = job.attempts + 1;
+        let claimed = diesel::update(jobs::table.filter(jobs::id.eq(job.id).and(jobs::attempts.eq(job.attempts))))
+            .set((jobs::lockeduntil.eq(Some(lease)), jobs::attempts.eq(attempts)))
+            .execute(&connection)
+            .expect("Error claiming job");
+        if claimed == 0 {
+            continue;
+        }
+
+        let outcome = match panic::catch_unwind(|| run_job(&job.kind, &job.payload)) {
+            Ok(outcome) => outcome,
+            Err(_) => Err("the job panicked".to_string()),
+        };
+        finish_job(&job, attempts, outcome, &connection);
+    }
+}
+
+/// Runs due jobs in the background for as long as the server runs.
+#[cfg(feature = "diesel")]
+pub fn start_job_runner() {
+    use std::panic;
+    use std::thread;
+
+    thread::spawn(|| loop {
+        if panic::catch_unwind(run_due_jobs).is_err() {
+            println!("job round failed");
+        }
+        thread::sleep(Duration::from_secs(*JOB_POLL_SECONDS));
+    });
+}
+
+#[cfg(test)]
+#[test]
+fn scheduled_publish_test() {
+    use std::thread;
+
+    let client = Client::new();
+    let (_, email) = register_jacob();
+    let jwt = login_jacob(email, JACOB_PASSWORD.to_string());
+
+    let article_json = |publish_at: NaiveDateTime| {
+        format!(
+            r#"{{"article": {{"title": "Dragons at dawn {}", "description": "Later", "body": "Wait for it.", "publishAt": "{}"}}}}"#,
+            since_the_epoch(),
+            publish_at.format("%Y-%m-%dT%H:%M:%S")
+        )
+    };
+
+    let res = client
+        .post("http://localhost:6767/api/articles")
+        .header(Authorization(Bearer { token: jwt.to_owned() }))
+        .body(&article_json(Utc::now().naive_utc() - ChronoDuration::seconds(60)))
+        .send()
+        .unwrap();
+    assert_eq!(res.status, hyper::status::StatusCode::UnprocessableEntity);
+
+    let mut res = client
+        .post("http://localhost:6767/api/articles")
+        .header(Authorization(Bearer { token: jwt }))
+        .body(&article_json(Utc::now().naive_utc() + ChronoDuration::seconds(2)))
+        .send()
+        .unwrap();
+    let mut buffer = String::new();
+    res.read_to_string(&mut buffer).unwrap();
+    assert_eq!(res.status, hyper::Ok);
+    let scheduled = serde_json::from_str::<ArticleResult>(&buffer).unwrap().article;
+    assert_eq!(scheduled.status, "draft");
+    assert!(scheduled.publishAt.is_some());
+
+    let url = format!("http://localhost:6767/api/articles/{}", scheduled.slug);
+    let mut published = false;
+    for _ in 0..(4 * *JOB_POLL_SECONDS + 4) {
+        if client.get(&url).send().unwrap().status == hyper::Ok {
+            published = true;
+            break;
+        }
+        thread::sleep(Duration::from_secs(1));
+    }
+    assert!(published);
+}
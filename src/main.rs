@@ -75,8 +75,9 @@ use std::env;
 use std::path::PathBuf;
 
 use hyper::server::{Server, Request, Response};
+use hyper::status::StatusCode;
 use reroute::{RouterBuilder, Captures};
-use hyper::header::{AccessControlAllowOrigin, AccessControlAllowHeaders};
+use hyper::header::{AccessControlAllowOrigin, AccessControlAllowHeaders, Location};
 
 use std::time::{SystemTime, UNIX_EPOCH};
 
@@ -135,14 +136,18 @@ struct UpdateArticle {
 
 #[derive(Serialize, Deserialize)]
 #[derive(Debug)]
+#[allow(non_snake_case)]
 struct UpdateArticleDetail {
     title: Option<String>,
     description: Option<String>,
     body: Option<String>,
+    status: Option<String>,
+    publishAt: Option<NaiveDateTime>,
 }
 
 #[derive(Serialize, Deserialize)]
 #[derive(Debug)]
+#[derive(Clone)]
 pub struct Profile {
     username: String,
     bio: Option<String>,
@@ -156,23 +161,39 @@ pub struct ProfileResult {
     profile: Profile,
 }
 
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+pub struct ProfilesResult {
+    profiles: Vec<Profile>,
+}
+
+impl Container<Profile> for ProfilesResult {
+    fn create_new_with_items(profiles: Vec<Profile>) -> ProfilesResult {
+        ProfilesResult { profiles: profiles }
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 #[derive(Debug)]
 #[allow(non_snake_case)]
 pub struct CommentResult {
-    pub comment: Comment,
+    pub comment: CommentDTO,
 }
 
 #[derive(Serialize, Deserialize)]
 #[derive(Debug)]
 #[allow(non_snake_case)]
 struct CommentsResult {
-    pub comments: Vec<Comment>,
+    pub comments: Vec<ThreadedComment>,
+    pub commentsCount: i64,
 }
 
-impl Container<Comment> for CommentsResult {
-    fn create_new_with_items(comments: Vec<Comment>) -> CommentsResult {
-        CommentsResult { comments: comments }
+impl Container<ThreadedComment> for CommentsResult {
+    fn create_new_with_items(comments: Vec<ThreadedComment>) -> CommentsResult {
+        CommentsResult {
+            commentsCount: comments.len() as i64,
+            comments: comments,
+        }
     }
 }
 
@@ -221,6 +242,17 @@ struct Login {
 #[derive(Debug, Deserialize)]
 struct Config {
     database: Option<DatabaseConfig>,
+    security: Option<SecurityConfig>,
+    account: Option<AccountConfig>,
+    stream: Option<StreamConfig>,
+    webhooks: Option<WebhookConfig>,
+    comments: Option<CommentsConfig>,
+    reactions: Option<ReactionsConfig>,
+    moderation: Option<ModerationConfig>,
+    filter: Option<FilterConfig>,
+    jobs: Option<JobsConfig>,
+    trash: Option<TrashConfig>,
+    tenants: Option<Vec<TenantConfig>>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -233,6 +265,89 @@ struct DatabaseConfig {
     DATABASE_URL: Option<String>,
 }
 
+#[derive(Debug, Deserialize, Default)]
+struct SecurityConfig {
+    max_failed_logins: Option<i32>,
+    ip_max_failed_logins: Option<i32>,
+    lockout_seconds: Option<i64>,
+    max_lockout_seconds: Option<i64>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+struct AccountConfig {
+    deletion_policy: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+struct StreamConfig {
+    max_clients: Option<usize>,
+    keep_alive_seconds: Option<u64>,
+    replay_events: Option<usize>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+struct WebhookConfig {
+    max_attempts: Option<i32>,
+    retry_seconds: Option<i64>,
+    poll_seconds: Option<u64>,
+    timeout_seconds: Option<u64>,
+    allowed_hosts: Option<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+struct CommentsConfig {
+    max_depth: Option<i32>,
+    delete_policy: Option<String>,
+    edit_window_seconds: Option<i64>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+struct ReactionsConfig {
+    allowed: Option<Vec<String>>,
+    on_articles: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+struct ModerationConfig {
+    report_threshold: Option<i64>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+struct FilterConfig {
+    enabled: Option<bool>,
+    hold_links: Option<usize>,
+    reject_links: Option<usize>,
+    held_words: Option<Vec<String>>,
+    banned_words: Option<Vec<String>>,
+    duplicate_window_seconds: Option<i64>,
+    spam_hold: Option<f64>,
+    spam_reject: Option<f64>,
+    min_examples: Option<usize>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+struct TrashConfig {
+    retention_days: Option<i64>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct TenantConfig {
+    id: i32,
+    name: String,
+    hosts: Option<Vec<String>>,
+    path_prefix: Option<String>,
+    open_registration: Option<bool>,
+    comment_max_depth: Option<i32>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+struct JobsConfig {
+    poll_seconds: Option<u64>,
+    max_attempts: Option<i32>,
+    retry_seconds: Option<i64>,
+    lease_seconds: Option<i64>,
+}
+
 #[derive(Serialize, Deserialize)]
 #[derive(Debug)]
 struct UpdateUser {
@@ -250,6 +365,7 @@ struct AddComment {
 #[allow(non_snake_case)]
 struct AddCommentDetail {
     body: String,
+    parentId: Option<i32>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -292,6 +408,9 @@ struct GetTagsResult {
 
 static CONFIG_FILE_NAME: &'static str = r#"conduit.toml"#;
 
+// worker threads for regular requests, every open event stream holds one more
+static REQUEST_THREADS: usize = 16;
+
 #[cfg(feature = "tiberius")]
 lazy_static! {
     pub static ref CONNECTION_STRING : String = match get_database_config().connection_string {
@@ -314,8 +433,58 @@ lazy_static! {
             None => panic!("DATABASE_URL not present in [database] section in {}", CONFIG_FILE_NAME),
         };  
 }
+lazy_static! {
+    pub static ref MAX_FAILED_LOGINS : i32 = get_security_config().max_failed_logins.unwrap_or(5);
+    pub static ref IP_MAX_FAILED_LOGINS : i32 = get_security_config().ip_max_failed_logins.unwrap_or(20);
+    pub static ref LOCKOUT_SECONDS : i64 = get_security_config().lockout_seconds.unwrap_or(30);
+    pub static ref MAX_LOCKOUT_SECONDS : i64 = get_security_config().max_lockout_seconds.unwrap_or(3600);
+    pub static ref ACCOUNT_DELETION_POLICY : DeletionPolicy = match get_account_config().deletion_policy {
+            Some(name) => match DeletionPolicy::from_name(&name) {
+                Some(policy) => policy,
+                None => panic!("unknown deletion_policy '{}' in [account] section in {}", name, CONFIG_FILE_NAME),
+            },
+            None => DeletionPolicy::Anonymize,
+        };
+    pub static ref STREAM_MAX_CLIENTS : usize = get_stream_config().max_clients.unwrap_or(32);
+    pub static ref STREAM_KEEP_ALIVE_SECONDS : u64 = get_stream_config().keep_alive_seconds.unwrap_or(15);
+    pub static ref STREAM_REPLAY_EVENTS : usize = get_stream_config().replay_events.unwrap_or(1000);
+    pub static ref WEBHOOK_MAX_ATTEMPTS : i32 = get_webhook_config().max_attempts.unwrap_or(6);
+    pub static ref WEBHOOK_RETRY_SECONDS : i64 = get_webhook_config().retry_seconds.unwrap_or(30);
+    pub static ref WEBHOOK_POLL_SECONDS : u64 = get_webhook_config().poll_seconds.unwrap_or(5);
+    pub static ref WEBHOOK_TIMEOUT_SECONDS : u64 = get_webhook_config().timeout_seconds.unwrap_or(10);
+    pub static ref WEBHOOK_ALLOWED_HOSTS : Vec<String> = get_webhook_config().allowed_hosts.unwrap_or_default();
+    pub static ref COMMENT_MAX_DEPTH : i32 = get_comments_config().max_depth.unwrap_or(5);
+    pub static ref COMMENT_DELETE_POLICY : CommentDeletePolicy = match get_comments_config().delete_policy {
+            Some(name) => match CommentDeletePolicy::from_name(&name) {
+                Some(policy) => policy,
+                None => panic!("unknown delete_policy '{}' in [comments] section in {}", name, CONFIG_FILE_NAME),
+            },
+            None => CommentDeletePolicy::Tombstone,
+        };
+    pub static ref COMMENT_EDIT_WINDOW_SECONDS : i64 = get_comments_config().edit_window_seconds.unwrap_or(900);
+    pub static ref REACTIONS : Vec<String> = get_reactions_config().allowed.unwrap_or_else(|| {
+            ["+1", "-1", "heart", "laugh", "hooray", "confused"].iter().map(|r| r.to_string()).collect()
+        });
+    pub static ref ARTICLE_REACTIONS : bool = get_reactions_config().on_articles.unwrap_or(true);
+    pub static ref REPORT_THRESHOLD : i64 = get_moderation_config().report_threshold.unwrap_or(3);
+    pub static ref FILTER_ENABLED : bool = get_filter_config().enabled.unwrap_or(true);
+    pub static ref FILTER_HOLD_LINKS : usize = get_filter_config().hold_links.unwrap_or(3);
+    pub static ref FILTER_REJECT_LINKS : usize = get_filter_config().reject_links.unwrap_or(10);
+    pub static ref FILTER_HELD_WORDS : Vec<String> = get_filter_config().held_words.unwrap_or_default();
+    pub static ref FILTER_BANNED_WORDS : Vec<String> = get_filter_config().banned_words.unwrap_or_default();
+    pub static ref FILTER_DUPLICATE_WINDOW_SECONDS : i64 = get_filter_config().duplicate_window_seconds.unwrap_or(600);
+    pub static ref FILTER_SPAM_HOLD : f64 = get_filter_config().spam_hold.unwrap_or(0.8);
+    pub static ref FILTER_SPAM_REJECT : f64 = get_filter_config().spam_reject.unwrap_or(0.99);
+    pub static ref FILTER_MIN_EXAMPLES : usize = get_filter_config().min_examples.unwrap_or(20);
+    pub static ref JOB_POLL_SECONDS : u64 = get_jobs_config().poll_seconds.unwrap_or(5);
+    pub static ref JOB_MAX_ATTEMPTS : i32 = get_jobs_config().max_attempts.unwrap_or(5);
+    pub static ref JOB_RETRY_SECONDS : i64 = get_jobs_config().retry_seconds.unwrap_or(60);
+    pub static ref JOB_LEASE_SECONDS : i64 = get_jobs_config().lease_seconds.unwrap_or(300);
+    pub static ref TRASH_RETENTION_DAYS : i64 = get_trash_config().retention_days.unwrap_or(30);
+    pub static ref TENANTS : Vec<TenantConfig> = get_config().tenants.unwrap_or_default();
+}
 
-fn get_database_config() -> DatabaseConfig {
+fn get_config() -> Config {
 
     let env_config = match env::var("DATABASECONFIG") {
         Ok(lang) => lang,
@@ -342,7 +511,11 @@ fn get_database_config() -> DatabaseConfig {
     let toml_str: &str = &content;
     let config: Config = toml::from_str(toml_str).unwrap();
 
-    let database_config: DatabaseConfig = match config.database {
+    config
+}
+
+fn get_database_config() -> DatabaseConfig {
+    let database_config: DatabaseConfig = match get_config().database {
         Some(database_config) => database_config,
         None => panic!("database not present in {}", CONFIG_FILE_NAME),
     };
@@ -350,6 +523,46 @@ fn get_database_config() -> DatabaseConfig {
     database_config
 }
 
+fn get_security_config() -> SecurityConfig {
+    get_config().security.unwrap_or_default()
+}
+
+fn get_account_config() -> AccountConfig {
+    get_config().account.unwrap_or_default()
+}
+
+fn get_stream_config() -> StreamConfig {
+    get_config().stream.unwrap_or_default()
+}
+
+fn get_webhook_config() -> WebhookConfig {
+    get_config().webhooks.unwrap_or_default()
+}
+
+fn get_comments_config() -> CommentsConfig {
+    get_config().comments.unwrap_or_default()
+}
+
+fn get_reactions_config() -> ReactionsConfig {
+    get_config().reactions.unwrap_or_default()
+}
+
+fn get_moderation_config() -> ModerationConfig {
+    get_config().moderation.unwrap_or_default()
+}
+
+fn get_filter_config() -> FilterConfig {
+    get_config().filter.unwrap_or_default()
+}
+
+fn get_jobs_config() -> JobsConfig {
+    get_config().jobs.unwrap_or_default()
+}
+
+fn get_trash_config() -> TrashConfig {
+    get_config().trash.unwrap_or_default()
+}
+
 use hyper::header::{Authorization, Bearer};
 
 fn prepare_parameters(mut req: Request) -> (String, i32) {
@@ -359,7 +572,8 @@ fn prepare_parameters(mut req: Request) -> (String, i32) {
     let logged_id: i32 = match token {
         Some(token) => {
             let jwt = &token.0.token;
-            login(&jwt).unwrap()
+            // a token issued by another tenant logs nobody in here
+            login(&jwt).unwrap_or(0)
 
         }
         _ => 0,
@@ -369,6 +583,44 @@ fn prepare_parameters(mut req: Request) -> (String, i32) {
     (body, logged_id)
 }
 
+fn get_query_param<'a>(url: &'a str, name: &str) -> Option<&'a str> {
+    let query = match url.find('?') {
+        Some(index) => &url[index + 1..],
+        None => return None,
+    };
+
+    for param in query.split('&') {
+        let name_value: Vec<&str> = param.splitn(2, '=').collect();
+        if name_value[0] == name && name_value.len() == 2 {
+            return Some(name_value[1]);
+        }
+    }
+    None
+}
+
+/// Address of the calling client; behind the local IIS proxy this is the last hop it appended to X-Forwarded-For.
+fn client_ip(req: &Request) -> String {
+    use std::net::SocketAddr;
+
+    let peer = req.remote_addr.ip();
+    if peer.is_loopback() {
+        let forwarded: Option<String> = req.headers
+            .get_raw("X-Forwarded-For")
+            .and_then(|values| values.last())
+            .and_then(|value| String::from_utf8(value.clone()).ok())
+            .and_then(|value| value.split(',').last().map(|hop| hop.trim().to_string()))
+            .and_then(|hop| if hop.is_empty() { None } else { Some(hop) });
+
+        if let Some(hop) = forwarded {
+            return match hop.parse::<SocketAddr>() {
+                Ok(address) => address.ip().to_string(),
+                Err(_) => hop,
+            };
+        }
+    }
+    peer.to_string()
+}
+
 use unicase::UniCase;
 use hyper::header::ContentType;
 use hyper::mime::{Mime, TopLevel, SubLevel, Attr, Value};
@@ -542,15 +794,104 @@ fn process_container<'a, T, U, V>(
     res.send(&result).unwrap();
 }
 
+fn send_error(mut res: Response, status: StatusCode, message: &str) {
+    res.headers_mut().set(AccessControlAllowOrigin::Any);
+    res.headers_mut().set(AccessControlAllowHeaders(vec![
+        UniCase("content-type".to_owned()),
+        UniCase("authorization".to_owned()),
+    ]));
+    res.headers_mut().set(ContentType(Mime(
+        TopLevel::Application,
+        SubLevel::Json,
+        vec![(Attr::Charset, Value::Utf8)],
+    )));
+    *res.status_mut() = status;
+
+    let result = InternalError { errors: ErrorDetail { body: vec![message.to_string()] } };
+    let result = serde_json::to_string(&result).unwrap();
+    println!("Sending error '{:?}'", result.to_owned());
+    res.send(result.as_bytes()).unwrap();
+}
+
+fn send_redirect(mut res: Response, location: &str) {
+    res.headers_mut().set(AccessControlAllowOrigin::Any);
+    res.headers_mut().set(Location(location.to_string()));
+    *res.status_mut() = StatusCode::MovedPermanently;
+
+    println!("Redirecting to '{}'", location);
+    res.send(b"").unwrap();
+}
+
 mod user;
 use user::*;
 
+mod totp;
+
+mod throttle;
+use throttle::*;
+
+mod policy;
+use policy::*;
+
+mod admin;
+use admin::*;
+
+mod archive;
+
+mod block;
+use block::*;
+
+mod tag;
+use tag::*;
+
+mod notification;
+use notification::*;
+
+mod stream;
+use stream::*;
+
+mod webhook;
+use webhook::*;
+
+mod account;
+use account::*;
+
+mod twofactor;
+use twofactor::*;
+
 mod article;
 use article::*;
 
 mod comment;
 use comment::*;
 
+mod reaction;
+use reaction::*;
+
+mod report;
+use report::*;
+
+mod filter;
+use filter::*;
+
+mod job;
+use job::*;
+
+mod revision;
+use revision::*;
+
+mod slugs;
+use slugs::*;
+
+mod trash;
+use trash::*;
+
+mod audit;
+use audit::*;
+
+mod tenant;
+use tenant::*;
+
 #[cfg(feature = "tiberius")]
 fn handle_row_no_value(_: tiberius::query::QueryRow) -> tiberius::TdsResult<()> {
     Ok(())
@@ -679,32 +1020,87 @@ fn main() {
 
     #[cfg(feature = "tiberius")] builder.post(r"/createdb", create_db_handler);
 
+    builder.post(r"/api/users/login/2fa", two_factor_login_handler);
     builder.post(r"/api/users/login", authentication_handler);
     builder.post(r"/api/users", registration_handler);
     builder.get(r"/api/user", get_current_user_handler);
     builder.get(r"/test", test_handler);
     builder.put(r"/api/user", update_user_handler);
+    builder.delete(r"/api/user", delete_account_handler);
+    builder.get(r"/api/user/export.*", export_account_handler);
+    #[cfg(feature = "diesel")] builder.get(r"/api/user/articles.*", user_articles_handler);
+    #[cfg(feature = "diesel")] builder.get(r"/api/user/trash", trash_handler);
+    #[cfg(feature = "diesel")] builder.post(r"/api/user/trash/articles/.*/restore", restore_article_handler);
+    #[cfg(feature = "diesel")] builder.post(r"/api/user/trash/comments/.*/restore", restore_comment_handler);
+    #[cfg(feature = "diesel")] builder.post(r"/api/user/2fa/setup", two_factor_setup_handler);
+    #[cfg(feature = "diesel")] builder.post(r"/api/user/2fa/confirm", two_factor_confirm_handler);
+    builder.get(r"/api/user/notifications/preferences", get_notification_preferences_handler);
+    builder.put(r"/api/user/notifications/preferences", update_notification_preferences_handler);
+    builder.post(r"/api/notifications/read", mark_all_notifications_read_handler);
+    builder.post(r"/api/notifications/.*/read", mark_notification_read_handler);
+    builder.get(r"/api/notifications.*", list_notifications_handler);
+    builder.get(r"/api/stream.*", stream_handler);
+    builder.post(r"/api/webhooks/deliveries/.*/redeliver", redeliver_handler);
+    builder.get(r"/api/webhooks/.*/deliveries.*", list_deliveries_handler);
+    builder.delete(r"/api/webhooks/.*", delete_webhook_handler);
+    builder.get(r"/api/webhooks", list_webhooks_handler);
+    builder.post(r"/api/webhooks", create_webhook_handler);
+    builder.get(r"/api/profiles/.*/followers.*", followers_handler);
+    builder.get(r"/api/profiles/.*/following.*", following_handler);
     builder.get(r"/api/profiles/.*", get_profile_handler);
     builder.post(r"/api/profiles/.*/follow", follow_handler);
     builder.delete(r"/api/profiles/.*/follow", unfollow_handler);
+    // blocks and mutes are diesel only, the tiberius schema has no tables for them
+    #[cfg(feature = "diesel")] builder.post(r"/api/profiles/.*/block", block_handler);
+    #[cfg(feature = "diesel")] builder.delete(r"/api/profiles/.*/block", unblock_handler);
+    #[cfg(feature = "diesel")] builder.post(r"/api/profiles/.*/mute", mute_handler);
+    #[cfg(feature = "diesel")] builder.delete(r"/api/profiles/.*/mute", unmute_handler);
     builder.post(r"/api/articles", create_article_handler);
 
     builder.get(r"/api/tags", get_tags_handler);
-
+    #[cfg(feature = "diesel")] builder.post(r"/api/tags/.*/follow", follow_tag_handler);
+    #[cfg(feature = "diesel")] builder.delete(r"/api/tags/.*/follow", unfollow_tag_handler);
+
+    builder.put(r"/api/admin/users/.*/role", update_user_role_handler);
+    builder.get(r"/api/admin/lockouts.*", list_lockouts_handler);
+    #[cfg(feature = "diesel")] builder.get(r"/api/admin/audit.*", audit_log_handler);
+
+    #[cfg(feature = "diesel")] builder.get(r"/api/moderation/reports.*", list_reports_handler);
+    #[cfg(feature = "diesel")] builder.post(r"/api/moderation/reports/bulk", bulk_review_handler);
+    #[cfg(feature = "diesel")] builder.post(r"/api/moderation/reports/.*/resolve", resolve_report_handler);
+    #[cfg(feature = "diesel")] builder.post(r"/api/moderation/reports/.*/dismiss", dismiss_report_handler);
+
+    #[cfg(feature = "diesel")] builder.post(r"/api/articles/.*/reactions/.*", add_reaction_handler);
+    #[cfg(feature = "diesel")] builder.delete(r"/api/articles/.*/reactions/.*", remove_reaction_handler);
+    #[cfg(feature = "diesel")] builder.get(r"/api/articles/.*/reactions", article_reactions_handler);
+    #[cfg(feature = "diesel")] builder.post(r"/api/articles/.*/report", report_handler);
     builder.post(r"/api/articles/.*/comments", add_comment_handler);
     builder.post(r"/api/articles/.*/favorite", favorite_article_handler);
     builder.delete(r"/api/articles/.*/favorite", unfavorite_article_handler);
+    #[cfg(feature = "diesel")] builder.post(r"/api/articles/.*/pin", pin_article_handler);
+    #[cfg(feature = "diesel")] builder.post(r"/api/articles/.*/publish", publish_article_handler);
+    #[cfg(feature = "diesel")] builder.post(r"/api/articles/.*/revisions/.*/restore", restore_revision_handler);
+    #[cfg(feature = "diesel")] builder.delete(r"/api/articles/.*/pin", unpin_article_handler);
+    #[cfg(feature = "diesel")] builder.put(r"/api/articles/.*/comments/.*", update_comment_handler);
     builder.put(r"/api/articles/.*", update_article_handler);
     builder.delete(r"/api/articles/.*/comments/.*", delete_comment_handler);
     builder.delete(r"/api/articles/.*", delete_article_handler);
     builder.get(r"/api/articles/feed", feed_handler);
-    builder.get(r"/api/articles/.*/comments", get_comments_handler);
+    #[cfg(feature = "diesel")] builder.get(r"/api/articles/.*/comments/.*/revisions", comment_revisions_handler);
+    #[cfg(feature = "diesel")] builder.get(r"/api/articles/.*/revisions/diff.*", revision_diff_handler);
+    #[cfg(feature = "diesel")] builder.get(r"/api/articles/.*/revisions", article_revisions_handler);
+    builder.get(r"/api/articles/.*/comments.*", get_comments_handler);
     builder.get(r"/api/articles/.*", get_article_handler);
     builder.get(r"/api/articles?.*", list_article_handler);
     builder.options("/api/.*", options_handler);
 
     let router = builder.finalize().unwrap();
 
-    Server::http(listen_on).unwrap().handle(router).unwrap();
+    #[cfg(feature = "diesel")] register_tenants();
+    #[cfg(feature = "diesel")] start_webhook_worker();
+    #[cfg(feature = "diesel")] start_job_runner();
+
+    let router = TenantRouter { router: router };
+    Server::http(listen_on).unwrap().handle_threads(router, REQUEST_THREADS + *STREAM_MAX_CLIENTS).unwrap();
 
 }
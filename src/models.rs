@@ -17,6 +17,9 @@ pub struct User {
     pub bio: Option<String>,
     pub image: Option<String>,
     //pub following: Option<bool>
+    #[serde(skip_serializing)]
+    #[serde(skip_deserializing)]
+    pub tenantid: i32,
 }
 
 #[derive(Identifiable, Queryable, Associations)]
@@ -55,7 +58,12 @@ pub struct AdvancedArticle {
     pub author: i32,
     pub favorited: bool,
     pub favoritesCount: i64,
+    pub commentsCount: i64,
     pub tagList: Vec<String>,
+    pub pinned: bool,
+    pub status: String,
+    pub publishedAt: Option<NaiveDateTime>,
+    pub publishAt: Option<NaiveDateTime>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -73,6 +81,10 @@ pub struct UpdatedArticle<'a> {
     pub author: i32,
     pub createdat: NaiveDateTime,
     pub updatedat: Option<NaiveDateTime>,
+    pub status: &'a str,
+    pub publishedat: Option<NaiveDateTime>,
+    pub publishat: Option<NaiveDateTime>,
+    pub hidden: bool,
 }
 
 #[derive(Insertable)]
@@ -82,6 +94,7 @@ pub struct NewUser<'a> {
     pub email: &'a str,
     pub token: &'a str,
     pub username: &'a str,
+    pub tenantid: i32,
 }
 
 #[derive(Insertable)]
@@ -115,6 +128,12 @@ pub struct Comment {
     #[serde(skip_serializing)]
     #[serde(skip_deserializing)]
     pub articleid : i32,
+    pub parentId: Option<i32>,
+    pub deleted: bool,
+    pub edited: bool,
+    pub hidden: bool,
+    pub deletedAt: Option<NaiveDateTime>,
+    pub deletedBy: Option<i32>,
 }
 
 #[derive(Insertable)]
@@ -127,6 +146,8 @@ pub struct NewComment<'a> {
     pub body:  &'a str,
     pub author: i32,
     pub articleid: i32,
+    pub parentid: Option<i32>,
+    pub hidden: bool,
 }
 
 #[derive(Insertable)]
@@ -140,6 +161,11 @@ pub struct NewArticle<'a> {
     pub author: i32,
     pub createdat: NaiveDateTime,
     pub updatedat: Option<NaiveDateTime>,
+    pub hidden: bool,
+    pub status: &'a str,
+    pub publishedat: Option<NaiveDateTime>,
+    pub publishat: Option<NaiveDateTime>,
+    pub tenantid: i32,
     //pub tagList: &'a Vec<str>,
 }
 
@@ -152,6 +178,8 @@ pub struct IncomingArticle {
     pub description: String,
     pub body: String,
     pub tagList: Option<Vec<String>>,
+    pub status: Option<String>,
+    pub publishAt: Option<NaiveDateTime>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -178,6 +206,15 @@ pub struct Article {
     pub createdAt: NaiveDateTime,
     pub updatedAt: Option<NaiveDateTime>,
     pub author: i32,
+    pub hidden: bool,
+    pub status: String,
+    pub publishedAt: Option<NaiveDateTime>,
+    pub publishAt: Option<NaiveDateTime>,
+    pub deletedAt: Option<NaiveDateTime>,
+    pub deletedBy: Option<i32>,
+    #[serde(skip_serializing)]
+    #[serde(skip_deserializing)]
+    pub tenantid: i32,
 }
 
 #[derive(Identifiable, Queryable, Associations)]
@@ -228,3 +265,525 @@ pub struct Tag {
     pub id: i32,
     pub tag: String,
 }
+
+#[derive(Identifiable, Queryable, Associations)]
+#[derive(Debug)]
+#[table_name = "twofactors"]
+#[belongs_to(User, foreign_key = "userid")]
+pub struct TwoFactor {
+    pub id: i32,
+    pub userid: i32,
+    pub secret: String,
+    pub enabled: bool,
+    pub createdat: NaiveDateTime,
+    pub lastusedstep: Option<i64>,
+}
+
+#[derive(Insertable)]
+#[derive(Debug)]
+#[table_name="twofactors"]
+pub struct NewTwoFactor<'a> {
+    pub userid: i32,
+    pub secret: &'a str,
+    pub enabled: bool,
+    pub createdat: NaiveDateTime,
+}
+
+#[derive(Identifiable, Queryable, Associations)]
+#[derive(Debug)]
+#[table_name = "recoverycodes"]
+#[belongs_to(User, foreign_key = "userid")]
+pub struct RecoveryCode {
+    pub id: i32,
+    pub userid: i32,
+    pub codehash: String,
+    pub usedat: Option<NaiveDateTime>,
+}
+
+#[derive(Insertable)]
+#[derive(Debug)]
+#[table_name="recoverycodes"]
+pub struct NewRecoveryCode<'a> {
+    pub userid: i32,
+    pub codehash: &'a str,
+}
+
+#[derive(Identifiable, Queryable)]
+#[derive(Debug)]
+#[table_name = "loginthrottles"]
+pub struct LoginThrottle {
+    pub id: i32,
+    pub scope: String,
+    pub throttlekey: String,
+    pub failures: i32,
+    pub lastfailureat: Option<NaiveDateTime>,
+    pub lockeduntil: Option<NaiveDateTime>,
+}
+
+#[derive(Insertable)]
+#[derive(Debug)]
+#[table_name="loginthrottles"]
+pub struct NewLoginThrottle<'a> {
+    pub scope: &'a str,
+    pub throttlekey: &'a str,
+    pub failures: i32,
+    pub lastfailureat: Option<NaiveDateTime>,
+    pub lockeduntil: Option<NaiveDateTime>,
+}
+
+#[derive(Identifiable, Queryable)]
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+#[table_name = "lockoutevents"]
+#[allow(non_snake_case)]
+pub struct LockoutEvent {
+    pub id: i32,
+    pub scope: String,
+    pub key: String,
+    pub event: String,
+    pub failures: i32,
+    pub lockedUntil: Option<NaiveDateTime>,
+    pub createdAt: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[derive(Debug)]
+#[table_name="lockoutevents"]
+pub struct NewLockoutEvent<'a> {
+    pub scope: &'a str,
+    pub throttlekey: &'a str,
+    pub event: &'a str,
+    pub failures: i32,
+    pub lockeduntil: Option<NaiveDateTime>,
+    pub createdat: NaiveDateTime,
+}
+
+#[derive(Identifiable, Queryable, Associations)]
+#[derive(Debug)]
+#[table_name = "userroles"]
+#[belongs_to(User, foreign_key = "userid")]
+pub struct UserRole {
+    pub id: i32,
+    pub userid: i32,
+    pub role: String,
+    pub grantedby: Option<i32>,
+    pub grantedat: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[derive(Debug)]
+#[table_name="userroles"]
+pub struct NewUserRole<'a> {
+    pub userid: i32,
+    pub role: &'a str,
+    pub grantedby: Option<i32>,
+    pub grantedat: NaiveDateTime,
+}
+
+#[derive(Identifiable, Queryable, Associations)]
+#[derive(Debug)]
+#[table_name = "pinnedarticles"]
+#[belongs_to(Article, foreign_key = "articleid")]
+pub struct PinnedArticle {
+    pub id: i32,
+    pub articleid: i32,
+    pub pinnedby: i32,
+    pub pinnedat: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[derive(Debug)]
+#[table_name="pinnedarticles"]
+pub struct NewPinnedArticle {
+    pub articleid: i32,
+    pub pinnedby: i32,
+    pub pinnedat: NaiveDateTime,
+}
+
+#[derive(Identifiable, Queryable)]
+#[derive(Debug)]
+#[table_name = "blocks"]
+pub struct Block {
+    pub id: i32,
+    pub blockerid: i32,
+    pub blockedid: i32,
+    pub createdat: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[derive(Debug)]
+#[table_name="blocks"]
+pub struct NewBlock {
+    pub blockerid: i32,
+    pub blockedid: i32,
+    pub createdat: NaiveDateTime,
+}
+
+#[derive(Identifiable, Queryable)]
+#[derive(Debug)]
+#[table_name = "mutes"]
+pub struct Mute {
+    pub id: i32,
+    pub muterid: i32,
+    pub mutedid: i32,
+    pub createdat: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[derive(Debug)]
+#[table_name="mutes"]
+pub struct NewMute {
+    pub muterid: i32,
+    pub mutedid: i32,
+    pub createdat: NaiveDateTime,
+}
+
+#[derive(Identifiable, Queryable, Associations)]
+#[derive(Debug)]
+#[table_name = "tagfollowings"]
+#[belongs_to(Tag, foreign_key = "tagid")]
+pub struct TagFollowing {
+    pub id: i32,
+    pub tagid: i32,
+    pub followerid: i32,
+}
+
+#[derive(Insertable)]
+#[derive(Debug)]
+#[table_name="tagfollowings"]
+pub struct NewTagFollowing {
+    pub tagid: i32,
+    pub followerid: i32,
+}
+
+#[derive(Identifiable, Queryable)]
+#[derive(Debug)]
+#[table_name = "notifications"]
+pub struct Notification {
+    pub id: i32,
+    pub userid: i32,
+    pub actorid: i32,
+    pub kind: String,
+    pub articleid: Option<i32>,
+    pub commentid: Option<i32>,
+    pub readat: Option<NaiveDateTime>,
+    pub createdat: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[derive(Debug)]
+#[table_name="notifications"]
+pub struct NewNotification<'a> {
+    pub userid: i32,
+    pub actorid: i32,
+    pub kind: &'a str,
+    pub articleid: Option<i32>,
+    pub commentid: Option<i32>,
+    pub createdat: NaiveDateTime,
+}
+
+#[derive(Identifiable, Queryable)]
+#[derive(Debug)]
+#[table_name = "notificationpreferences"]
+pub struct NotificationPreference {
+    pub id: i32,
+    pub userid: i32,
+    pub kind: String,
+    pub enabled: bool,
+}
+
+#[derive(Insertable)]
+#[derive(Debug)]
+#[table_name="notificationpreferences"]
+pub struct NewNotificationPreference<'a> {
+    pub userid: i32,
+    pub kind: &'a str,
+    pub enabled: bool,
+}
+
+#[derive(Identifiable, Queryable)]
+#[derive(Debug)]
+#[table_name = "webhooks"]
+pub struct Webhook {
+    pub id: i32,
+    pub ownerid: i32,
+    pub url: String,
+    pub secret: String,
+    pub events: String,
+    pub global: bool,
+    pub active: bool,
+    pub createdat: NaiveDateTime,
+    pub tenantid: i32,
+}
+
+#[derive(Insertable)]
+#[derive(Debug)]
+#[table_name="webhooks"]
+pub struct NewWebhook<'a> {
+    pub ownerid: i32,
+    pub url: &'a str,
+    pub secret: &'a str,
+    pub events: &'a str,
+    pub global: bool,
+    pub active: bool,
+    pub createdat: NaiveDateTime,
+    pub tenantid: i32,
+}
+
+#[derive(Identifiable, Queryable)]
+#[derive(Debug)]
+#[table_name = "webhookdeliveries"]
+pub struct WebhookDelivery {
+    pub id: i32,
+    pub webhookid: i32,
+    pub event: String,
+    pub payload: String,
+    pub status: String,
+    pub attempts: i32,
+    pub nextattemptat: Option<NaiveDateTime>,
+    pub createdat: NaiveDateTime,
+    pub deliveredat: Option<NaiveDateTime>,
+}
+
+#[derive(Insertable)]
+#[derive(Debug)]
+#[table_name="webhookdeliveries"]
+pub struct NewWebhookDelivery<'a> {
+    pub webhookid: i32,
+    pub event: &'a str,
+    pub payload: &'a str,
+    pub status: &'a str,
+    pub attempts: i32,
+    pub nextattemptat: Option<NaiveDateTime>,
+    pub createdat: NaiveDateTime,
+}
+
+#[derive(Identifiable, Queryable)]
+#[derive(Debug)]
+#[table_name = "webhookattempts"]
+pub struct WebhookAttempt {
+    pub id: i32,
+    pub deliveryid: i32,
+    pub attemptedat: NaiveDateTime,
+    pub responsestatus: Option<i32>,
+    pub error: Option<String>,
+    pub durationms: i32,
+}
+
+#[derive(Insertable)]
+#[derive(Debug)]
+#[table_name="webhookattempts"]
+pub struct NewWebhookAttempt<'a> {
+    pub deliveryid: i32,
+    pub attemptedat: NaiveDateTime,
+    pub responsestatus: Option<i32>,
+    pub error: Option<&'a str>,
+    pub durationms: i32,
+}
+
+#[derive(Identifiable, Queryable)]
+#[derive(Debug)]
+#[table_name = "commentrevisions"]
+pub struct CommentRevision {
+    pub id: i32,
+    pub commentid: i32,
+    pub body: String,
+    pub editorid: Option<i32>,
+    pub revisedat: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[derive(Debug)]
+#[table_name="commentrevisions"]
+pub struct NewCommentRevision<'a> {
+    pub commentid: i32,
+    pub body: &'a str,
+    pub editorid: i32,
+    pub revisedat: NaiveDateTime,
+}
+
+#[derive(Identifiable, Queryable)]
+#[derive(Debug)]
+#[table_name = "reactions"]
+pub struct Reaction {
+    pub id: i32,
+    pub userid: i32,
+    pub reaction: String,
+    pub commentid: Option<i32>,
+    pub articleid: Option<i32>,
+    pub createdat: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[derive(Debug)]
+#[table_name="reactions"]
+pub struct NewReaction<'a> {
+    pub userid: i32,
+    pub reaction: &'a str,
+    pub commentid: Option<i32>,
+    pub articleid: Option<i32>,
+    pub createdat: NaiveDateTime,
+}
+
+#[derive(Identifiable, Queryable)]
+#[derive(Debug)]
+#[table_name = "reports"]
+pub struct Report {
+    pub id: i32,
+    pub reporterid: Option<i32>,
+    pub articleid: i32,
+    pub commentid: Option<i32>,
+    pub reason: String,
+    pub details: Option<String>,
+    pub status: String,
+    pub resolvedby: Option<i32>,
+    pub resolvedat: Option<NaiveDateTime>,
+    pub createdat: NaiveDateTime,
+    pub tenantid: i32,
+}
+
+#[derive(Insertable)]
+#[derive(Debug)]
+#[table_name="reports"]
+pub struct NewReport<'a> {
+    pub reporterid: Option<i32>,
+    pub articleid: i32,
+    pub commentid: Option<i32>,
+    pub reason: &'a str,
+    pub details: Option<&'a str>,
+    pub status: &'a str,
+    pub createdat: NaiveDateTime,
+    pub tenantid: i32,
+}
+
+#[derive(Identifiable, Queryable)]
+#[derive(Debug)]
+#[table_name = "filterexamples"]
+pub struct FilterExample {
+    pub id: i32,
+    pub body: String,
+    pub spam: bool,
+    pub reportid: Option<i32>,
+    pub createdat: NaiveDateTime,
+    pub tenantid: i32,
+}
+
+#[derive(Insertable)]
+#[derive(Debug)]
+#[table_name="filterexamples"]
+pub struct NewFilterExample<'a> {
+    pub body: &'a str,
+    pub spam: bool,
+    pub reportid: Option<i32>,
+    pub createdat: NaiveDateTime,
+    pub tenantid: i32,
+}
+
+#[derive(Identifiable, Queryable)]
+#[derive(Debug)]
+#[table_name = "jobs"]
+pub struct Job {
+    pub id: i32,
+    pub kind: String,
+    pub payload: String,
+    pub status: String,
+    pub attempts: i32,
+    pub runat: NaiveDateTime,
+    pub lockeduntil: Option<NaiveDateTime>,
+    pub lasterror: Option<String>,
+    pub createdat: NaiveDateTime,
+    pub finishedat: Option<NaiveDateTime>,
+}
+
+#[derive(Insertable)]
+#[derive(Debug)]
+#[table_name="jobs"]
+pub struct NewJob<'a> {
+    pub kind: &'a str,
+    pub payload: &'a str,
+    pub status: &'a str,
+    pub runat: NaiveDateTime,
+    pub createdat: NaiveDateTime,
+}
+
+#[derive(Identifiable, Queryable)]
+#[derive(Debug)]
+#[table_name = "articlerevisions"]
+pub struct ArticleRevision {
+    pub id: i32,
+    pub articleid: i32,
+    pub title: String,
+    pub description: String,
+    pub body: String,
+    pub editorid: Option<i32>,
+    pub revisedat: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[derive(Debug)]
+#[table_name="articlerevisions"]
+pub struct NewArticleRevision<'a> {
+    pub articleid: i32,
+    pub title: &'a str,
+    pub description: &'a str,
+    pub body: &'a str,
+    pub editorid: i32,
+    pub revisedat: NaiveDateTime,
+}
+
+#[derive(Identifiable, Queryable)]
+#[derive(Debug)]
+#[table_name = "slugaliases"]
+pub struct SlugAlias {
+    pub id: i32,
+    pub articleid: i32,
+    pub slug: String,
+    pub createdat: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[derive(Debug)]
+#[table_name="slugaliases"]
+pub struct NewSlugAlias<'a> {
+    pub articleid: i32,
+    pub slug: &'a str,
+    pub createdat: NaiveDateTime,
+}
+
+#[derive(Identifiable, Queryable)]
+#[derive(Debug)]
+#[table_name = "auditlog"]
+pub struct AuditEntry {
+    pub id: i32,
+    pub actorid: Option<i32>,
+    pub action: String,
+    pub entitytype: String,
+    pub entityid: i32,
+    pub before: Option<String>,
+    pub after: Option<String>,
+    pub createdat: NaiveDateTime,
+    pub tenantid: i32,
+}
+
+#[derive(Insertable)]
+#[derive(Debug)]
+#[table_name="auditlog"]
+pub struct NewAuditEntry<'a> {
+    pub actorid: Option<i32>,
+    pub action: &'a str,
+    pub entitytype: &'a str,
+    pub entityid: i32,
+    pub before: Option<&'a str>,
+    pub after: Option<&'a str>,
+    pub createdat: NaiveDateTime,
+    pub tenantid: i32,
+}
+
+#[derive(Insertable)]
+#[derive(Debug)]
+#[table_name="tenants"]
+pub struct NewTenant<'a> {
+    pub id: i32,
+    pub name: &'a str,
+    pub createdat: NaiveDateTime,
+}
@@ -0,0 +1,497 @@
+extern crate hyper;
+
+extern crate serde;
+extern crate serde_json;
+
+extern crate chrono;
+
+extern crate reroute;
+
+use hyper::status::StatusCode;
+
+use hyper::server::{Request, Response};
+use reroute::Captures;
+
+use super::*;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NotificationKind {
+    Follow,
+    Favorite,
+    Comment,
+    Mention,
+}
+
+impl NotificationKind {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            NotificationKind::Follow => "follow",
+            NotificationKind::Favorite => "favorite",
+            NotificationKind::Comment => "comment",
+            NotificationKind::Mention => "mention",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+#[allow(non_snake_case)]
+pub struct NotificationDTO {
+    pub id: i32,
+    pub kind: String,
+    pub actor: String,
+    pub article: Option<String>,
+    pub commentId: Option<i32>,
+    pub read: bool,
+    pub createdAt: NaiveDateTime,
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+pub struct NotificationEvent {
+    pub notification: NotificationDTO,
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+#[allow(non_snake_case)]
+pub struct NotificationsResult {
+    pub notifications: Vec<NotificationDTO>,
+    pub unreadCount: i64,
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+pub struct NotificationPreferences {
+    pub follow: bool,
+    pub favorite: bool,
+    pub comment: bool,
+    pub mention: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+pub struct NotificationPreferencesResult {
+    pub preferences: NotificationPreferences,
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+struct UpdateNotificationPreferencesDetail {
+    follow: Option<bool>,
+    favorite: Option<bool>,
+    comment: Option<bool>,
+    mention: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+struct UpdateNotificationPreferences {
+    preferences: UpdateNotificationPreferencesDetail,
+}
+
+/// Usernames written as `@name` in a text, each once and in order of appearance.
+pub fn parse_mentions(text: &str) -> Vec<String> {
+    let mut mentions: Vec<String> = Vec::new();
+    let mut previous: Option<char> = None;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        // an @ inside a word is an e-mail address, not a mention
+        let starts_word = previous.map_or(true, |p| !p.is_alphanumeric());
+        previous = Some(c);
+        if c != '@' || !starts_word {
+            continue;
+        }
+
+        let mut name = String::new();
+        while let Some(&next) = chars.peek() {
+            if next.is_alphanumeric() || next == '-' || next == '_' {
+                name.push(next);
+                previous = Some(next);
+                chars.next();
+            } else {
+                break;
+            }
+        }
+        let name = name.trim_right_matches(|c: char| c == '-' || c == '_').to_string();
+        if !name.is_empty() && !mentions.contains(&name) {
+            mentions.push(name);
+        }
+    }
+    mentions
+}
+
+#[cfg(feature = "diesel")]
+fn is_notification_enabled(user_id: i32, notification_kind: NotificationKind) -> bool {
+    use schema::notificationpreferences::dsl::*;
+
+    let connection = establish_connection();
+    let preference: Option<NotificationPreference> = notificationpreferences
+        .filter(userid.eq(user_id).and(kind.eq(notification_kind.name())))
+        .first(&connection)
+        .optional()
+        .expect("Error loading notification preference");
+
+    preference.map_or(true, |p| p.enabled)
+}
+
+/// Records a notification for `recipient_id`, unless it is about their own action, they opted out or they blocked the actor.
+#[cfg(feature = "diesel")]
+pub fn notify(
+    recipient_id: i32,
+    actor_id: i32,
+    notification_kind: NotificationKind,
+    article_id: Option<i32>,
+    comment_id: Option<i32>,
+) {
+    use schema::notifications;
+
+    if recipient_id == actor_id || !is_notification_enabled(recipient_id, notification_kind) ||
+        is_blocked(recipient_id, actor_id)
+    {
+        return;
+    }
+
+    let connection = establish_connection();
+    let notification = NewNotification {
+        userid: recipient_id,
+        actorid: actor_id,
+        kind: notification_kind.name(),
+        articleid: article_id,
+        commentid: comment_id,
+        createdat: Utc::now().naive_utc(),
+    };
+    let notification: Notification = diesel::insert(&notification)
+        .into(notifications::table)
+        .get_result(&connection)
+        .expect("Error saving notification");
+
+    let event = NotificationEvent {
+        notification: NotificationDTO {
+            id: notification.id,
+            kind: notification.kind,
+            actor: get_user_by_id(actor_id).unwrap().user.username,
+            article: article_id.and_then(|article_id| get_article_slug(article_id, &connection)),
+            commentId: notification.commentid,
+            read: false,
+            createdAt: notification.createdat,
+        },
+    };
+    publish("notification", StreamTopic::Notification { user_id: recipient_id }, &event);
+}
+
+#[cfg(feature = "diesel")]
+fn get_article_slug(article_id: i32, connection: &PgConnection) -> Option<String> {
+    use schema::articles::dsl::*;
+
+    articles
+        .find(article_id)
+        .select(slug)
+        .first(connection)
+        .optional()
+        .expect("Error loading notification article")
+}
+
+/// Notifies the users mentioned in `text`, skipping those in `already_notified`.
+#[cfg(feature = "diesel")]
+pub fn notify_mentions(
+    text: &str,
+    actor_id: i32,
+    article_id: Option<i32>,
+    comment_id: Option<i32>,
+    already_notified: &[i32],
+) {
+    for user_name in parse_mentions(text) {
+        if let Some(mentioned) = get_user_by_name(&user_name) {
+            if !already_notified.contains(&mentioned.id) {
+                notify(mentioned.id, actor_id, NotificationKind::Mention, article_id, comment_id);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "diesel")]
+fn get_notifications(params: (i32, bool, i64, i64)) -> Option<NotificationsResult> {
+    use schema::{articles, notifications, users};
+    use diesel::expression::dsl::any;
+
+    let (user_id, unread_only, limit, offset) = params;
+    let connection = establish_connection();
+
+    let page: Vec<Notification> = if unread_only {
+        notifications::table
+            .filter(notifications::userid.eq(user_id).and(notifications::readat.is_null()))
+            .order(notifications::id.desc())
+            .limit(limit)
+            .offset(offset)
+            .load(&connection)
+    } else {
+        notifications::table
+            .filter(notifications::userid.eq(user_id))
+            .order(notifications::id.desc())
+            .limit(limit)
+            .offset(offset)
+            .load(&connection)
+    }.expect("Error loading notifications");
+
+    let unread_count: i64 = notifications::table
+        .filter(notifications::userid.eq(user_id).and(notifications::readat.is_null()))
+        .count()
+        .get_result(&connection)
+        .expect("Error counting unread notifications");
+
+    let actor_ids: Vec<i32> = page.iter().map(|n| n.actorid).collect();
+    let actors: Vec<(i32, String)> = users::table
+        .filter(users::id.eq(any(&actor_ids)))
+        .select((users::id, users::username))
+        .load(&connection)
+        .expect("Error loading notification actors");
+    let article_ids: Vec<i32> = page.iter().filter_map(|n| n.articleid).collect();
+    let slugs: Vec<(i32, String)> = articles::table
+        .filter(articles::id.eq(any(&article_ids)))
+        .select((articles::id, articles::slug))
+        .load(&connection)
+        .expect("Error loading notification articles");
+
+    let name_of = |id: i32, names: &[(i32, String)]| {
+        names.iter().find(|&&(key, _)| key == id).map(|&(_, ref name)| name.to_owned())
+    };
+
+    Some(NotificationsResult {
+        notifications: page.into_iter()
+            .map(|n| {
+                NotificationDTO {
+                    id: n.id,
+                    actor: name_of(n.actorid, &actors).unwrap_or_default(),
+                    article: n.articleid.and_then(|id| name_of(id, &slugs)),
+                    commentId: n.commentid,
+                    read: n.readat.is_some(),
+                    kind: n.kind,
+                    createdAt: n.createdat,
+                }
+            })
+            .collect(),
+        unreadCount: unread_count,
+    })
+}
+
+pub fn list_notifications_handler(req: Request, res: Response, c: Captures) {
+    let (_, logged_id) = prepare_parameters(req);
+
+    let caps = c.unwrap();
+    let url = &caps[0];
+    let unread_only = get_query_param(url, "unread") == Some("true");
+    let limit: i64 = get_query_param(url, "limit").and_then(|v| v.parse().ok()).unwrap_or(20);
+    let offset: i64 = get_query_param(url, "offset").and_then(|v| v.parse().ok()).unwrap_or(0);
+
+    #[cfg(feature = "diesel")]
+    {
+        if logged_id <= 0 {
+            send_denied(res, Denied::Unauthenticated);
+            return;
+        }
+        process(res, get_notifications, (logged_id, unread_only, limit, offset))
+    }
+}
+
+#[cfg(feature = "diesel")]
+fn mark_notifications_read(params: (i32, Option<i32>)) -> Option<NotificationsResult> {
+    use schema::notifications::dsl::*;
+
+    let (user_id, notification_id) = params;
+    let connection = establish_connection();
+    let now = Utc::now().naive_utc();
+
+    match notification_id {
+        Some(notification_id) => {
+            diesel::update(notifications.filter(
+                id.eq(notification_id).and(userid.eq(user_id)).and(readat.is_null()),
+            )).set(readat.eq(Some(now)))
+                .execute(&connection)
+        }
+        None => {
+            diesel::update(notifications.filter(userid.eq(user_id).and(readat.is_null())))
+                .set(readat.eq(Some(now)))
+                .execute(&connection)
+        }
+    }.expect("Error marking notifications as read");
+
+    get_notifications((user_id, true, 20, 0))
+}
+
+pub fn mark_all_notifications_read_handler(req: Request, res: Response, _: Captures) {
+    let (_, logged_id) = prepare_parameters(req);
+
+    #[cfg(feature = "diesel")]
+    {
+        if logged_id <= 0 {
+            send_denied(res, Denied::Unauthenticated);
+            return;
+        }
+        process(res, mark_notifications_read, (logged_id, None))
+    }
+}
+
+pub fn mark_notification_read_handler(req: Request, res: Response, c: Captures) {
+    let (_, logged_id) = prepare_parameters(req);
+
+    let caps = c.unwrap();
+    let notification_id = caps[0].replace("/api/notifications/", "").replace("/read", "");
+    println!("mark_notification_read_handler id: '{}'", notification_id);
+
+    #[cfg(feature = "diesel")]
+    {
+        if logged_id <= 0 {
+            send_denied(res, Denied::Unauthenticated);
+            return;
+        }
+        match notification_id.parse::<i32>() {
+            Ok(notification_id) => process(res, mark_notifications_read, (logged_id, Some(notification_id))),
+            Err(_) => send_error(res, StatusCode::NotFound, "notification not found"),
+        }
+    }
+}
+
+#[cfg(feature = "diesel")]
+fn get_notification_preferences(user_id: i32) -> Option<NotificationPreferencesResult> {
+    Some(NotificationPreferencesResult {
+        preferences: NotificationPreferences {
+            follow: is_notification_enabled(user_id, NotificationKind::Follow),
+            favorite: is_notification_enabled(user_id, NotificationKind::Favorite),
+            comment: is_notification_enabled(user_id, NotificationKind::Comment),
+            mention: is_notification_enabled(user_id, NotificationKind::Mention),
+        },
+    })
+}
+
+#[cfg(feature = "diesel")]
+fn set_notification_preference(user_id: i32, notification_kind: NotificationKind, enabled: bool) {
+    use schema::notificationpreferences;
+
+    let connection = establish_connection();
+
+    diesel::delete(notificationpreferences::table.filter(
+        notificationpreferences::userid.eq(user_id).and(
+            notificationpreferences::kind.eq(notification_kind.name()),
+        ),
+    )).execute(&connection)
+        .expect("Error removing notification preference");
+
+    let preference = NewNotificationPreference {
+        userid: user_id,
+        kind: notification_kind.name(),
+        enabled: enabled,
+    };
+    diesel::insert(&preference)
+        .into(notificationpreferences::table)
+        .execute(&connection)
+        .expect("Error saving notification preference");
+}
+
+pub fn get_notification_preferences_handler(req: Request, res: Response, _: Captures) {
+    let (_, logged_id) = prepare_parameters(req);
+
+    #[cfg(feature = "diesel")]
+    {
+        if logged_id <= 0 {
+            send_denied(res, Denied::Unauthenticated);
+            return;
+        }
+        process(res, get_notification_preferences, logged_id)
+    }
+}
+
+pub fn update_notification_preferences_handler(req: Request, res: Response, _: Captures) {
+    let (body, logged_id) = prepare_parameters(req);
+
+    let incoming: UpdateNotificationPreferences = serde_json::from_str(&body).unwrap();
+    let changes = incoming.preferences;
+
+    #[cfg(feature = "diesel")]
+    {
+        if logged_id <= 0 {
+            send_denied(res, Denied::Unauthenticated);
+            return;
+        }
+
+        let requested = [
+            (NotificationKind::Follow, changes.follow),
+            (NotificationKind::Favorite, changes.favorite),
+            (NotificationKind::Comment, changes.comment),
+            (NotificationKind::Mention, changes.mention),
+        ];
+        for &(notification_kind, enabled) in &requested {
+            if let Some(enabled) = enabled {
+                set_notification_preference(logged_id, notification_kind, enabled);
+            }
+        }
+
+        process(res, get_notification_preferences, logged_id)
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn parse_mentions_test() {
+    assert_eq!(parse_mentions("thanks @jake and @Jacob-1-2!"), vec!["jake", "Jacob-1-2"]);
+    assert_eq!(parse_mentions("@jake, again @jake"), vec!["jake"]);
+    assert_eq!(parse_mentions("mail jake@jake.jake"), Vec::<String>::new());
+    assert_eq!(parse_mentions("just @ and @-"), Vec::<String>::new());
+}
+
+#[cfg(test)]
+fn get_notifications_request(jwt: &str) -> NotificationsResult {
+    let client = Client::new();
+
+    let mut res = client
+        .get("http://localhost:6767/api/notifications?unread=true")
+        .header(Authorization(Bearer { token: jwt.to_owned() }))
+        .send()
+        .unwrap();
+    let mut buffer = String::new();
+    res.read_to_string(&mut buffer).unwrap();
+
+    assert_eq!(res.status, hyper::Ok);
+    serde_json::from_str(&buffer).unwrap()
+}
+
+#[cfg(test)]
+#[test]
+fn comment_notification_test() {
+    let client = Client::new();
+    let (author_jwt, slug, _) = login_create_article(false);
+    let (mentioned_name, mentioned_email) = register_jacob();
+    let mentioned_jwt = login_jacob(mentioned_email, JACOB_PASSWORD.to_string());
+    let (commenter_name, commenter_email) = register_jacob();
+    let commenter_jwt = login_jacob(commenter_email, JACOB_PASSWORD.to_string());
+
+    let url = format!("http://localhost:6767/api/articles/{}/comments", slug);
+    let body = format!(r#"{{"comment": {{"body": "Have you read this, @{}?"}}}}"#, mentioned_name);
+    let res = client
+        .post(&url)
+        .header(Authorization(Bearer { token: commenter_jwt }))
+        .body(&body)
+        .send()
+        .unwrap();
+    assert_eq!(res.status, hyper::Ok);
+
+    let result = get_notifications_request(&author_jwt);
+    assert_eq!(result.unreadCount, 1);
+    assert_eq!(result.notifications[0].kind, "comment");
+    assert_eq!(result.notifications[0].actor, commenter_name);
+    assert_eq!(result.notifications[0].article, Some(slug));
+
+    let result = get_notifications_request(&mentioned_jwt);
+    assert_eq!(result.unreadCount, 1);
+    assert_eq!(result.notifications[0].kind, "mention");
+
+    let res = client
+        .post("http://localhost:6767/api/notifications/read")
+        .header(Authorization(Bearer { token: author_jwt.to_owned() }))
+        .body("")
+        .send()
+        .unwrap();
+    assert_eq!(res.status, hyper::Ok);
+    assert_eq!(get_notifications_request(&author_jwt).unreadCount, 0);
+}
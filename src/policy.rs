@@ -0,0 +1,178 @@
+extern crate hyper;
+
+extern crate chrono;
+
+use hyper::status::StatusCode;
+
+use hyper::server::Response;
+
+use super::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Role {
+    User,
+    Moderator,
+    Admin,
+}
+
+impl Role {
+    pub fn from_name(name: &str) -> Option<Role> {
+        match name {
+            "user" => Some(Role::User),
+            "moderator" => Some(Role::Moderator),
+            "admin" => Some(Role::Admin),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Role::User => "user",
+            Role::Moderator => "moderator",
+            Role::Admin => "admin",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    CreateArticle,
+    UpdateArticle,
+    DeleteArticle,
+    FavoriteArticle,
+    CreateComment,
+    UpdateComment,
+    DeleteComment,
+    ViewCommentRevisions,
+    ViewArticleRevisions,
+    ReportContent,
+    ViewHiddenContent,
+    ModerateContent,
+    PinContent,
+    ManageUsers,
+    ManageWebhook,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Denied {
+    Unauthenticated,
+    Forbidden,
+}
+
+/// The single place deciding what a role may do; `is_owner` is true when the caller authored the content.
+pub fn is_allowed(role: Role, action: Action, is_owner: bool) -> bool {
+    match action {
+        Action::CreateArticle | Action::FavoriteArticle | Action::CreateComment | Action::ReportContent => true,
+        Action::UpdateArticle | Action::UpdateComment | Action::ManageWebhook => is_owner || role == Role::Admin,
+        Action::DeleteArticle | Action::DeleteComment | Action::ViewHiddenContent | Action::ViewArticleRevisions => {
+            is_owner || role >= Role::Moderator
+        }
+        Action::PinContent | Action::ViewCommentRevisions | Action::ModerateContent => role >= Role::Moderator,
+        Action::ManageUsers => role == Role::Admin,
+    }
+}
+
+#[cfg(feature = "diesel")]
+pub fn role_of(user_id: i32) -> Role {
+    use schema::userroles::dsl::*;
+
+    let connection = establish_connection();
+    let user_role: Option<UserRole> = userroles
+        .filter(userid.eq(user_id))
+        .first(&connection)
+        .optional()
+        .expect("Error loading user role");
+
+    user_role
+        .and_then(|r| Role::from_name(&r.role))
+        .unwrap_or(Role::User)
+}
+
+#[cfg(feature = "diesel")]
+pub fn grant_role(user_id: i32, new_role: Role, granted_by: Option<i32>) {
+    use schema::userroles;
+    use chrono::prelude::*;
+
+    let connection = establish_connection();
+
+    diesel::delete(userroles::table.filter(userroles::userid.eq(user_id)))
+        .execute(&connection)
+        .expect("Error removing previous user role");
+
+    if new_role == Role::User {
+        return;
+    }
+
+    let utc: DateTime<Utc> = Utc::now();
+    let user_role = NewUserRole {
+        userid: user_id,
+        role: new_role.name(),
+        grantedby: granted_by,
+        grantedat: utc.naive_utc(),
+    };
+    diesel::insert(&user_role)
+        .into(userroles::table)
+        .execute(&connection)
+        .expect("Error saving user role");
+}
+
+#[cfg(feature = "diesel")]
+pub fn check(actor_id: i32, action: Action, owner_id: Option<i32>) -> Result<(), Denied> {
+    if actor_id <= 0 {
+        return Err(Denied::Unauthenticated);
+    }
+
+    let is_owner = owner_id == Some(actor_id);
+    // plain users are the common case, so only look up the role when it matters
+    if is_allowed(Role::User, action, is_owner) || is_allowed(role_of(actor_id), action, is_owner) {
+        Ok(())
+    } else {
+        Err(Denied::Forbidden)
+    }
+}
+
+pub fn send_denied(res: Response, denied: Denied) {
+    match denied {
+        Denied::Unauthenticated => {
+            send_error(res, StatusCode::Unauthorized, "authentication required")
+        }
+        Denied::Forbidden => {
+            send_error(
+                res,
+                StatusCode::Forbidden,
+                "you are not allowed to perform this action",
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn policy_rules_test() {
+    assert!(is_allowed(Role::User, Action::UpdateArticle, true));
+    assert!(!is_allowed(Role::User, Action::UpdateArticle, false));
+    assert!(!is_allowed(Role::Moderator, Action::UpdateArticle, false));
+    assert!(is_allowed(Role::Admin, Action::UpdateArticle, false));
+
+    assert!(!is_allowed(Role::User, Action::DeleteComment, false));
+    assert!(is_allowed(Role::Moderator, Action::DeleteComment, false));
+
+    assert!(!is_allowed(Role::User, Action::PinContent, true));
+    assert!(is_allowed(Role::Moderator, Action::PinContent, false));
+    assert!(!is_allowed(Role::User, Action::ViewCommentRevisions, true));
+    assert!(is_allowed(Role::User, Action::ViewArticleRevisions, true));
+    assert!(!is_allowed(Role::User, Action::ViewArticleRevisions, false));
+    assert!(is_allowed(Role::Moderator, Action::ViewArticleRevisions, false));
+
+    assert!(is_allowed(Role::User, Action::ReportContent, false));
+    assert!(is_allowed(Role::User, Action::ViewHiddenContent, true));
+    assert!(!is_allowed(Role::User, Action::ViewHiddenContent, false));
+    assert!(!is_allowed(Role::User, Action::ModerateContent, true));
+    assert!(is_allowed(Role::Moderator, Action::ModerateContent, false));
+
+    assert!(!is_allowed(Role::Moderator, Action::ManageUsers, false));
+    assert!(is_allowed(Role::Admin, Action::ManageUsers, false));
+
+    assert!(is_allowed(Role::User, Action::ManageWebhook, true));
+    assert!(!is_allowed(Role::Moderator, Action::ManageWebhook, false));
+}
@@ -0,0 +1,326 @@
+extern crate hyper;
+
+extern crate serde;
+extern crate serde_json;
+
+extern crate chrono;
+
+extern crate reroute;
+
+use hyper::status::StatusCode;
+
+use hyper::server::{Request, Response};
+use reroute::Captures;
+
+use std::collections::HashMap;
+
+use super::*;
+
+/// How often one reaction was given to a comment or article, and whether the viewer gave it.
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReactionCount {
+    pub reaction: String,
+    pub count: i64,
+    pub reacted: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+pub struct ReactionsResult {
+    pub reactions: Vec<ReactionCount>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReactionTarget {
+    Comment(i32),
+    Article(i32),
+}
+
+/// Sums up `(target, user, reaction)` rows per target, reactions in the order of the configured set.
+pub fn count_reactions(
+    rows: &[(i32, i32, String)],
+    viewer_id: i32,
+    reaction_set: &[String],
+) -> HashMap<i32, Vec<ReactionCount>> {
+    let mut counts: HashMap<i32, Vec<ReactionCount>> = HashMap::new();
+    for &(target_id, user_id, ref reaction) in rows {
+        let target = counts.entry(target_id).or_insert_with(Vec::new);
+        let position = match target.iter().position(|r| r.reaction == *reaction) {
+            Some(position) => position,
+            None => {
+                target.push(ReactionCount {
+                    reaction: reaction.to_owned(),
+                    count: 0,
+                    reacted: false,
+                });
+                target.len() - 1
+            }
+        };
+        target[position].count += 1;
+        target[position].reacted |= user_id == viewer_id;
+    }
+
+    // reactions removed from the configuration since go last
+    for target in counts.values_mut() {
+        target.sort_by_key(|r| {
+            reaction_set.iter().position(|name| *name == r.reaction).unwrap_or(reaction_set.len())
+        });
+    }
+    counts
+}
+
+/// Reactions of every comment in `comment_ids`, loaded in one query.
+#[cfg(feature = "diesel")]
+pub fn get_comment_reactions(comment_ids: &[i32], viewer_id: i32) -> HashMap<i32, Vec<ReactionCount>> {
+    use schema::reactions;
+    use diesel::expression::dsl::any;
+
+    let connection = establish_connection();
+    let target_ids: Vec<Option<i32>> = comment_ids.iter().map(|&id| Some(id)).collect();
+
+    let rows: Vec<(Option<i32>, i32, String)> = reactions::table
+        .filter(reactions::commentid.eq(any(&target_ids)))
+        .order(reactions::id.asc())
+        .select((reactions::commentid, reactions::userid, reactions::reaction))
+        .load(&connection)
+        .expect("Error loading reactions");
+    let rows: Vec<(i32, i32, String)> = rows.into_iter()
+        .filter_map(|(comment_id, user_id, reaction)| comment_id.map(|id| (id, user_id, reaction)))
+        .collect();
+
+    count_reactions(&rows, viewer_id, &REACTIONS)
+}
+
+#[cfg(feature = "diesel")]
+fn get_reactions_result(params: (ReactionTarget, i32)) -> Option<ReactionsResult> {
+    use schema::reactions::dsl::*;
+
+    let (target, viewer_id) = params;
+    let connection = establish_connection();
+
+    let rows: Vec<(i32, String)> = match target {
+        ReactionTarget::Comment(comment_id) => {
+            reactions
+                .filter(commentid.eq(comment_id))
+                .order(id.asc())
+                .select((userid, reaction))
+                .load(&connection)
+        }
+        ReactionTarget::Article(article_id) => {
+            reactions
+                .filter(articleid.eq(article_id))
+                .order(id.asc())
+                .select((userid, reaction))
+                .load(&connection)
+        }
+    }.expect("Error loading reactions");
+    let rows: Vec<(i32, i32, String)> = rows.into_iter().map(|(user_id, name)| (0, user_id, name)).collect();
+
+    Some(ReactionsResult {
+        reactions: count_reactions(&rows, viewer_id, &REACTIONS).remove(&0).unwrap_or_default(),
+    })
+}
+
+#[cfg(feature = "diesel")]
+fn add_reaction(target: ReactionTarget, user_id: i32, name: &str) {
+    use schema::reactions;
+    use diesel::result::{DatabaseErrorKind, Error};
+
+    let connection = establish_connection();
+    let (comment_id, article_id) = match target {
+        ReactionTarget::Comment(comment_id) => (Some(comment_id), None),
+        ReactionTarget::Article(article_id) => (None, Some(article_id)),
+    };
+    let new_reaction = NewReaction {
+        userid: user_id,
+        reaction: name,
+        commentid: comment_id,
+        articleid: article_id,
+        createdat: Utc::now().naive_utc(),
+    };
+    // reacting twice is a no-op, see ix_reactions_comments and ix_reactions_articles
+    match diesel::insert(&new_reaction).into(reactions::table).execute(&connection) {
+        Ok(_) | Err(Error::DatabaseError(DatabaseErrorKind::UniqueViolation, _)) => {}
+        Err(e) => panic!("Error saving reaction: {}", e),
+    }
+}
+
+#[cfg(feature = "diesel")]
+fn remove_reaction(target: ReactionTarget, user_id: i32, name: &str) {
+    use schema::reactions::dsl::*;
+
+    let connection = establish_connection();
+    let own = reactions.filter(userid.eq(user_id).and(reaction.eq(name)));
+    match target {
+        ReactionTarget::Comment(comment_id) => diesel::delete(own.filter(commentid.eq(comment_id))).execute(&connection),
+        ReactionTarget::Article(article_id) => diesel::delete(own.filter(articleid.eq(article_id))).execute(&connection),
+    }.expect("Error removing reaction");
+}
+
+/// The comment or article a `<slug>` or `<slug>/comments/<id>` path names, with its author, if `viewer_id` can see it.
+#[cfg(feature = "diesel")]
+fn find_reaction_target(path: &str, viewer_id: i32) -> Option<(ReactionTarget, i32)> {
+    if path.contains("/comments/") {
+        let mut parts = path.splitn(2, "/comments/");
+        let url_slug = parts.next().unwrap();
+        let comment_id = parts.next().unwrap();
+        find_visible_article(url_slug, viewer_id)?;
+        // a reported comment waiting for review is in no thread
+        match find_article_comment(url_slug, comment_id) {
+            Some(ref comment) if !comment.deleted && !comment.hidden => {
+                Some((ReactionTarget::Comment(comment.id), comment.author))
+            }
+            _ => None,
+        }
+    } else {
+        find_visible_article(path, viewer_id).map(|article| (ReactionTarget::Article(article.id), article.author))
+    }
+}
+
+#[cfg(feature = "diesel")]
+fn reaction_handler(req: Request, res: Response, c: Captures, change: fn(ReactionTarget, i32, &str)) {
+    let (_, logged_id) = prepare_parameters(req);
+
+    let caps = c.unwrap();
+    let path = caps[0].replace("/api/articles/", "");
+    let mut parts = path.splitn(2, "/reactions/");
+    let target_path = parts.next().unwrap();
+    let name = parts.next().unwrap_or("");
+    println!("reaction_handler target: '{}' reaction: '{}'", target_path, name);
+
+    if logged_id <= 0 {
+        send_denied(res, Denied::Unauthenticated);
+        return;
+    }
+    if !REACTIONS.iter().any(|r| r == name) {
+        let message = format!("reaction must be one of: {}", REACTIONS.join(", "));
+        send_error(res, StatusCode::UnprocessableEntity, &message);
+        return;
+    }
+    let (target, author_id) = match find_reaction_target(target_path, logged_id) {
+        Some((ReactionTarget::Article(_), _)) if !*ARTICLE_REACTIONS => {
+            send_error(res, StatusCode::NotFound, "reactions on articles are disabled");
+            return;
+        }
+        Some(found) => found,
+        None => {
+            send_error(res, StatusCode::NotFound, "nothing to react to");
+            return;
+        }
+    };
+    if is_blocked(author_id, logged_id) {
+        send_error(res, StatusCode::Forbidden, "the author has blocked you");
+        return;
+    }
+
+    change(target, logged_id, name);
+    process(res, get_reactions_result, (target, logged_id))
+}
+
+#[cfg(feature = "diesel")]
+pub fn add_reaction_handler(req: Request, res: Response, c: Captures) {
+    reaction_handler(req, res, c, add_reaction)
+}
+
+#[cfg(feature = "diesel")]
+pub fn remove_reaction_handler(req: Request, res: Response, c: Captures) {
+    reaction_handler(req, res, c, remove_reaction)
+}
+
+#[cfg(feature = "diesel")]
+pub fn article_reactions_handler(req: Request, res: Response, c: Captures) {
+    let (_, logged_id) = prepare_parameters(req);
+
+    let caps = c.unwrap();
+    let url_slug = caps[0].replace("/api/articles/", "").replace("/reactions", "");
+
+    match find_visible_article(&url_slug, logged_id) {
+        Some(ref article) if *ARTICLE_REACTIONS => {
+            process(res, get_reactions_result, (ReactionTarget::Article(article.id), logged_id))
+        }
+        _ => send_error(res, StatusCode::NotFound, "article not found"),
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn count_reactions_test() {
+    let reaction_set = vec!["+1".to_string(), "heart".to_string(), "rocket".to_string()];
+    let rows = vec![
+        (1, 10, "rocket".to_string()),
+        (1, 11, "+1".to_string()),
+        (1, 12, "rocket".to_string()),
+        (2, 10, "heart".to_string()),
+    ];
+
+    let counts = count_reactions(&rows, 12, &reaction_set);
+    assert_eq!(
+        counts[&1],
+        vec![
+            ReactionCount { reaction: "+1".to_string(), count: 1, reacted: false },
+            ReactionCount { reaction: "rocket".to_string(), count: 2, reacted: true },
+        ]
+    );
+    assert_eq!(counts[&2].len(), 1);
+    assert!(!counts.contains_key(&3));
+}
+
+#[cfg(test)]
+#[test]
+fn comment_reaction_test() {
+    let client = Client::new();
+
+    let (jwt, slug, _) = login_create_article(false);
+    let url = format!("http://localhost:6767/api/articles/{}/comments", slug);
+
+    let mut ids = Vec::new();
+    for body in &["Plain comment.", "Popular comment."] {
+        let mut res = client
+            .post(&url)
+            .header(Authorization(Bearer { token: jwt.to_owned() }))
+            .body(&format!(r#"{{"comment": {{"body": "{}"}}}}"#, body))
+            .send()
+            .unwrap();
+        let mut buffer = String::new();
+        res.read_to_string(&mut buffer).unwrap();
+        let created: CommentResult = serde_json::from_str(&buffer).unwrap();
+        ids.push(created.comment.id);
+    }
+
+    let reaction_url = format!("{}/{}/reactions/{}", url, ids[1], REACTIONS[0]);
+    for _ in 0..2 {
+        let mut res = client
+            .post(&reaction_url)
+            .header(Authorization(Bearer { token: jwt.to_owned() }))
+            .body("")
+            .send()
+            .unwrap();
+        let mut buffer = String::new();
+        res.read_to_string(&mut buffer).unwrap();
+        assert_eq!(res.status, hyper::Ok);
+        let result: ReactionsResult = serde_json::from_str(&buffer).unwrap();
+        assert_eq!(result.reactions[0].count, 1);
+        assert!(result.reactions[0].reacted);
+    }
+
+    let res = client
+        .post(&format!("{}/{}/reactions/not-a-reaction", url, ids[1]))
+        .header(Authorization(Bearer { token: jwt.to_owned() }))
+        .body("")
+        .send()
+        .unwrap();
+    assert_eq!(res.status, StatusCode::UnprocessableEntity);
+
+    let mut res = client
+        .get(&format!("{}?sort=top", url))
+        .header(Authorization(Bearer { token: jwt }))
+        .send()
+        .unwrap();
+    let mut buffer = String::new();
+    res.read_to_string(&mut buffer).unwrap();
+    let comments: CommentsResult = serde_json::from_str(&buffer).unwrap();
+    assert_eq!(comments.comments[0].id, ids[1]);
+    assert_eq!(comments.comments[0].reactions[0].count, 1);
+    assert!(comments.comments[1].reactions.is_empty());
+}
@@ -0,0 +1,639 @@
+extern crate hyper;
+
+extern crate serde;
+extern crate serde_json;
+
+extern crate chrono;
+
+extern crate reroute;
+
+use hyper::status::StatusCode;
+
+use hyper::server::{Request, Response};
+use reroute::Captures;
+
+use std::collections::HashMap;
+
+use super::*;
+
+static OPEN: &'static str = "open";
+static RESOLVED: &'static str = "resolved";
+static DISMISSED: &'static str = "dismissed";
+
+/// Shown as the reporter of content the content filter held back.
+static FILTER_REPORTER: &'static str = "content filter";
+
+const MAX_REPORTS_LIMIT: i64 = 100;
+
+/// Why a reader reported an article or comment.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReportReason {
+    Spam,
+    Abuse,
+    Harassment,
+    OffTopic,
+    Other,
+}
+
+impl ReportReason {
+    pub fn from_name(name: &str) -> Option<ReportReason> {
+        match name {
+            "spam" => Some(ReportReason::Spam),
+            "abuse" => Some(ReportReason::Abuse),
+            "harassment" => Some(ReportReason::Harassment),
+            "off-topic" => Some(ReportReason::OffTopic),
+            "other" => Some(ReportReason::Other),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            ReportReason::Spam => "spam",
+            ReportReason::Abuse => "abuse",
+            ReportReason::Harassment => "harassment",
+            ReportReason::OffTopic => "off-topic",
+            ReportReason::Other => "other",
+        }
+    }
+}
+
+/// What a moderator decided: `Resolve` upholds the reports and keeps the content hidden, `Dismiss` shows it again.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReviewAction {
+    Resolve,
+    Dismiss,
+}
+
+impl ReviewAction {
+    pub fn from_name(name: &str) -> Option<ReviewAction> {
+        match name {
+            "resolve" => Some(ReviewAction::Resolve),
+            "dismiss" => Some(ReviewAction::Dismiss),
+            _ => None,
+        }
+    }
+
+    fn status(&self) -> &'static str {
+        match *self {
+            ReviewAction::Resolve => RESOLVED,
+            ReviewAction::Dismiss => DISMISSED,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+struct IncomingReportDetail {
+    reason: String,
+    details: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+struct IncomingReport {
+    report: IncomingReportDetail,
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+struct BulkReview {
+    action: String,
+    ids: Vec<i32>,
+}
+
+/// A report as the moderation queue shows it; `hidden` tells whether the reported content is hidden right now.
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+#[allow(non_snake_case)]
+pub struct ReportDTO {
+    pub id: i32,
+    pub reason: String,
+    pub details: Option<String>,
+    pub status: String,
+    pub reporter: String,
+    pub article: String,
+    pub commentId: Option<i32>,
+    pub hidden: bool,
+    pub createdAt: NaiveDateTime,
+    pub resolvedBy: Option<String>,
+    pub resolvedAt: Option<NaiveDateTime>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+pub struct ReportResult {
+    pub report: ReportDTO,
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+pub struct ReportsResult {
+    pub reports: Vec<ReportDTO>,
+}
+
+impl Container<ReportDTO> for ReportsResult {
+    fn create_new_with_items(reports: Vec<ReportDTO>) -> ReportsResult {
+        ReportsResult { reports: reports }
+    }
+}
+
+/// Hidden articles stay readable for their author and for moderators.
+#[cfg(feature = "diesel")]
+pub fn is_visible(article: &Article, viewer_id: i32) -> bool {
+    !article.hidden || check(viewer_id, Action::ViewHiddenContent, Some(article.author)).is_ok()
+}
+
+#[cfg(feature = "diesel")]
+fn to_report_dtos(found_reports: Vec<Report>, connection: &PgConnection) -> Vec<ReportDTO> {
+    use schema::{articles, comments, users};
+    use diesel::expression::dsl::any;
+
+    let mut user_ids: Vec<i32> = found_reports.iter().filter_map(|r| r.reporterid).collect();
+    user_ids.extend(found_reports.iter().filter_map(|r| r.resolvedby));
+    let article_ids: Vec<i32> = found_reports.iter().map(|r| r.articleid).collect();
+    let comment_ids: Vec<i32> = found_reports.iter().filter_map(|r| r.commentid).collect();
+
+    let user_names: HashMap<i32, String> = users::table
+        .filter(users::id.eq(any(&user_ids)))
+        .select((users::id, users::username))
+        .load::<(i32, String)>(connection)
+        .expect("Error loading reporters")
+        .into_iter()
+        .collect();
+    let reported_articles: HashMap<i32, (String, bool)> = articles::table
+        .filter(articles::id.eq(any(&article_ids)))
+        .select((articles::id, articles::slug, articles::hidden))
+        .load::<(i32, String, bool)>(connection)
+        .expect("Error loading reported articles")
+        .into_iter()
+        .map(|(article_id, article_slug, article_hidden)| (article_id, (article_slug, article_hidden)))
+        .collect();
+    let hidden_comments: HashMap<i32, bool> = comments::table
+        .filter(comments::id.eq(any(&comment_ids)))
+        .select((comments::id, comments::hidden))
+        .load::<(i32, bool)>(connection)
+        .expect("Error loading reported comments")
+        .into_iter()
+        .collect();
+
+    found_reports
+        .into_iter()
+        .map(|r| {
+            let (article_slug, article_hidden) = reported_articles[&r.articleid].clone();
+            ReportDTO {
+                id: r.id,
+                reason: r.reason,
+                details: r.details,
+                status: r.status,
+                reporter: match r.reporterid {
+                    Some(user_id) => user_names.get(&user_id).cloned().unwrap_or_default(),
+                    None => FILTER_REPORTER.to_string(),
+                },
+                article: article_slug,
+                commentId: r.commentid,
+                hidden: match r.commentid {
+                    Some(comment_id) => hidden_comments.get(&comment_id).cloned().unwrap_or(false),
+                    None => article_hidden,
+                },
+                createdAt: r.createdat,
+                resolvedBy: r.resolvedby.and_then(|user_id| user_names.get(&user_id).cloned()),
+                resolvedAt: r.resolvedat,
+            }
+        })
+        .collect()
+}
+
+#[cfg(feature = "diesel")]
+fn set_hidden(article_id: i32, comment_id: Option<i32>, hide: bool, connection: &PgConnection) {
+    use schema::{articles, comments};
+
+    match comment_id {
+        Some(comment_id) => {
+            diesel::update(comments::table.find(comment_id))
+                .set(comments::hidden.eq(hide))
+                .execute(connection)
+        }
+        None => {
+            diesel::update(articles::table.find(article_id))
+                .set(articles::hidden.eq(hide))
+                .execute(connection)
+        }
+    }.expect("Error hiding reported content");
+}
+
+#[cfg(feature = "diesel")]
+fn count_open_reports(article_id: i32, comment_id: Option<i32>, connection: &PgConnection) -> i64 {
+    use schema::reports::dsl::*;
+
+    let open_reports = reports.filter(articleid.eq(article_id).and(status.eq(OPEN)));
+    match comment_id {
+        Some(comment_id) => open_reports.filter(commentid.eq(comment_id)).count().get_result(connection),
+        None => open_reports.filter(commentid.is_null()).count().get_result(connection),
+    }.expect("Error counting reports")
+}
+
+#[cfg(feature = "diesel")]
+fn find_own_report(reporter_id: i32, article_id: i32, comment_id: Option<i32>, connection: &PgConnection) -> Report {
+    use schema::reports::dsl::*;
+
+    let own_reports = reports.filter(reporterid.eq(reporter_id).and(articleid.eq(article_id)));
+    match comment_id {
+        Some(comment_id) => own_reports.filter(commentid.eq(comment_id)).first(connection),
+        None => own_reports.filter(commentid.is_null()).first(connection),
+    }.expect("Error loading report")
+}
+
+#[cfg(feature = "diesel")]
+fn file_report(params: (i32, i32, Option<i32>, ReportReason, Option<String>)) -> Option<ReportResult> {
+    use schema::reports;
+    use diesel::result::{DatabaseErrorKind, Error};
+
+    let (reporter_id, article_id, comment_id, reason, details) = params;
+    let connection = establish_connection();
+
+    let new_report = NewReport {
+        reporterid: Some(reporter_id),
+        articleid: article_id,
+        commentid: comment_id,
+        reason: reason.name(),
+        details: details.as_ref().map(|d| d.as_str()),
+        status: OPEN,
+        createdat: Utc::now().naive_utc(),
+        tenantid: current_tenant(),
+    };
+    // reporting the same content twice counts once, see ix_reports_articles and ix_reports_comments
+    let report: Report = match diesel::insert(&new_report).into(reports::table).get_result(&connection) {
+        Ok(report) => report,
+        Err(Error::DatabaseError(DatabaseErrorKind::UniqueViolation, _)) => {
+            find_own_report(reporter_id, article_id, comment_id, &connection)
+        }
+        Err(e) => panic!("Error saving report: {}", e),
+    };
+
+    if report.status == OPEN && count_open_reports(article_id, comment_id, &connection) >= *REPORT_THRESHOLD {
+        set_hidden(article_id, comment_id, true, &connection);
+    }
+
+    Some(ReportResult { report: to_report_dtos(vec![report], &connection).remove(0) })
+}
+
+/// Queues content the content filter held back; it was saved hidden already.
+#[cfg(feature = "diesel")]
+pub fn hold_for_review(article_id: i32, comment_id: Option<i32>, filter_reason: &str) {
+    use schema::reports;
+
+    let connection = establish_connection();
+    let new_report = NewReport {
+        reporterid: None,
+        articleid: article_id,
+        commentid: comment_id,
+        reason: ReportReason::Spam.name(),
+        details: Some(filter_reason),
+        status: OPEN,
+        createdat: Utc::now().naive_utc(),
+        tenantid: current_tenant(),
+    };
+    diesel::insert(&new_report)
+        .into(reports::table)
+        .execute(&connection)
+        .expect("Error saving report");
+}
+
+/// The article, optional comment and author a `<slug>` or `<slug>/comments/<id>` path names, if the reporter can see it.
+#[cfg(feature = "diesel")]
+fn find_report_target(path: &str, reporter_id: i32) -> Option<(i32, Option<i32>, i32)> {
+    let url_slug = path.split("/comments/").next().unwrap();
+    let article = match find_article(url_slug) {
+        Some(article) => article,
+        None => return None,
+    };
+    if is_blocked(article.author, reporter_id) || !is_visible(&article, reporter_id) {
+        return None;
+    }
+
+    if path.contains("/comments/") {
+        let comment_id = path.splitn(2, "/comments/").nth(1).unwrap();
+        match find_article_comment(url_slug, comment_id) {
+            Some(ref comment) if !comment.deleted && !comment.hidden => {
+                Some((article.id, Some(comment.id), comment.author))
+            }
+            _ => None,
+        }
+    } else {
+        Some((article.id, None, article.author))
+    }
+}
+
+#[cfg(feature = "diesel")]
+pub fn report_handler(req: Request, res: Response, c: Captures) {
+    let (body, logged_id) = prepare_parameters(req);
+
+    let caps = c.unwrap();
+    let path = caps[0].replace("/api/articles/", "").replace("/report", "");
+    println!("report_handler target: '{}'", path);
+
+    if let Err(denied) = check(logged_id, Action::ReportContent, None) {
+        send_denied(res, denied);
+        return;
+    }
+    let incoming: IncomingReport = match serde_json::from_str(&body) {
+        Ok(incoming) => incoming,
+        Err(_) => {
+            send_error(res, StatusCode::UnprocessableEntity, "report must have a reason");
+            return;
+        }
+    };
+    let reason = match ReportReason::from_name(&incoming.report.reason) {
+        Some(reason) => reason,
+        None => {
+            send_error(
+                res,
+                StatusCode::UnprocessableEntity,
+                "reason must be one of: spam, abuse, harassment, off-topic, other",
+            );
+            return;
+        }
+    };
+    let (article_id, comment_id, author_id) = match find_report_target(&path, logged_id) {
+        Some(target) => target,
+        None => {
+            send_error(res, StatusCode::NotFound, "nothing to report");
+            return;
+        }
+    };
+    if author_id == logged_id {
+        send_error(res, StatusCode::UnprocessableEntity, "you cannot report your own content");
+        return;
+    }
+
+    process(res, file_report, (logged_id, article_id, comment_id, reason, incoming.report.details))
+}
+
+fn reports_result(_: ReportsResult) {}
+
+#[cfg(feature = "diesel")]
+fn list_reports(params: (String, i64, i64)) -> Vec<ReportDTO> {
+    use schema::reports::dsl::*;
+
+    let (report_status, limit, offset) = params;
+    let connection = establish_connection();
+
+    // oldest first, nothing waits in the queue forever
+    let found_reports: Vec<Report> = reports
+        .filter(status.eq(report_status).and(tenantid.eq(current_tenant())))
+        .order(createdat.asc())
+        .limit(limit)
+        .offset(offset)
+        .load(&connection)
+        .expect("Error loading reports");
+    to_report_dtos(found_reports, &connection)
+}
+
+#[cfg(feature = "diesel")]
+pub fn list_reports_handler(req: Request, res: Response, c: Captures) {
+    let (_, logged_id) = prepare_parameters(req);
+
+    let caps = c.unwrap();
+    let url = &caps[0];
+    let report_status = get_query_param(url, "status").unwrap_or(OPEN);
+    let limit: i64 = get_query_param(url, "limit").and_then(|v| v.parse().ok()).unwrap_or(20);
+    let offset: i64 = get_query_param(url, "offset").and_then(|v| v.parse().ok()).unwrap_or(0);
+
+    if let Err(denied) = check(logged_id, Action::ModerateContent, None) {
+        send_denied(res, denied);
+        return;
+    }
+    if ![OPEN, RESOLVED, DISMISSED].contains(&report_status) {
+        send_error(res, StatusCode::UnprocessableEntity, "status must be one of: open, resolved, dismissed");
+        return;
+    }
+
+    process_container(
+        res,
+        reports_result,
+        list_reports,
+        (report_status.to_string(), limit.max(1).min(MAX_REPORTS_LIMIT), offset.max(0)),
+    )
+}
+
+/// Applies the decision to `report` and every open report on the same content, returns the reports it closed.
+#[cfg(feature = "diesel")]
+fn review_report(report: &Report, action: ReviewAction, moderator_id: i32, connection: &PgConnection) -> Vec<Report> {
+    use schema::reports::dsl::*;
+
+    let now = Utc::now().naive_utc();
+    let reviewed = connection
+        .transaction::<_, diesel::result::Error, _>(|| {
+            set_hidden(report.articleid, report.commentid, action == ReviewAction::Resolve, connection);
+
+            // a reviewed report may be reviewed again, that overturns the earlier decision
+            let same_target = reports.filter(articleid.eq(report.articleid)).filter(
+                status.eq(OPEN).or(id.eq(report.id)),
+            );
+            let changes = (status.eq(action.status()), resolvedby.eq(Some(moderator_id)), resolvedat.eq(Some(now)));
+            match report.commentid {
+                Some(comment_id) => diesel::update(same_target.filter(commentid.eq(comment_id)))
+                    .set(changes)
+                    .get_results(connection),
+                None => diesel::update(same_target.filter(commentid.is_null()))
+                    .set(changes)
+                    .get_results(connection),
+            }
+        })
+        .expect("Error reviewing report");
+
+    learn_from_review(report, action, connection);
+    reviewed
+}
+
+#[cfg(feature = "diesel")]
+fn find_report(report_id: &str) -> Option<Report> {
+    use schema::reports::dsl::*;
+
+    let connection = establish_connection();
+    match report_id.parse::<i32>() {
+        Ok(parsed_id) => reports
+            .find(parsed_id)
+            .filter(tenantid.eq(current_tenant()))
+            .first(&connection)
+            .optional()
+            .expect("Error loading report"),
+        Err(_) => None,
+    }
+}
+
+#[cfg(feature = "diesel")]
+fn review(params: (Report, ReviewAction, i32)) -> Option<ReportResult> {
+    let (report, action, moderator_id) = params;
+    let connection = establish_connection();
+
+    let report_id = report.id;
+    let reviewed = review_report(&report, action, moderator_id, &connection)
+        .into_iter()
+        .find(|r| r.id == report_id)
+        .unwrap_or(report);
+    Some(ReportResult { report: to_report_dtos(vec![reviewed], &connection).remove(0) })
+}
+
+#[cfg(feature = "diesel")]
+fn review_handler(req: Request, res: Response, c: Captures, action: ReviewAction) {
+    let (_, logged_id) = prepare_parameters(req);
+
+    let caps = c.unwrap();
+    let report_id = caps[0]
+        .replace("/api/moderation/reports/", "")
+        .replace("/resolve", "")
+        .replace("/dismiss", "");
+    println!("review_handler report: '{}' action: {:?}", report_id, action);
+
+    if let Err(denied) = check(logged_id, Action::ModerateContent, None) {
+        send_denied(res, denied);
+        return;
+    }
+    match find_report(&report_id) {
+        Some(report) => process(res, review, (report, action, logged_id)),
+        None => send_error(res, StatusCode::NotFound, "report not found"),
+    }
+}
+
+#[cfg(feature = "diesel")]
+pub fn resolve_report_handler(req: Request, res: Response, c: Captures) {
+    review_handler(req, res, c, ReviewAction::Resolve)
+}
+
+#[cfg(feature = "diesel")]
+pub fn dismiss_report_handler(req: Request, res: Response, c: Captures) {
+    review_handler(req, res, c, ReviewAction::Dismiss)
+}
+
+#[cfg(feature = "diesel")]
+fn review_all(params: (Vec<i32>, ReviewAction, i32)) -> Vec<ReportDTO> {
+    use schema::reports::dsl::*;
+    use diesel::expression::dsl::any;
+
+    let (report_ids, action, moderator_id) = params;
+    let connection = establish_connection();
+
+    let found_reports: Vec<Report> = reports
+        .filter(id.eq(any(&report_ids)).and(tenantid.eq(current_tenant())))
+        .order(id.asc())
+        .load(&connection)
+        .expect("Error loading reports");
+    let mut reviewed: Vec<Report> = Vec::new();
+    for report in &found_reports {
+        // an earlier report on the same content may have closed this one already
+        if reviewed.iter().any(|r| r.id == report.id) {
+            continue;
+        }
+        reviewed.extend(review_report(report, action, moderator_id, &connection));
+    }
+    reviewed.retain(|r| report_ids.contains(&r.id));
+    reviewed.sort_by_key(|r| r.id);
+    to_report_dtos(reviewed, &connection)
+}
+
+#[cfg(feature = "diesel")]
+pub fn bulk_review_handler(req: Request, res: Response, _: Captures) {
+    let (body, logged_id) = prepare_parameters(req);
+
+    if let Err(denied) = check(logged_id, Action::ModerateContent, None) {
+        send_denied(res, denied);
+        return;
+    }
+    let incoming: BulkReview = match serde_json::from_str(&body) {
+        Ok(incoming) => incoming,
+        Err(_) => {
+            send_error(res, StatusCode::UnprocessableEntity, "a bulk review needs an action and report ids");
+            return;
+        }
+    };
+    let action = match ReviewAction::from_name(&incoming.action) {
+        Some(action) => action,
+        None => {
+            send_error(res, StatusCode::UnprocessableEntity, "action must be one of: resolve, dismiss");
+            return;
+        }
+    };
+    if incoming.ids.len() as i64 > MAX_REPORTS_LIMIT {
+        let message = format!("at most {} reports can be reviewed at once", MAX_REPORTS_LIMIT);
+        send_error(res, StatusCode::UnprocessableEntity, &message);
+        return;
+    }
+
+    process_container(res, reports_result, review_all, (incoming.ids, action, logged_id))
+}
+
+#[cfg(test)]
+#[test]
+fn report_threshold_test() {
+    let client = Client::new();
+    let (_, slug, _) = login_create_article(false);
+    let article_url = format!("http://localhost:6767/api/articles/{}", slug);
+
+    let mut report_ids = Vec::new();
+    for _ in 0..*REPORT_THRESHOLD {
+        let (_, email) = register_jacob();
+        let jwt = login_jacob(email, JACOB_PASSWORD.to_string());
+        let mut res = client
+            .post(&format!("{}/report", article_url))
+            .header(Authorization(Bearer { token: jwt }))
+            .body(r#"{"report": {"reason": "spam", "details": "sells dragon eggs"}}"#)
+            .send()
+            .unwrap();
+        let mut buffer = String::new();
+        res.read_to_string(&mut buffer).unwrap();
+        assert_eq!(res.status, hyper::Ok);
+        let reported: ReportResult = serde_json::from_str(&buffer).unwrap();
+        assert_eq!(reported.report.status, "open");
+        report_ids.push(reported.report.id);
+    }
+
+    // enough reports hide the article from readers
+    let res = client.get(&article_url).send().unwrap();
+    assert_eq!(res.status, StatusCode::NotFound);
+
+    let (_, admin_jwt) = register_admin();
+    let mut res = client
+        .get("http://localhost:6767/api/moderation/reports?status=open&limit=100")
+        .header(Authorization(Bearer { token: admin_jwt.to_owned() }))
+        .send()
+        .unwrap();
+    let mut buffer = String::new();
+    res.read_to_string(&mut buffer).unwrap();
+    assert_eq!(res.status, hyper::Ok);
+    let queue: ReportsResult = serde_json::from_str(&buffer).unwrap();
+    let queued = queue.reports.iter().find(|r| r.id == report_ids[0]).unwrap();
+    assert_eq!(queued.article, slug);
+    assert!(queued.hidden);
+
+    let mut res = client
+        .post(&format!("http://localhost:6767/api/moderation/reports/{}/dismiss", report_ids[0]))
+        .header(Authorization(Bearer { token: admin_jwt }))
+        .body("")
+        .send()
+        .unwrap();
+    let mut buffer = String::new();
+    res.read_to_string(&mut buffer).unwrap();
+    assert_eq!(res.status, hyper::Ok);
+    let dismissed: ReportResult = serde_json::from_str(&buffer).unwrap();
+    assert_eq!(dismissed.report.status, "dismissed");
+    assert!(!dismissed.report.hidden);
+
+    let res = client.get(&article_url).send().unwrap();
+    assert_eq!(res.status, hyper::Ok);
+}
+
+#[cfg(test)]
+#[test]
+fn report_requires_moderator_test() {
+    let client = Client::new();
+    let jwt = login_other_jacob();
+
+    let res = client
+        .get("http://localhost:6767/api/moderation/reports")
+        .header(Authorization(Bearer { token: jwt }))
+        .send()
+        .unwrap();
+    assert_eq!(res.status, StatusCode::Forbidden);
+}
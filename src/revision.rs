@@ -0,0 +1,440 @@
+extern crate hyper;
+
+extern crate serde;
+extern crate serde_json;
+
+extern crate chrono;
+
+extern crate reroute;
+
+use hyper::status::StatusCode;
+
+use hyper::server::{Request, Response};
+use reroute::Captures;
+
+use super::*;
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+#[allow(non_snake_case)]
+pub struct ArticleRevisionDTO {
+    pub id: i32,
+    pub title: String,
+    pub description: String,
+    pub body: String,
+    /// `None` once the editor deleted their account.
+    pub editedBy: Option<i32>,
+    pub revisedAt: NaiveDateTime,
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+pub struct ArticleRevisionsResult {
+    pub revisions: Vec<ArticleRevisionDTO>,
+}
+
+impl Container<ArticleRevisionDTO> for ArticleRevisionsResult {
+    fn create_new_with_items(revisions: Vec<ArticleRevisionDTO>) -> ArticleRevisionsResult {
+        ArticleRevisionsResult { revisions: revisions }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DiffOp {
+    #[serde(rename = "equal")]
+    Equal,
+    #[serde(rename = "insert")]
+    Insert,
+    #[serde(rename = "delete")]
+    Delete,
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiffLine {
+    pub op: DiffOp,
+    pub text: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+pub struct RevisionDiff {
+    pub from: i32,
+    pub to: i32,
+    pub title: Vec<DiffLine>,
+    pub description: Vec<DiffLine>,
+    pub body: Vec<DiffLine>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+pub struct RevisionDiffResult {
+    pub diff: RevisionDiff,
+}
+
+fn diff_line(op: DiffOp, text: &str) -> DiffLine {
+    DiffLine {
+        op: op,
+        text: text.to_owned(),
+    }
+}
+
+/// Line-level diff from `old` to `new` along their longest common subsequence.
+pub fn diff_lines(old: &str, new: &str) -> Vec<DiffLine> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    // edits usually touch a few lines in the middle, the table only covers what lies between
+    let prefix = old.iter().zip(new.iter()).take_while(|&(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|&(a, b)| a == b)
+        .count();
+    let a = &old[prefix..old.len() - suffix];
+    let b = &new[prefix..new.len() - suffix];
+
+    // common[i][j] is the length of the longest common subsequence of a[i..] and b[j..]
+    let mut common = vec![vec![0u32; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            common[i][j] = if a[i] == b[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                std::cmp::max(common[i + 1][j], common[i][j + 1])
+            };
+        }
+    }
+
+    let mut lines: Vec<DiffLine> = old[..prefix].iter().map(|line| diff_line(DiffOp::Equal, line)).collect();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if a[i] == b[j] {
+            lines.push(diff_line(DiffOp::Equal, a[i]));
+            i += 1;
+            j += 1;
+        } else if common[i + 1][j] >= common[i][j + 1] {
+            lines.push(diff_line(DiffOp::Delete, a[i]));
+            i += 1;
+        } else {
+            lines.push(diff_line(DiffOp::Insert, b[j]));
+            j += 1;
+        }
+    }
+    lines.extend(a[i..].iter().map(|line| diff_line(DiffOp::Delete, line)));
+    lines.extend(b[j..].iter().map(|line| diff_line(DiffOp::Insert, line)));
+    lines.extend(old[old.len() - suffix..].iter().map(|line| diff_line(DiffOp::Equal, line)));
+    lines
+}
+
+/// Stores the text of `article` as a revision by `editor_id`, unless the latest revision already has it.
+#[cfg(feature = "diesel")]
+pub fn record_revision(article: &Article, editor_id: i32, connection: &PgConnection) -> QueryResult<()> {
+    use schema::articlerevisions;
+
+    let latest: Option<ArticleRevision> = articlerevisions::table
+        .filter(articlerevisions::articleid.eq(article.id))
+        .order(articlerevisions::id.desc())
+        .first(connection)
+        .optional()?;
+    // status changes and publishing leave the text alone
+    if let Some(latest) = latest {
+        if latest.title == article.title && latest.description == article.description && latest.body == article.body {
+            return Ok(());
+        }
+    }
+
+    let revision = NewArticleRevision {
+        articleid: article.id,
+        title: &article.title,
+        description: &article.description,
+        body: &article.body,
+        editorid: editor_id,
+        revisedat: Utc::now().naive_utc(),
+    };
+    diesel::insert(&revision)
+        .into(articlerevisions::table)
+        .execute(connection)?;
+    Ok(())
+}
+
+#[cfg(feature = "diesel")]
+fn get_article_revisions(article_id: i32) -> Vec<ArticleRevisionDTO> {
+    use schema::articlerevisions::dsl::*;
+
+    let connection = establish_connection();
+    articlerevisions
+        .filter(articleid.eq(article_id))
+        .order(id.asc())
+        .load::<ArticleRevision>(&connection)
+        .expect("Error loading article revisions")
+        .into_iter()
+        .map(|revision| {
+            ArticleRevisionDTO {
+                id: revision.id,
+                title: revision.title,
+                description: revision.description,
+                body: revision.body,
+                editedBy: revision.editorid,
+                revisedAt: revision.revisedat,
+            }
+        })
+        .collect()
+}
+
+#[cfg(feature = "diesel")]
+fn find_revision(article_id: i32, revision_id: &str) -> Option<ArticleRevision> {
+    use schema::articlerevisions::dsl::*;
+
+    let connection = establish_connection();
+    match revision_id.parse::<i32>() {
+        Ok(parsed_id) => articlerevisions
+            .filter(id.eq(parsed_id).and(articleid.eq(article_id)))
+            .first(&connection)
+            .optional()
+            .expect("Error loading article revision"),
+        Err(_) => None,
+    }
+}
+
+#[cfg(feature = "diesel")]
+fn diff_revisions(params: (ArticleRevision, ArticleRevision)) -> Option<RevisionDiffResult> {
+    let (from, to) = params;
+
+    Some(RevisionDiffResult {
+        diff: RevisionDiff {
+            from: from.id,
+            to: to.id,
+            title: diff_lines(&from.title, &to.title),
+            description: diff_lines(&from.description, &to.description),
+            body: diff_lines(&from.body, &to.body),
+        },
+    })
+}
+
+/// Saves the text of `revision` as a new update, so restoring is itself part of the history.
+#[cfg(feature = "diesel")]
+fn restore_revision(params: (Article, ArticleRevision, i32, Option<String>)) -> Option<ArticleResult> {
+    use models::UpdatedArticle;
+
+    let (article, revision, editor_id, held_because) = params;
+    let restored_slug = if revision.title == article.title {
+        article.slug.clone()
+    } else {
+        unique_slug(&revision.title, Some(article.id))
+    };
+    let restored = UpdatedArticle {
+        id: article.id,
+        slug: &restored_slug,
+        title: &revision.title,
+        description: &revision.description,
+        body: &revision.body,
+        author: article.author,
+        createdat: article.createdAt,
+        updatedat: article.updatedAt,
+        status: &article.status,
+        publishedat: article.publishedAt,
+        publishat: article.publishAt,
+        hidden: article.hidden || held_because.is_some(),
+    };
+    update_article((restored, editor_id, false, false, held_because))
+}
+
+fn article_revisions_result(_: ArticleRevisionsResult) {}
+
+#[cfg(feature = "diesel")]
+pub fn article_revisions_handler(req: Request, res: Response, c: Captures) {
+    let (_, logged_id) = prepare_parameters(req);
+
+    let caps = c.unwrap();
+    let url_slug = caps[0].replace("/api/articles/", "").replace("/revisions", "");
+    println!("article_revisions_handler slug: '{}'", url_slug);
+
+    let article = match find_article(&url_slug) {
+        Some(article) => article,
+        None => {
+            send_error(res, StatusCode::NotFound, "article not found");
+            return;
+        }
+    };
+    if let Err(denied) = check(logged_id, Action::ViewArticleRevisions, Some(article.author)) {
+        send_denied(res, denied);
+        return;
+    }
+    process_container(res, article_revisions_result, get_article_revisions, article.id)
+}
+
+#[cfg(feature = "diesel")]
+pub fn revision_diff_handler(req: Request, res: Response, c: Captures) {
+    let (_, logged_id) = prepare_parameters(req);
+
+    let caps = c.unwrap();
+    let url = &caps[0];
+    let path = url.split('?').next().unwrap();
+    let url_slug = path.replace("/api/articles/", "").replace("/revisions/diff", "");
+    println!("revision_diff_handler url: '{}'", url);
+
+    let article = match find_article(&url_slug) {
+        Some(article) => article,
+        None => {
+            send_error(res, StatusCode::NotFound, "article not found");
+            return;
+        }
+    };
+    if let Err(denied) = check(logged_id, Action::ViewArticleRevisions, Some(article.author)) {
+        send_denied(res, denied);
+        return;
+    }
+    let (from, to) = match (get_query_param(url, "from"), get_query_param(url, "to")) {
+        (Some(from), Some(to)) => (from, to),
+        _ => {
+            send_error(res, StatusCode::UnprocessableEntity, "from and to revisions are required");
+            return;
+        }
+    };
+    match (find_revision(article.id, from), find_revision(article.id, to)) {
+        (Some(from), Some(to)) => process(res, diff_revisions, (from, to)),
+        _ => send_error(res, StatusCode::NotFound, "revision not found"),
+    }
+}
+
+#[cfg(feature = "diesel")]
+pub fn restore_revision_handler(req: Request, mut res: Response, c: Captures) {
+    let (_, logged_id) = prepare_parameters(req);
+
+    let caps = c.unwrap();
+    let path = caps[0].replace("/api/articles/", "").replace("/restore", "");
+    let mut parts = path.splitn(2, "/revisions/");
+    let url_slug = parts.next().unwrap();
+    let revision_id = parts.next().unwrap_or("");
+    println!("restore_revision_handler slug: '{}' revision: '{}'", url_slug, revision_id);
+
+    let article = match find_article(url_slug) {
+        Some(article) => article,
+        None => {
+            send_error(res, StatusCode::NotFound, "article not found");
+            return;
+        }
+    };
+    if let Err(denied) = check(logged_id, Action::UpdateArticle, Some(article.author)) {
+        send_denied(res, denied);
+        return;
+    }
+    let revision = match find_revision(article.id, revision_id) {
+        Some(revision) => revision,
+        None => {
+            send_error(res, StatusCode::NotFound, "revision not found");
+            return;
+        }
+    };
+
+    // an old text may hold words banned since, it goes through the filters like an edit
+    let mut held_because = None;
+    let text = article_text(&revision.title, &revision.description, &revision.body);
+    if text != article_text(&article.title, &article.description, &article.body) {
+        let submission = Submission { author: article.author, kind: ContentKind::Article, text: &text, edited: Some(article.id) };
+        match run_filters(&content_filters(), &submission) {
+            Verdict::Allow => {}
+            Verdict::Hold(reason) => held_because = Some(reason),
+            Verdict::Reject(reason) => {
+                send_error(res, StatusCode::UnprocessableEntity, &reason);
+                return;
+            }
+        }
+    }
+    if held_because.is_some() {
+        *res.status_mut() = StatusCode::Accepted;
+    }
+    process(res, restore_revision, (article, revision, logged_id, held_because))
+}
+
+#[cfg(test)]
+#[test]
+fn diff_lines_test() {
+    let old = "Dragons fly.\nDragons sleep.\nDragons eat.";
+    let new = "Dragons fly.\nDragons dream.\nDragons eat.\nThe end.";
+
+    assert_eq!(
+        diff_lines(old, new),
+        vec![
+            diff_line(DiffOp::Equal, "Dragons fly."),
+            diff_line(DiffOp::Delete, "Dragons sleep."),
+            diff_line(DiffOp::Insert, "Dragons dream."),
+            diff_line(DiffOp::Equal, "Dragons eat."),
+            diff_line(DiffOp::Insert, "The end."),
+        ]
+    );
+    assert!(diff_lines(old, old).iter().all(|line| line.op == DiffOp::Equal));
+    assert_eq!(diff_lines("", "One line."), vec![diff_line(DiffOp::Insert, "One line.")]);
+}
+
+#[cfg(test)]
+#[test]
+fn article_revisions_test() {
+    let client = Client::new();
+
+    let (jwt, slug, _) = login_create_article(false);
+    let url = format!("http://localhost:6767/api/articles/{}", slug);
+
+    let res = client
+        .put(&url)
+        .header(Authorization(Bearer { token: jwt.to_owned() }))
+        .body(r#"{"article": {"body": "You have to believe\nand to practice"}}"#)
+        .send()
+        .unwrap();
+    assert_eq!(res.status, hyper::Ok);
+
+    let mut res = client
+        .get(&format!("{}/revisions", url))
+        .header(Authorization(Bearer { token: jwt.to_owned() }))
+        .send()
+        .unwrap();
+    let mut buffer = String::new();
+    res.read_to_string(&mut buffer).unwrap();
+    assert_eq!(res.status, hyper::Ok);
+    let history: ArticleRevisionsResult = serde_json::from_str(&buffer).unwrap();
+    assert_eq!(history.revisions.len(), 2);
+    let (first, second) = (history.revisions[0].id, history.revisions[1].id);
+
+    let res = client
+        .get(&format!("{}/revisions", url))
+        .header(Authorization(Bearer { token: login_other_jacob() }))
+        .send()
+        .unwrap();
+    assert_eq!(res.status, StatusCode::Forbidden);
+
+    let mut res = client
+        .get(&format!("{}/revisions/diff?from={}&to={}", url, first, second))
+        .header(Authorization(Bearer { token: jwt.to_owned() }))
+        .send()
+        .unwrap();
+    let mut buffer = String::new();
+    res.read_to_string(&mut buffer).unwrap();
+    let diff = serde_json::from_str::<RevisionDiffResult>(&buffer).unwrap().diff;
+    assert_eq!(diff.body, vec![diff_line(DiffOp::Equal, "You have to believe"), diff_line(DiffOp::Insert, "and to practice")]);
+    assert!(diff.title.iter().all(|line| line.op == DiffOp::Equal));
+
+    let mut res = client
+        .post(&format!("{}/revisions/{}/restore", url, first))
+        .header(Authorization(Bearer { token: jwt.to_owned() }))
+        .body("")
+        .send()
+        .unwrap();
+    let mut buffer = String::new();
+    res.read_to_string(&mut buffer).unwrap();
+    assert_eq!(res.status, hyper::Ok);
+    let restored = serde_json::from_str::<ArticleResult>(&buffer).unwrap().article;
+    assert_eq!(restored.body, "You have to believe");
+
+    let mut res = client
+        .get(&format!("{}/revisions", url))
+        .header(Authorization(Bearer { token: jwt }))
+        .send()
+        .unwrap();
+    let mut buffer = String::new();
+    res.read_to_string(&mut buffer).unwrap();
+    let history: ArticleRevisionsResult = serde_json::from_str(&buffer).unwrap();
+    assert_eq!(history.revisions.len(), 3);
+}
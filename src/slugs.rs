@@ -0,0 +1,150 @@
+extern crate hyper;
+
+extern crate chrono;
+
+extern crate slug;
+
+use slug::slugify;
+
+use super::*;
+
+/// `base` if nobody has it yet, otherwise `base-2`, `base-3` and so on.
+pub fn next_free_slug(base: &str, taken: &[String]) -> String {
+    // a title without letters or digits leaves nothing to build on
+    let base = if base.is_empty() { "article" } else { base };
+    if !taken.iter().any(|slug| slug == base) {
+        return base.to_string();
+    }
+    (2..)
+        .map(|n| format!("{}-{}", base, n))
+        .find(|candidate| !taken.iter().any(|slug| slug == candidate))
+        .unwrap()
+}
+
+/// A slug for `title` no other article has, now or as an alias; `article_id` may keep its own old slugs.
+#[cfg(feature = "diesel")]
+pub fn unique_slug(title: &str, article_id: Option<i32>) -> String {
+    use schema::{articles, slugaliases};
+
+    let connection = establish_connection();
+    let base = slugify(title);
+    let pattern = format!("{}-%", base);
+    let own_id = article_id.unwrap_or(-1);
+
+    let mut taken: Vec<String> = articles::table
+        .filter(articles::slug.eq(&base).or(articles::slug.like(&pattern)))
+        .filter(articles::id.ne(own_id))
+        .select(articles::slug)
+        .load(&connection)
+        .expect("Error loading article slugs");
+    let aliases: Vec<String> = slugaliases::table
+        .filter(slugaliases::slug.eq(&base).or(slugaliases::slug.like(&pattern)))
+        .filter(slugaliases::articleid.ne(own_id))
+        .select(slugaliases::slug)
+        .load(&connection)
+        .expect("Error loading slug aliases");
+    taken.extend(aliases);
+
+    next_free_slug(&base, &taken)
+}
+
+/// Keeps `old_slug` leading to the renamed article; renaming back to an old slug takes it out of the aliases.
+#[cfg(feature = "diesel")]
+pub fn record_slug_change(article_id: i32, old_slug: &str, new_slug: &str, connection: &PgConnection) -> QueryResult<()> {
+    use schema::slugaliases;
+
+    diesel::delete(
+        slugaliases::table.filter(slugaliases::articleid.eq(article_id).and(slugaliases::slug.eq(new_slug))),
+    ).execute(connection)?;
+
+    let alias = NewSlugAlias {
+        articleid: article_id,
+        slug: old_slug,
+        createdat: Utc::now().naive_utc(),
+    };
+    diesel::insert(&alias)
+        .into(slugaliases::table)
+        .execute(connection)?;
+    Ok(())
+}
+
+/// The article an old slug used to name.
+#[cfg(feature = "diesel")]
+pub fn find_aliased_article_id(old_slug: &str) -> Option<i32> {
+    use schema::slugaliases::dsl::*;
+
+    let connection = establish_connection();
+    slugaliases
+        .filter(slug.eq(old_slug))
+        .select(articleid)
+        .first(&connection)
+        .optional()
+        .expect("Error loading slug alias")
+}
+
+#[cfg(test)]
+#[test]
+fn next_free_slug_test() {
+    let taken = vec!["dragons".to_string(), "dragons-2".to_string(), "dragons-4".to_string()];
+
+    assert_eq!(next_free_slug("wyverns", &taken), "wyverns");
+    assert_eq!(next_free_slug("dragons", &taken), "dragons-3");
+    assert_eq!(next_free_slug("dragons-2", &taken), "dragons-2-2");
+    assert_eq!(next_free_slug("", &[]), "article");
+}
+
+#[cfg(test)]
+#[test]
+fn slug_collision_and_rename_test() {
+    use hyper::client::RedirectPolicy;
+
+    let mut client = Client::new();
+    client.set_redirect_policy(RedirectPolicy::FollowNone);
+    let (_, email) = register_jacob();
+    let jwt = login_jacob(email, JACOB_PASSWORD.to_string());
+
+    let title = format!("Twin dragons {}", since_the_epoch());
+    let mut slugs = Vec::new();
+    for _ in 0..2 {
+        let mut res = client
+            .post("http://localhost:6767/api/articles")
+            .header(Authorization(Bearer { token: jwt.to_owned() }))
+            .body(&format!(r#"{{"article": {{"title": "{}", "description": "Two", "body": "Of a kind."}}}}"#, title))
+            .send()
+            .unwrap();
+        let mut buffer = String::new();
+        res.read_to_string(&mut buffer).unwrap();
+        assert_eq!(res.status, hyper::Ok);
+        slugs.push(serde_json::from_str::<ArticleResult>(&buffer).unwrap().article.slug);
+    }
+    assert_eq!(slugs[1], format!("{}-2", slugs[0]));
+
+    let mut res = client
+        .put(&format!("http://localhost:6767/api/articles/{}", slugs[0]))
+        .header(Authorization(Bearer { token: jwt.to_owned() }))
+        .body(&format!(r#"{{"article": {{"title": "Renamed {}"}}}}"#, title))
+        .send()
+        .unwrap();
+    let mut buffer = String::new();
+    res.read_to_string(&mut buffer).unwrap();
+    let renamed = serde_json::from_str::<ArticleResult>(&buffer).unwrap().article.slug;
+    assert!(renamed != slugs[0]);
+
+    let res = client
+        .get(&format!("http://localhost:6767/api/articles/{}", slugs[0]))
+        .send()
+        .unwrap();
+    assert_eq!(res.status, StatusCode::MovedPermanently);
+    assert_eq!(
+        res.headers.get::<Location>().map(|location| location.to_string()),
+        Some(format!("/api/articles/{}", renamed))
+    );
+
+    // everything else under the old slug keeps working as it is
+    let res = client
+        .get(&format!("http://localhost:6767/api/articles/{}/comments", slugs[0]))
+        .header(Authorization(Bearer { token: jwt }))
+        .send()
+        .unwrap();
+    assert_eq!(res.status, hyper::Ok);
+}
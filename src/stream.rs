@@ -0,0 +1,218 @@
+extern crate hyper;
+
+extern crate serde;
+extern crate serde_json;
+
+extern crate reroute;
+
+use hyper::status::StatusCode;
+
+use hyper::server::{Request, Response};
+use reroute::Captures;
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Condvar, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use super::*;
+
+/// Who an event is meant for; decided when it is delivered, so follows made after publishing still count.
+#[derive(Debug, Clone)]
+pub enum StreamTopic {
+    Comment { article_slug: String, article_id: i32, article_author: i32, commenter_id: i32 },
+    Article { author_id: i32 },
+    Notification { user_id: i32 },
+}
+
+#[derive(Debug)]
+pub struct StreamEvent {
+    pub id: u64,
+    pub name: &'static str,
+    pub topic: StreamTopic,
+    pub tenant: i32,
+    pub data: String,
+}
+
+struct EventBus {
+    last_id: u64,
+    recent: VecDeque<Arc<StreamEvent>>,
+}
+
+lazy_static! {
+    static ref EVENT_BUS: (Mutex<EventBus>, Condvar) = (
+        Mutex::new(EventBus { last_id: 0, recent: VecDeque::new() }),
+        Condvar::new(),
+    );
+    static ref OPEN_STREAMS: AtomicUsize = AtomicUsize::new(0);
+}
+
+/// Queues an event for every open stream; the newest `STREAM_REPLAY_EVENTS` are kept for `Last-Event-ID` replay.
+pub fn publish<T>(name: &'static str, topic: StreamTopic, payload: &T)
+where
+    T: serde::Serialize,
+{
+    let data = serde_json::to_string(payload).unwrap();
+
+    let &(ref bus, ref published) = &*EVENT_BUS;
+    let mut bus = bus.lock().unwrap();
+    bus.last_id += 1;
+    let event = StreamEvent {
+        id: bus.last_id,
+        name: name,
+        topic: topic,
+        tenant: current_tenant(),
+        data: data,
+    };
+    bus.recent.push_back(Arc::new(event));
+    while bus.recent.len() > *STREAM_REPLAY_EVENTS {
+        bus.recent.pop_front();
+    }
+    published.notify_all();
+}
+
+fn last_event_id() -> u64 {
+    EVENT_BUS.0.lock().unwrap().last_id
+}
+
+/// Events published after `after_id`, waiting up to `timeout` for the first one.
+fn wait_for_events(after_id: u64, timeout: Duration) -> Vec<Arc<StreamEvent>> {
+    let &(ref bus, ref published) = &*EVENT_BUS;
+    let mut bus = bus.lock().unwrap();
+    if bus.last_id <= after_id {
+        bus = published.wait_timeout(bus, timeout).unwrap().0;
+    }
+    bus.recent.iter().filter(|e| e.id > after_id).cloned().collect()
+}
+
+/// Whether `user_id` may read `article_id` right now, by the rules of `find_visible_article`.
+#[cfg(feature = "diesel")]
+fn can_read_comments(article_id: i32, user_id: i32) -> bool {
+    use schema::articles;
+
+    let connection = establish_connection();
+    let article: Option<Article> = articles::table
+        .find(article_id)
+        .filter(articles::deletedat.is_null())
+        .first(&connection)
+        .optional()
+        .expect("Error loading commented article");
+    match article {
+        Some(ref article) => is_article_visible(article, user_id),
+        None => false,
+    }
+}
+
+#[cfg(feature = "diesel")]
+fn is_wanted(event: &StreamEvent, user_id: i32, article_slugs: &[String]) -> bool {
+    // streams only carry what happened on their own tenant
+    if event.tenant != current_tenant() {
+        return false;
+    }
+    match event.topic {
+        StreamTopic::Comment { ref article_slug, article_id, article_author, commenter_id } => {
+            article_slugs.contains(article_slug) && !is_blocked(article_author, user_id) &&
+                !muted_authors(user_id).contains(&commenter_id) && can_read_comments(article_id, user_id)
+        }
+        StreamTopic::Article { author_id } => {
+            author_id != user_id && is_followed(user_id, author_id) && !hidden_authors(user_id).contains(&author_id)
+        }
+        StreamTopic::Notification { user_id: recipient_id } => recipient_id == user_id,
+    }
+}
+
+pub fn format_event(event: &StreamEvent) -> String {
+    // a data line cannot hold a line break, serde_json output never has one
+    format!("id: {}\nevent: {}\ndata: {}\n\n", event.id, event.name, event.data)
+}
+
+pub fn stream_handler(req: Request, mut res: Response, c: Captures) {
+    let replay_from: Option<u64> = req.headers
+        .get_raw("Last-Event-ID")
+        .and_then(|values| values.last())
+        .and_then(|value| String::from_utf8(value.clone()).ok())
+        .and_then(|value| value.trim().parse().ok());
+    let (_, logged_id) = prepare_parameters(req);
+
+    let caps = c.unwrap();
+    let article_slugs: Vec<String> = get_query_param(&caps[0], "articles")
+        .map(|slugs| {
+            slugs
+                .split(',')
+                .filter(|slug| !slug.is_empty())
+                .map(|slug| slug.to_string())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    #[cfg(feature = "diesel")]
+    {
+        if logged_id <= 0 {
+            send_denied(res, Denied::Unauthenticated);
+            return;
+        }
+        if OPEN_STREAMS.fetch_add(1, Ordering::SeqCst) >= *STREAM_MAX_CLIENTS {
+            OPEN_STREAMS.fetch_sub(1, Ordering::SeqCst);
+            send_error(res, StatusCode::ServiceUnavailable, "too many open streams, try again later");
+            return;
+        }
+
+        res.headers_mut().set(AccessControlAllowOrigin::Any);
+        res.headers_mut().set(ContentType(Mime(
+            TopLevel::Text,
+            SubLevel::EventStream,
+            vec![(Attr::Charset, Value::Utf8)],
+        )));
+        res.headers_mut().set_raw("Cache-Control", vec![b"no-cache".to_vec()]);
+
+        // ids start over when the server restarts, a client may come back with one from before
+        let mut cursor = match replay_from {
+            Some(replay_from) => std::cmp::min(replay_from, last_event_id()),
+            None => last_event_id(),
+        };
+        if let Ok(mut stream) = res.start() {
+            let keep_alive = Duration::from_secs(*STREAM_KEEP_ALIVE_SECONDS);
+            let mut open = stream.write_all(b"retry: 3000\n\n").and_then(|_| stream.flush()).is_ok();
+
+            // a failed write means the client went away
+            while open {
+                let events = wait_for_events(cursor, keep_alive);
+                let mut chunk = String::new();
+                for event in &events {
+                    cursor = event.id;
+                    if is_wanted(event, logged_id, &article_slugs) {
+                        chunk.push_str(&format_event(event));
+                    }
+                }
+                if chunk.is_empty() {
+                    chunk.push_str(": keep-alive\n\n");
+                }
+                open = stream.write_all(chunk.as_bytes()).and_then(|_| stream.flush()).is_ok();
+            }
+            let _ = stream.end();
+        }
+
+        OPEN_STREAMS.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn replay_test() {
+    #[derive(Serialize)]
+    struct Ping {
+        ping: i32,
+    }
+
+    let before = last_event_id();
+    publish("ping", StreamTopic::Notification { user_id: -1 }, &Ping { ping: 1 });
+    publish("ping", StreamTopic::Notification { user_id: -1 }, &Ping { ping: 2 });
+
+    let events: Vec<Arc<StreamEvent>> = wait_for_events(before, Duration::from_millis(10))
+        .into_iter()
+        .filter(|e| e.name == "ping")
+        .collect();
+    assert_eq!(events.len(), 2);
+    assert!(events[0].id < events[1].id);
+    assert_eq!(format_event(&events[1]), format!("id: {}\nevent: ping\ndata: {{\"ping\":2}}\n\n", events[1].id));
+}
@@ -0,0 +1,263 @@
+extern crate hyper;
+
+extern crate serde;
+extern crate serde_json;
+
+extern crate chrono;
+
+extern crate reroute;
+
+use hyper::status::StatusCode;
+
+use hyper::server::{Request, Response};
+use reroute::Captures;
+
+use super::*;
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+pub struct TagFollow {
+    pub name: String,
+    pub following: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+pub struct TagFollowResult {
+    pub tag: TagFollow,
+}
+
+/// An article of the combined feed, the fields of `Article` and `reason`: `author` for followed authors or `tag:<name>` for followed tags.
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+#[allow(non_snake_case)]
+pub struct FeedArticle {
+    pub id: i32,
+    pub slug: String,
+    pub title: String,
+    pub description: String,
+    pub body: String,
+    pub createdAt: NaiveDateTime,
+    pub updatedAt: Option<NaiveDateTime>,
+    pub author: i32,
+    pub hidden: bool,
+    pub status: String,
+    pub publishedAt: Option<NaiveDateTime>,
+    pub publishAt: Option<NaiveDateTime>,
+    pub deletedAt: Option<NaiveDateTime>,
+    pub deletedBy: Option<i32>,
+    pub reason: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+pub struct FeedArticlesResult {
+    pub articles: Vec<FeedArticle>,
+}
+
+impl Container<FeedArticle> for FeedArticlesResult {
+    fn create_new_with_items(articles: Vec<FeedArticle>) -> FeedArticlesResult {
+        FeedArticlesResult { articles: articles }
+    }
+}
+
+#[cfg(feature = "diesel")]
+fn find_tag(tag_name: &str) -> Option<Tag> {
+    use schema::tags::dsl::*;
+
+    let connection = establish_connection();
+    tags.filter(tag.eq(tag_name))
+        .first(&connection)
+        .optional()
+        .expect("Error loading tag")
+}
+
+#[cfg(feature = "diesel")]
+fn follow_tag(params: (Tag, i32)) -> Option<TagFollowResult> {
+    use schema::tagfollowings;
+    use diesel::result::{DatabaseErrorKind, Error};
+
+    let (followed_tag, follower_id) = params;
+    let connection = establish_connection();
+
+    let follow = NewTagFollowing {
+        tagid: followed_tag.id,
+        followerid: follower_id,
+    };
+    match diesel::insert(&follow).into(tagfollowings::table).execute(&connection) {
+        Ok(_) | Err(Error::DatabaseError(DatabaseErrorKind::UniqueViolation, _)) => {}
+        Err(e) => panic!("Error saving tag following: {}", e),
+    }
+
+    Some(TagFollowResult {
+        tag: TagFollow {
+            name: followed_tag.tag,
+            following: true,
+        },
+    })
+}
+
+#[cfg(feature = "diesel")]
+fn unfollow_tag(params: (Tag, i32)) -> Option<TagFollowResult> {
+    use schema::tagfollowings::dsl::*;
+
+    let (followed_tag, follower_id) = params;
+    let connection = establish_connection();
+
+    diesel::delete(tagfollowings.filter(tagid.eq(followed_tag.id).and(followerid.eq(follower_id))))
+        .execute(&connection)
+        .expect("Error removing tag following");
+
+    Some(TagFollowResult {
+        tag: TagFollow {
+            name: followed_tag.tag,
+            following: false,
+        },
+    })
+}
+
+#[cfg(feature = "diesel")]
+fn tag_follow_handler(req: Request, res: Response, c: Captures, change: fn((Tag, i32)) -> Option<TagFollowResult>) {
+    let (_, logged_id) = prepare_parameters(req);
+
+    let caps = c.unwrap();
+    let tag_name = &caps[0].replace("/api/tags/", "").replace("/follow", "");
+    println!("tag_follow_handler tag: '{}'", tag_name);
+
+    if logged_id <= 0 {
+        send_denied(res, Denied::Unauthenticated);
+        return;
+    }
+    match find_tag(tag_name) {
+        Some(followed_tag) => process(res, change, (followed_tag, logged_id)),
+        None => send_error(res, StatusCode::NotFound, "tag not found"),
+    }
+}
+
+#[cfg(feature = "diesel")]
+pub fn follow_tag_handler(req: Request, res: Response, c: Captures) {
+    tag_follow_handler(req, res, c, follow_tag)
+}
+
+#[cfg(feature = "diesel")]
+pub fn unfollow_tag_handler(req: Request, res: Response, c: Captures) {
+    tag_follow_handler(req, res, c, unfollow_tag)
+}
+
+pub fn feed_articles_result(_: FeedArticlesResult) {}
+
+/// Articles of followed authors and followed tags, each once, latest published first.
+#[cfg(feature = "diesel")]
+pub fn get_combined_feed(params: FilterParams) -> Vec<FeedArticle> {
+    use schema::{articles, articletags, followings, tagfollowings, tags};
+    use diesel::expression::dsl::{all, any};
+
+    let connection = establish_connection();
+
+    let author_ids: Vec<i32> = followings::table
+        .filter(followings::followerid.eq(params.viewer))
+        .select(followings::followingid)
+        .load(&connection)
+        .expect("Error loading followed authors");
+    let followed_tags: Vec<(i32, String)> = tagfollowings::table
+        .inner_join(tags::table)
+        .filter(tagfollowings::followerid.eq(params.viewer))
+        .select((tags::id, tags::tag))
+        .load(&connection)
+        .expect("Error loading followed tags");
+    let tag_ids: Vec<i32> = followed_tags.iter().map(|&(id, _)| id).collect();
+
+    let tagged: Vec<(i32, i32)> = articletags::table
+        .filter(articletags::tagid.eq(any(&tag_ids)))
+        .select((articletags::articleid, articletags::tagid))
+        .load(&connection)
+        .expect("Error loading articles with followed tags");
+    let tagged_ids: Vec<i32> = tagged.iter().map(|&(id, _)| id).collect();
+    let hidden_ids: Vec<i32> = hidden_authors(params.viewer).into_iter().collect();
+
+    // the same rules as is_listed, in the query so that a page is a full page
+    let page: Vec<Article> = articles::table
+        .filter(articles::author.eq(any(&author_ids)).or(
+            articles::id.eq(any(&tagged_ids)),
+        ))
+        .filter(articles::tenantid.eq(current_tenant()))
+        .filter(articles::status.eq(ArticleStatus::Published.name()))
+        .filter(articles::deletedat.is_null())
+        .filter(articles::hidden.eq(false))
+        .filter(articles::author.ne(all(&hidden_ids)))
+        .order(articles::publishedat.desc())
+        .limit(std::cmp::max(params.limit, 0) as i64)
+        .offset(std::cmp::max(params.offset, 0) as i64)
+        .load(&connection)
+        .expect("Error loading combined feed");
+
+    page.into_iter()
+        .map(|a| {
+            // a followed author explains an article better than one of its tags
+            let reason = if author_ids.contains(&a.author) {
+                "author".to_string()
+            } else {
+                tagged
+                    .iter()
+                    .filter(|&&(article_id, _)| article_id == a.id)
+                    .filter_map(|&(_, tag_id)| followed_tags.iter().find(|&&(id, _)| id == tag_id))
+                    .map(|&(_, ref name)| format!("tag:{}", name))
+                    .next()
+                    .unwrap_or_default()
+            };
+            FeedArticle {
+                id: a.id,
+                slug: a.slug,
+                title: a.title,
+                description: a.description,
+                body: a.body,
+                createdAt: a.createdAt,
+                updatedAt: a.updatedAt,
+                author: a.author,
+                hidden: a.hidden,
+                status: a.status,
+                publishedAt: a.publishedAt,
+                publishAt: a.publishAt,
+                deletedAt: a.deletedAt,
+                deletedBy: a.deletedBy,
+                reason: reason,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+#[test]
+fn follow_tag_feed_test() {
+    let client = Client::new();
+    let (_, slug, _) = login_create_article(false);
+    let (_, email) = register_jacob();
+    let jwt = login_jacob(email, JACOB_PASSWORD.to_string());
+
+    let mut res = client
+        .post("http://localhost:6767/api/tags/dragons/follow")
+        .header(Authorization(Bearer { token: jwt.to_owned() }))
+        .body("")
+        .send()
+        .unwrap();
+    let mut buffer = String::new();
+    res.read_to_string(&mut buffer).unwrap();
+    assert_eq!(res.status, hyper::Ok);
+    let followed: TagFollowResult = serde_json::from_str(&buffer).unwrap();
+    assert_eq!(followed.tag.following, true);
+
+    let mut res = client
+        .get("http://localhost:6767/api/articles/feed?mode=combined&limit=100")
+        .header(Authorization(Bearer { token: jwt }))
+        .send()
+        .unwrap();
+    let mut buffer = String::new();
+    res.read_to_string(&mut buffer).unwrap();
+    assert_eq!(res.status, hyper::Ok);
+
+    let feed: FeedArticlesResult = serde_json::from_str(&buffer).unwrap();
+    let article = feed.articles.iter().find(|a| a.slug == slug).unwrap();
+    assert_eq!(article.reason, "tag:dragons");
+    assert_eq!(article.status, "published");
+    assert!(article.publishedAt.is_some());
+}
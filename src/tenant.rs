@@ -0,0 +1,241 @@
+extern crate hyper;
+
+extern crate chrono;
+
+use hyper::server::{Handler, Request, Response};
+use hyper::header::Host;
+use hyper::uri::RequestUri;
+
+use std::cell::Cell;
+
+use super::*;
+
+/// Serves every host and path no `[[tenants]]` section claims, and all data from before tenancy.
+pub const DEFAULT_TENANT_ID: i32 = 1;
+
+// a request is handled from start to end on one server thread
+thread_local!(static CURRENT_TENANT: Cell<i32> = Cell::new(DEFAULT_TENANT_ID));
+
+pub fn current_tenant() -> i32 {
+    CURRENT_TENANT.with(|tenant| tenant.get())
+}
+
+/// Used by the request threads, and by the job runner for the tenant of the job at hand.
+pub fn set_current_tenant(tenant_id: i32) {
+    CURRENT_TENANT.with(|tenant| tenant.set(tenant_id));
+}
+
+fn current_tenant_config() -> Option<&'static TenantConfig> {
+    let tenant_id = current_tenant();
+    TENANTS.iter().find(|tenant| tenant.id == tenant_id)
+}
+
+pub fn registration_open() -> bool {
+    current_tenant_config().and_then(|tenant| tenant.open_registration).unwrap_or(true)
+}
+
+pub fn comment_max_depth() -> i32 {
+    current_tenant_config().and_then(|tenant| tenant.comment_max_depth).unwrap_or(*COMMENT_MAX_DEPTH)
+}
+
+/// The tenant serving a request for `path` on `host`, and `path` without the tenant's prefix.
+pub fn resolve_tenant(tenants: &[TenantConfig], host: Option<&str>, path: &str) -> (i32, String) {
+    for tenant in tenants {
+        let prefix = match tenant.path_prefix {
+            Some(ref prefix) => prefix.trim_right_matches('/'),
+            None => continue,
+        };
+        if prefix.is_empty() || !path.starts_with(prefix) {
+            continue;
+        }
+        // "/eng" is no prefix of "/engineering/api/articles"
+        let rest = &path[prefix.len()..];
+        if rest.is_empty() || rest.starts_with('?') {
+            return (tenant.id, format!("/{}", rest));
+        }
+        if rest.starts_with('/') {
+            return (tenant.id, rest.to_string());
+        }
+    }
+
+    if let Some(host) = host {
+        let tenant = tenants.iter().find(|tenant| {
+            tenant.hosts.as_ref().map_or(false, |hosts| hosts.iter().any(|h| h.eq_ignore_ascii_case(host)))
+        });
+        if let Some(tenant) = tenant {
+            return (tenant.id, path.to_string());
+        }
+    }
+    (DEFAULT_TENANT_ID, path.to_string())
+}
+
+/// Puts every request in the hands of its tenant before the routes see it.
+pub struct TenantRouter<H: Handler> {
+    pub router: H,
+}
+
+impl<H: Handler> Handler for TenantRouter<H> {
+    fn handle<'a, 'k>(&'a self, mut req: Request<'a, 'k>, res: Response<'a>) {
+        let host = req.headers.get::<Host>().map(|host| host.hostname.to_owned());
+        let resolved = match req.uri {
+            RequestUri::AbsolutePath(ref path) => Some(resolve_tenant(&TENANTS, host.as_ref().map(|h| &**h), path)),
+            _ => None,
+        };
+
+        let tenant_id = match resolved {
+            Some((tenant_id, path)) => {
+                req.uri = RequestUri::AbsolutePath(path);
+                tenant_id
+            }
+            None => DEFAULT_TENANT_ID,
+        };
+        set_current_tenant(tenant_id);
+        self.router.handle(req, res)
+    }
+}
+
+/// Adds the configured tenants the database does not know yet, the default one comes with the migration.
+#[cfg(feature = "diesel")]
+pub fn register_tenants() {
+    use schema::tenants;
+
+    let connection = establish_connection();
+    let known: Vec<i32> = tenants::table
+        .select(tenants::id)
+        .load(&connection)
+        .expect("Error loading tenants");
+
+    for tenant in TENANTS.iter().filter(|tenant| !known.contains(&tenant.id)) {
+        let new_tenant = NewTenant {
+            id: tenant.id,
+            name: &tenant.name,
+            createdat: Utc::now().naive_utc(),
+        };
+        diesel::insert(&new_tenant)
+            .into(tenants::table)
+            .execute(&connection)
+            .expect("Error saving new tenant");
+    }
+}
+
+/// Whether `user_id` signed up to the blog the request is for; a token never opens another tenant.
+#[cfg(feature = "diesel")]
+pub fn is_tenant_member(user_id: i32) -> bool {
+    use schema::users::dsl::*;
+
+    let connection = establish_connection();
+    let members: i64 = users
+        .filter(id.eq(user_id).and(tenantid.eq(current_tenant())))
+        .count()
+        .get_result(&connection)
+        .expect("Error loading user tenant");
+    members > 0
+}
+
+#[cfg(test)]
+fn tenant_config(id: i32, hosts: Vec<&str>, path_prefix: Option<&str>) -> TenantConfig {
+    TenantConfig {
+        id: id,
+        name: format!("tenant-{}", id),
+        hosts: Some(hosts.into_iter().map(|host| host.to_string()).collect()),
+        path_prefix: path_prefix.map(|prefix| prefix.to_string()),
+        open_registration: None,
+        comment_max_depth: None,
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn resolve_tenant_test() {
+    let tenants = vec![
+        tenant_config(2, vec!["engineering.example.com"], Some("/engineering/")),
+        tenant_config(3, vec!["design.example.com"], None),
+    ];
+
+    assert_eq!(resolve_tenant(&tenants, None, "/engineering/api/articles?tag=rust"), (2, "/api/articles?tag=rust".to_string()));
+    assert_eq!(resolve_tenant(&tenants, None, "/engineering?x=1"), (2, "/?x=1".to_string()));
+    assert_eq!(resolve_tenant(&tenants, Some("Design.Example.com"), "/api/tags"), (3, "/api/tags".to_string()));
+    // the prefix wins over the host
+    assert_eq!(resolve_tenant(&tenants, Some("design.example.com"), "/engineering/api/tags"), (2, "/api/tags".to_string()));
+    assert_eq!(resolve_tenant(&tenants, Some("localhost"), "/engineeringx/api/tags"), (DEFAULT_TENANT_ID, "/engineeringx/api/tags".to_string()));
+    assert_eq!(resolve_tenant(&[], None, "/api/tags"), (DEFAULT_TENANT_ID, "/api/tags".to_string()));
+}
+
+// the isolation tests run against a second tenant reached through its path prefix
+#[cfg(test)]
+fn other_tenant_url(path: &str) -> String {
+    let prefix = TENANTS
+        .iter()
+        .filter(|tenant| tenant.id != DEFAULT_TENANT_ID)
+        .filter_map(|tenant| tenant.path_prefix.clone())
+        .next()
+        .expect("the tenant isolation tests need a second tenant with a path_prefix in conduit.toml");
+    format!("http://localhost:6767{}{}", prefix.trim_right_matches('/'), path)
+}
+
+#[cfg(test)]
+#[test]
+fn tenant_article_isolation_test() {
+    let client = Client::new();
+
+    let (_, slug, user_name) = login_create_article(false);
+
+    let res = client.get(&other_tenant_url(&format!("/api/articles/{}", slug))).send().unwrap();
+    assert_eq!(res.status, StatusCode::NotFound);
+
+    for query in &[format!("author={}", user_name), "tag=dragons".to_string()] {
+        let mut res = client
+            .get(&other_tenant_url(&format!("/api/articles?{}", query)))
+            .send()
+            .unwrap();
+        let mut buffer = String::new();
+        res.read_to_string(&mut buffer).unwrap();
+        assert_eq!(res.status, hyper::Ok);
+        let listed: ArticlesResult = serde_json::from_str(&buffer).unwrap();
+        assert!(listed.articles.iter().all(|article| article.slug != slug));
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn tenant_user_isolation_test() {
+    let client = Client::new();
+
+    let (user_name, email) = register_jacob();
+    let jwt = login_jacob(email.to_owned(), JACOB_PASSWORD.to_string());
+    let credentials = format!(r#"{{"user":{{"email": "{}","password": "{}"}}}}"#, email, JACOB_PASSWORD);
+
+    let res = client.get(&other_tenant_url(&format!("/api/profiles/{}", user_name))).send().unwrap();
+    assert_eq!(res.status, StatusCode::NotFound);
+
+    let res = client.post(&other_tenant_url("/api/users/login")).body(&credentials).send().unwrap();
+    assert_eq!(res.status, StatusCode::Unauthorized);
+
+    let res = client
+        .get(&other_tenant_url("/api/user/trash"))
+        .header(Authorization(Bearer { token: jwt }))
+        .send()
+        .unwrap();
+    assert_eq!(res.status, StatusCode::Unauthorized);
+
+    // the same person signs up to the other blog as a separate account
+    let registration = format!(
+        r#"{{"user":{{"username": "{}","email": "{}","password": "{}"}}}}"#,
+        user_name,
+        email,
+        JACOB_PASSWORD
+    );
+    let res = client.post(&other_tenant_url("/api/users")).body(&registration).send().unwrap();
+    assert_eq!(res.status, hyper::Ok);
+
+    let res = client.post(&other_tenant_url("/api/users/login")).body(&credentials).send().unwrap();
+    assert_eq!(res.status, hyper::Ok);
+    let other_jwt = res.headers.get::<Authorization<Bearer>>().unwrap().0.token.to_owned();
+
+    let res = client
+        .get("http://localhost:6767/api/user/trash")
+        .header(Authorization(Bearer { token: other_jwt }))
+        .send()
+        .unwrap();
+    assert_eq!(res.status, StatusCode::Unauthorized);
+}
@@ -0,0 +1,315 @@
+extern crate hyper;
+
+extern crate serde;
+extern crate serde_json;
+
+extern crate chrono;
+
+extern crate reroute;
+
+use hyper::status::StatusCode;
+
+use hyper::server::{Request, Response};
+use reroute::Captures;
+
+use chrono::Duration;
+
+use super::*;
+
+static ACCOUNT_SCOPE: &'static str = "account";
+static IP_SCOPE: &'static str = "ip";
+
+const MAX_LOCKOUTS_LIMIT: i64 = 100;
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+pub struct LockoutsResult {
+    pub lockouts: Vec<LockoutEvent>,
+}
+
+impl Container<LockoutEvent> for LockoutsResult {
+    fn create_new_with_items(lockouts: Vec<LockoutEvent>) -> LockoutsResult {
+        LockoutsResult { lockouts: lockouts }
+    }
+}
+
+// the same email may have an account on every tenant, each with its own counter
+fn account_key(user_email: &str) -> String {
+    format!("{}:{}", current_tenant(), user_email.trim().to_lowercase())
+}
+
+fn seconds_until(locked_until: Option<NaiveDateTime>, now: NaiveDateTime) -> Option<u64> {
+    match locked_until {
+        Some(until) if until > now => Some(std::cmp::max((until - now).num_seconds(), 1) as u64),
+        _ => None,
+    }
+}
+
+fn lockout_seconds(failures: i32, max_failures: i32) -> i64 {
+    if failures < max_failures {
+        return 0;
+    }
+    // doubles with every failure past the threshold, capped at the configured maximum
+    let exponent = std::cmp::min(failures - max_failures, 20) as u32;
+    std::cmp::min(*LOCKOUT_SECONDS * 2i64.pow(exponent), *MAX_LOCKOUT_SECONDS)
+}
+
+#[cfg(feature = "diesel")]
+fn get_throttle(throttle_scope: &str, key: &str, connection: &PgConnection) -> Option<LoginThrottle> {
+    use schema::loginthrottles::dsl::*;
+
+    loginthrottles
+        .filter(scope.eq(throttle_scope).and(throttlekey.eq(key)))
+        .first::<LoginThrottle>(connection)
+        .optional()
+        .expect("Error loading login throttle")
+}
+
+#[cfg(feature = "diesel")]
+fn record_lockout_event(
+    throttle_scope: &str,
+    key: &str,
+    event: &str,
+    failures: i32,
+    locked_until: Option<NaiveDateTime>,
+    connection: &PgConnection,
+) {
+    use schema::lockoutevents;
+
+    let new_event = NewLockoutEvent {
+        scope: throttle_scope,
+        throttlekey: key,
+        event: event,
+        failures: failures,
+        lockeduntil: locked_until,
+        createdat: Utc::now().naive_utc(),
+    };
+    diesel::insert(&new_event)
+        .into(lockoutevents::table)
+        .execute(connection)
+        .expect("Error saving lockout event");
+}
+
+#[cfg(feature = "diesel")]
+fn register_failure(throttle_scope: &str, key: &str, max_failures: i32, connection: &PgConnection) {
+    use diesel::result::{DatabaseErrorKind, Error};
+    use schema::loginthrottles;
+
+    let now = Utc::now().naive_utc();
+
+    // parallel first failures all try to insert, the unique index keeps one row
+    let new_throttle = NewLoginThrottle {
+        scope: throttle_scope,
+        throttlekey: key,
+        failures: 0,
+        lastfailureat: None,
+        lockeduntil: None,
+    };
+    match diesel::insert(&new_throttle).into(loginthrottles::table).execute(connection) {
+        Ok(_) | Err(Error::DatabaseError(DatabaseErrorKind::UniqueViolation, _)) => {}
+        Err(e) => panic!("Error saving login throttle: {}", e),
+    }
+
+    let (failures, locked_until) = connection
+        .transaction::<_, Error, _>(|| {
+            // setting a column to itself locks the row, a parallel failure waits and counts on top of this one
+            let throttle: LoginThrottle = diesel::update(loginthrottles::table.filter(
+                loginthrottles::scope.eq(throttle_scope).and(loginthrottles::throttlekey.eq(key)),
+            )).set(loginthrottles::failures.eq(loginthrottles::failures))
+                .get_result(connection)?;
+
+            // failures older than the longest possible lockout no longer count
+            let stale = match throttle.lastfailureat {
+                Some(at) => now - at > Duration::seconds(*MAX_LOCKOUT_SECONDS),
+                None => true,
+            };
+            let failures = if stale { 1 } else { throttle.failures + 1 };
+            let seconds = lockout_seconds(failures, max_failures);
+            let locked_until = if seconds > 0 {
+                Some(now + Duration::seconds(seconds))
+            } else {
+                None
+            };
+
+            diesel::update(loginthrottles::table.find(throttle.id))
+                .set((
+                    loginthrottles::failures.eq(failures),
+                    loginthrottles::lastfailureat.eq(Some(now)),
+                    loginthrottles::lockeduntil.eq(locked_until),
+                ))
+                .execute(connection)?;
+            Ok((failures, locked_until))
+        })
+        .expect("Error updating login throttle");
+
+    if locked_until.is_some() {
+        record_lockout_event(throttle_scope, key, "locked", failures, locked_until, connection);
+    }
+}
+
+#[cfg(feature = "diesel")]
+fn reset_throttle(throttle_scope: &str, key: &str, connection: &PgConnection) {
+    use schema::loginthrottles;
+
+    if let Some(throttle) = get_throttle(throttle_scope, key, connection) {
+        if throttle.failures == 0 && throttle.lockeduntil.is_none() {
+            return;
+        }
+        diesel::update(loginthrottles::table.find(throttle.id))
+            .set((
+                loginthrottles::failures.eq(0),
+                loginthrottles::lockeduntil.eq(None::<NaiveDateTime>),
+            ))
+            .execute(connection)
+            .expect("Error resetting login throttle");
+
+        if throttle.lockeduntil.is_some() {
+            record_lockout_event(throttle_scope, key, "unlocked", throttle.failures, None, connection);
+        }
+    }
+}
+
+/// Seconds to wait before the next login attempt, if either the account or the client IP is locked.
+#[cfg(feature = "diesel")]
+pub fn login_retry_after(user_email: &str, remote_ip: &str) -> Option<u64> {
+    let connection = establish_connection();
+    let now = Utc::now().naive_utc();
+
+    let account_wait = get_throttle(ACCOUNT_SCOPE, &account_key(user_email), &connection)
+        .and_then(|throttle| seconds_until(throttle.lockeduntil, now));
+    let ip_wait = get_throttle(IP_SCOPE, remote_ip, &connection)
+        .and_then(|throttle| seconds_until(throttle.lockeduntil, now));
+
+    match (account_wait, ip_wait) {
+        (Some(account_wait), Some(ip_wait)) => Some(std::cmp::max(account_wait, ip_wait)),
+        (account_wait, ip_wait) => account_wait.or(ip_wait),
+    }
+}
+
+#[cfg(feature = "diesel")]
+pub fn record_login_failure(user_email: &str, remote_ip: &str) {
+    let connection = establish_connection();
+
+    register_failure(ACCOUNT_SCOPE, &account_key(user_email), *MAX_FAILED_LOGINS, &connection);
+    register_failure(IP_SCOPE, remote_ip, *IP_MAX_FAILED_LOGINS, &connection);
+}
+
+#[cfg(feature = "diesel")]
+pub fn record_login_success(user_email: &str) {
+    let connection = establish_connection();
+
+    reset_throttle(ACCOUNT_SCOPE, &account_key(user_email), &connection);
+}
+
+/// Drops the per-account counter and lockout events of a deleted user, both name the account by its email.
+#[cfg(feature = "diesel")]
+pub fn forget_account_throttle(user_email: &str, connection: &PgConnection) -> QueryResult<usize> {
+    use schema::{lockoutevents, loginthrottles};
+
+    let key = account_key(user_email);
+    let events = diesel::delete(lockoutevents::table.filter(
+        lockoutevents::scope.eq(ACCOUNT_SCOPE).and(lockoutevents::throttlekey.eq(&key)),
+    )).execute(connection)?;
+    let throttles = diesel::delete(loginthrottles::table.filter(
+        loginthrottles::scope.eq(ACCOUNT_SCOPE).and(loginthrottles::throttlekey.eq(&key)),
+    )).execute(connection)?;
+    Ok(events + throttles)
+}
+
+#[cfg(feature = "diesel")]
+fn get_lockout_events(params: (i64, i64)) -> Vec<LockoutEvent> {
+    use schema::lockoutevents::dsl::*;
+
+    let (limit, offset) = params;
+    let connection = establish_connection();
+
+    let own_accounts = scope.eq(ACCOUNT_SCOPE).and(throttlekey.like(format!("{}:%", current_tenant())));
+    // client IPs are locked for the whole server, only the default tenant's admins see them
+    let events = if current_tenant() == DEFAULT_TENANT_ID {
+        lockoutevents.filter(own_accounts.or(scope.eq(IP_SCOPE))).into_boxed()
+    } else {
+        lockoutevents.filter(own_accounts).into_boxed()
+    };
+
+    events
+        .order(createdat.desc())
+        .limit(limit)
+        .offset(offset)
+        .load::<LockoutEvent>(&connection)
+        .expect("Error loading lockout events")
+}
+
+fn lockouts_result(_: LockoutsResult) {}
+
+pub fn list_lockouts_handler(req: Request, res: Response, c: Captures) {
+    let (_, logged_id) = prepare_parameters(req);
+
+    let caps = c.unwrap();
+    let url = &caps[0];
+    let limit: i64 = get_query_param(url, "limit").and_then(|v| v.parse().ok()).unwrap_or(20);
+    let offset: i64 = get_query_param(url, "offset").and_then(|v| v.parse().ok()).unwrap_or(0);
+
+    #[cfg(feature = "diesel")]
+    {
+        if let Err(denied) = check(logged_id, Action::ManageUsers, None) {
+            send_denied(res, denied);
+            return;
+        }
+
+        process_container(res, lockouts_result, get_lockout_events, (limit.max(1).min(MAX_LOCKOUTS_LIMIT), offset.max(0)))
+    }
+}
+
+pub fn send_too_many_requests(mut res: Response, retry_after: u64) {
+    res.headers_mut().set_raw(
+        "Retry-After",
+        vec![retry_after.to_string().into_bytes()],
+    );
+    send_error(
+        res,
+        StatusCode::TooManyRequests,
+        "too many failed login attempts, try again later",
+    );
+}
+
+#[cfg(test)]
+use rand::Rng;
+
+#[cfg(test)]
+fn post_login(email: &str, password: &str, forwarded_for: &str) -> hyper::client::Response {
+    let client = Client::new();
+    let body = format!(
+        r#"{{"user":{{"email": "{}","password": "{}"}}}}"#,
+        email,
+        password
+    );
+
+    let mut headers = hyper::header::Headers::new();
+    headers.set_raw("X-Forwarded-For", vec![forwarded_for.as_bytes().to_vec()]);
+
+    client
+        .post("http://localhost:6767/api/users/login")
+        .headers(headers)
+        .body(&body)
+        .send()
+        .unwrap()
+}
+
+#[cfg(test)]
+#[test]
+fn account_lockout_test() {
+    let (_, email) = register_jacob();
+    let mut rng = rand::thread_rng();
+    let forwarded_for = format!("10.{}.{}.{}", rng.gen_range(0, 255), rng.gen_range(0, 255), rng.gen_range(1, 255));
+    let wrong_password = user::JACOB_PASSWORD.to_string() + "!";
+
+    for _ in 0..*MAX_FAILED_LOGINS {
+        let res = post_login(&email, &wrong_password, &forwarded_for);
+        assert_eq!(res.status, StatusCode::Unauthorized);
+    }
+
+    // even the right password is refused while the account is locked
+    let res = post_login(&email, user::JACOB_PASSWORD, &forwarded_for);
+    assert_eq!(res.status, StatusCode::TooManyRequests);
+    assert!(res.headers.get_raw("Retry-After").is_some());
+}
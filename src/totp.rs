@@ -0,0 +1,205 @@
+extern crate crypto;
+
+extern crate rand;
+
+use crypto::hmac::Hmac;
+use crypto::mac::Mac;
+use crypto::sha1::Sha1;
+use crypto::util::fixed_time_eq;
+
+use rand::Rng;
+use rand::os::OsRng;
+
+pub static TOTP_ISSUER: &'static str = "Conduit";
+
+const TOTP_PERIOD: u64 = 30;
+const TOTP_DIGITS: u32 = 6;
+const TOTP_SECRET_LENGTH: usize = 20;
+// number of 30 second steps accepted before and after the current one
+const TOTP_SKEW: i64 = 1;
+
+static BASE32_ALPHABET: &'static [u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+pub fn base32_encode(data: &[u8]) -> String {
+    let mut result = String::new();
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+
+    for &byte in data {
+        buffer = (buffer << 8) | byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            let index = (buffer >> (bits - 5)) & 0x1f;
+            result.push(BASE32_ALPHABET[index as usize] as char);
+            bits -= 5;
+        }
+        buffer &= (1 << bits) - 1;
+    }
+    if bits > 0 {
+        let index = (buffer << (5 - bits)) & 0x1f;
+        result.push(BASE32_ALPHABET[index as usize] as char);
+    }
+    result
+}
+
+pub fn base32_decode(encoded: &str) -> Option<Vec<u8>> {
+    let mut result = Vec::new();
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+
+    for c in encoded.trim_right_matches('=').chars() {
+        let c = c.to_ascii_uppercase() as u8;
+        let value = match BASE32_ALPHABET.iter().position(|&a| a == c) {
+            Some(value) => value as u32,
+            None => return None,
+        };
+        buffer = (buffer << 5) | value;
+        bits += 5;
+        if bits >= 8 {
+            result.push((buffer >> (bits - 8)) as u8);
+            bits -= 8;
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    Some(result)
+}
+
+pub fn generate_secret() -> String {
+    let mut secret = [0u8; TOTP_SECRET_LENGTH];
+    OsRng::new().expect("Failed to access the OS random number generator").fill_bytes(&mut secret);
+    base32_encode(&secret)
+}
+
+/// RFC 4226 HOTP value for the given key and counter.
+pub fn hotp(key: &[u8], counter: u64) -> u32 {
+    let mut message = [0u8; 8];
+    for i in 0..8 {
+        message[i] = (counter >> (8 * (7 - i))) as u8;
+    }
+
+    let mut mac = Hmac::new(Sha1::new(), key);
+    mac.input(&message);
+    let result = mac.result();
+    let digest = result.code();
+
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let binary = ((digest[offset] as u32 & 0x7f) << 24) | ((digest[offset + 1] as u32) << 16) |
+        ((digest[offset + 2] as u32) << 8) | (digest[offset + 3] as u32);
+
+    binary % 10u32.pow(TOTP_DIGITS)
+}
+
+/// RFC 6238 TOTP value for the given key at `unix_seconds`.
+pub fn totp_at(key: &[u8], unix_seconds: u64) -> u32 {
+    hotp(key, unix_seconds / TOTP_PERIOD)
+}
+
+pub fn format_code(code: u32) -> String {
+    format!("{:0width$}", code, width = TOTP_DIGITS as usize)
+}
+
+/// The time step `code` belongs to, if it is valid now and newer than `last_used_step`; a code is good only once.
+pub fn verify_code(secret: &str, code: &str, unix_seconds: u64, last_used_step: Option<i64>) -> Option<i64> {
+    let code = code.trim();
+    if code.len() != TOTP_DIGITS as usize || !code.chars().all(|c| c.is_digit(10)) {
+        return None;
+    }
+    let key = match base32_decode(secret) {
+        Some(key) => key,
+        None => return None,
+    };
+
+    let current_step = (unix_seconds / TOTP_PERIOD) as i64;
+    let mut accepted = None;
+    for skew in -TOTP_SKEW..TOTP_SKEW + 1 {
+        let step = current_step + skew;
+        if step < 0 || last_used_step.map_or(false, |last| step <= last) {
+            continue;
+        }
+        let expected = format_code(hotp(&key, step as u64));
+        if fixed_time_eq(expected.as_bytes(), code.as_bytes()) {
+            accepted = Some(step);
+        }
+    }
+    accepted
+}
+
+fn url_encode(value: &str) -> String {
+    let mut result = String::new();
+    for byte in value.bytes() {
+        match byte {
+            b'A'...b'Z' | b'a'...b'z' | b'0'...b'9' | b'-' | b'.' | b'_' | b'~' => {
+                result.push(byte as char)
+            }
+            _ => result.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    result
+}
+
+pub fn otpauth_uri(account_name: &str, secret: &str) -> String {
+    format!(
+        "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm=SHA1&digits={}&period={}",
+        url_encode(TOTP_ISSUER),
+        url_encode(account_name),
+        secret,
+        url_encode(TOTP_ISSUER),
+        TOTP_DIGITS,
+        TOTP_PERIOD
+    )
+}
+
+/// One-time recovery codes in the `xxxxx-xxxxx` form, returned in plain text only once.
+pub fn generate_recovery_codes(count: usize) -> Vec<String> {
+    let mut rng = OsRng::new().expect("Failed to access the OS random number generator");
+    (0..count)
+        .map(|_| {
+            let code: String = rng.gen_ascii_chars()
+                .filter(|c| c.is_lowercase() || c.is_digit(10))
+                .take(10)
+                .collect();
+            format!("{}-{}", &code[..5], &code[5..])
+        })
+        .collect()
+}
+
+#[cfg(test)]
+static RFC6238_SECRET: &'static [u8] = b"12345678901234567890";
+
+#[cfg(test)]
+#[test]
+fn rfc6238_vectors_test() {
+    assert_eq!(format_code(totp_at(RFC6238_SECRET, 59)), "287082");
+    assert_eq!(format_code(totp_at(RFC6238_SECRET, 1111111109)), "081804");
+    assert_eq!(format_code(totp_at(RFC6238_SECRET, 1234567890)), "005924");
+    assert_eq!(format_code(totp_at(RFC6238_SECRET, 2000000000)), "279037");
+}
+
+#[cfg(test)]
+#[test]
+fn base32_roundtrip_test() {
+    let encoded = base32_encode(RFC6238_SECRET);
+    assert_eq!(encoded, "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ");
+    assert_eq!(base32_decode(&encoded).unwrap(), RFC6238_SECRET.to_vec());
+}
+
+#[cfg(test)]
+#[test]
+fn verify_code_skew_test() {
+    let secret = base32_encode(RFC6238_SECRET);
+    let step = verify_code(&secret, "081804", 1111111109, None);
+    assert_eq!(step, Some(1111111109 / 30));
+    assert_eq!(verify_code(&secret, "081804", 1111111109 + 30, None), step);
+    assert_eq!(verify_code(&secret, "081804", 1111111109 + 90, None), None);
+    assert_eq!(verify_code(&secret, "08180", 1111111109, None), None);
+}
+
+#[cfg(test)]
+#[test]
+fn verify_code_replay_test() {
+    let secret = base32_encode(RFC6238_SECRET);
+    let step = verify_code(&secret, "081804", 1111111109, None);
+    assert!(step.is_some());
+    assert_eq!(verify_code(&secret, "081804", 1111111109 + 30, step), None);
+    assert_eq!(verify_code(&secret, "081804", 1111111109, step.map(|s| s - 1)), step);
+}
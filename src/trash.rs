@@ -0,0 +1,355 @@
+extern crate hyper;
+
+extern crate serde;
+extern crate serde_json;
+
+extern crate chrono;
+
+extern crate reroute;
+
+use hyper::status::StatusCode;
+
+use hyper::server::{Request, Response};
+use reroute::Captures;
+
+use chrono::Duration as ChronoDuration;
+
+use super::*;
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+#[allow(non_snake_case)]
+pub struct TrashedArticle {
+    pub id: i32,
+    pub slug: String,
+    pub title: String,
+    pub deletedAt: NaiveDateTime,
+    pub deletedBy: Option<i32>,
+    pub purgeAt: NaiveDateTime,
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+#[allow(non_snake_case)]
+pub struct TrashedComment {
+    pub id: i32,
+    pub articleSlug: String,
+    pub body: String,
+    pub parentId: Option<i32>,
+    pub deletedAt: NaiveDateTime,
+    pub deletedBy: Option<i32>,
+    pub purgeAt: NaiveDateTime,
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+pub struct TrashResult {
+    pub articles: Vec<TrashedArticle>,
+    pub comments: Vec<TrashedComment>,
+}
+
+pub fn purge_time(deleted_at: NaiveDateTime) -> NaiveDateTime {
+    deleted_at + ChronoDuration::days(*TRASH_RETENTION_DAYS)
+}
+
+/// The user's own deleted articles and comments, the latest first.
+#[cfg(feature = "diesel")]
+fn get_trash(user_id: i32) -> Option<TrashResult> {
+    use schema::{articles, comments};
+
+    let connection = establish_connection();
+
+    let trashed_articles: Vec<Article> = articles::table
+        .filter(articles::author.eq(user_id).and(articles::deletedat.is_not_null()))
+        .order(articles::deletedat.desc())
+        .load(&connection)
+        .expect("Error loading trashed articles");
+    let trashed_comments: Vec<(Comment, Article)> = comments::table
+        .inner_join(articles::table)
+        .filter(comments::author.eq(user_id).and(comments::deletedat.is_not_null()))
+        .order(comments::deletedat.desc())
+        .load(&connection)
+        .expect("Error loading trashed comments");
+
+    Some(TrashResult {
+        articles: trashed_articles
+            .into_iter()
+            .filter_map(|article| {
+                article.deletedAt.map(|deleted_at| TrashedArticle {
+                    id: article.id,
+                    slug: article.slug,
+                    title: article.title,
+                    deletedAt: deleted_at,
+                    deletedBy: article.deletedBy,
+                    purgeAt: purge_time(deleted_at),
+                })
+            })
+            .collect(),
+        comments: trashed_comments
+            .into_iter()
+            .filter_map(|(comment, article)| {
+                comment.deletedAt.map(|deleted_at| TrashedComment {
+                    id: comment.id,
+                    articleSlug: article.slug,
+                    body: comment.body,
+                    parentId: comment.parentId,
+                    deletedAt: deleted_at,
+                    deletedBy: comment.deletedBy,
+                    purgeAt: purge_time(deleted_at),
+                })
+            })
+            .collect(),
+    })
+}
+
+#[cfg(feature = "diesel")]
+fn find_trashed_article(article_id: &str) -> Option<Article> {
+    use schema::articles::dsl::*;
+
+    let connection = establish_connection();
+    match article_id.parse::<i32>() {
+        Ok(parsed_id) => articles
+            .filter(id.eq(parsed_id).and(deletedat.is_not_null()).and(tenantid.eq(current_tenant())))
+            .first(&connection)
+            .optional()
+            .expect("Error loading trashed article"),
+        Err(_) => None,
+    }
+}
+
+#[cfg(feature = "diesel")]
+fn find_trashed_comment(comment_id: &str) -> Option<Comment> {
+    use schema::comments::dsl::*;
+
+    let connection = establish_connection();
+    match comment_id.parse::<i32>() {
+        Ok(parsed_id) => comments
+            .filter(id.eq(parsed_id).and(deletedat.is_not_null()))
+            .first(&connection)
+            .optional()
+            .expect("Error loading trashed comment"),
+        Err(_) => None,
+    }
+}
+
+/// Takes the article out of the trash, the purge job waiting for it finds nothing to do.
+#[cfg(feature = "diesel")]
+fn restore_article(params: (Article, i32)) -> Option<ArticleResult> {
+    use schema::articles::dsl::*;
+
+    let (article, restored_by) = params;
+
+    let connection = establish_connection();
+    let untrashed: Article = diesel::update(articles.find(article.id))
+        .set((deletedat.eq(None::<NaiveDateTime>), deletedby.eq(None::<i32>)))
+        .get_result(&connection)
+        .expect("Error restoring article");
+    record_audit(Some(restored_by), "restore", "article", article.id, audit_value(&article), audit_value(&untrashed), &connection)
+        .expect("Error recording audit entry");
+
+    let restored = get_advanced_article(&article.slug);
+    if let Some(ref restored) = restored {
+        dispatch_webhooks("article.restored", restored.article.author, restored);
+    }
+    restored
+}
+
+/// Takes the comment out of the trash together with the replies that went there with it.
+#[cfg(feature = "diesel")]
+fn restore_comment(params: (Comment, i32)) -> Option<CommentResult> {
+    use schema::comments::dsl::*;
+    use diesel::expression::dsl::any;
+
+    let (comment, restored_by) = params;
+
+    let connection = establish_connection();
+
+    let mut restored_ids = vec![comment.id];
+    if !comment.deleted {
+        let mut parent_ids = restored_ids.clone();
+        while !parent_ids.is_empty() {
+            let replies: Vec<i32> = comments
+                .filter(parentid.eq(any(&parent_ids)).and(deletedat.eq(comment.deletedAt)))
+                .select(id)
+                .load(&connection)
+                .expect("Error loading replies");
+            restored_ids.extend(replies.iter().cloned());
+            parent_ids = replies;
+        }
+    }
+    diesel::update(comments.filter(id.eq(any(&restored_ids))))
+        .set((deleted.eq(false), deletedat.eq(None::<NaiveDateTime>), deletedby.eq(None::<i32>)))
+        .execute(&connection)
+        .expect("Error restoring comment");
+
+    let restored: Comment = comments.find(comment.id).first(&connection).expect("Error loading comment");
+    record_audit(Some(restored_by), "restore", "comment", comment.id, audit_value(&comment), audit_value(&restored), &connection)
+        .expect("Error recording audit entry");
+    let author_id = restored.author;
+    Some(get_comment_result(restored, author_id))
+}
+
+/// Why `comment` would stay out of sight after a restore: its article or the comment it replies to is in the trash.
+#[cfg(feature = "diesel")]
+fn restore_blocker(comment: &Comment) -> Option<&'static str> {
+    use schema::{articles, comments};
+
+    let connection = establish_connection();
+    let article_deleted_at: Option<NaiveDateTime> = articles::table
+        .find(comment.articleid)
+        .select(articles::deletedat)
+        .first(&connection)
+        .expect("Error loading commented article");
+    if article_deleted_at.is_some() {
+        return Some("restore the article first");
+    }
+
+    let parent: Option<(bool, Option<NaiveDateTime>)> = match comment.parentId {
+        Some(parent_id) => comments::table
+            .find(parent_id)
+            .select((comments::deleted, comments::deletedat))
+            .first(&connection)
+            .optional()
+            .expect("Error loading parent comment"),
+        None => None,
+    };
+    match parent {
+        // a tombstone still carries its replies
+        Some((false, Some(_))) => Some("restore the comment it replies to first"),
+        _ => None,
+    }
+}
+
+#[cfg(feature = "diesel")]
+pub fn trash_handler(req: Request, res: Response, _: Captures) {
+    let (_, logged_id) = prepare_parameters(req);
+
+    if logged_id <= 0 {
+        send_denied(res, Denied::Unauthenticated);
+        return;
+    }
+    process(res, get_trash, logged_id)
+}
+
+#[cfg(feature = "diesel")]
+pub fn restore_article_handler(req: Request, res: Response, c: Captures) {
+    let (_, logged_id) = prepare_parameters(req);
+
+    let caps = c.unwrap();
+    let article_id = caps[0].replace("/api/user/trash/articles/", "").replace("/restore", "");
+    println!("restore_article_handler id: '{}'", article_id);
+
+    let article = match find_trashed_article(&article_id) {
+        Some(article) => article,
+        None => {
+            send_error(res, StatusCode::NotFound, "article not found in the trash");
+            return;
+        }
+    };
+    if let Err(denied) = check(logged_id, Action::DeleteArticle, Some(article.author)) {
+        send_denied(res, denied);
+        return;
+    }
+    process(res, restore_article, (article, logged_id))
+}
+
+#[cfg(feature = "diesel")]
+pub fn restore_comment_handler(req: Request, res: Response, c: Captures) {
+    let (_, logged_id) = prepare_parameters(req);
+
+    let caps = c.unwrap();
+    let comment_id = caps[0].replace("/api/user/trash/comments/", "").replace("/restore", "");
+    println!("restore_comment_handler id: '{}'", comment_id);
+
+    let comment = match find_trashed_comment(&comment_id) {
+        Some(comment) => comment,
+        None => {
+            send_error(res, StatusCode::NotFound, "comment not found in the trash");
+            return;
+        }
+    };
+    if let Err(denied) = check(logged_id, Action::DeleteComment, Some(comment.author)) {
+        send_denied(res, denied);
+        return;
+    }
+    if let Some(message) = restore_blocker(&comment) {
+        send_error(res, StatusCode::UnprocessableEntity, message);
+        return;
+    }
+    process(res, restore_comment, (comment, logged_id))
+}
+
+#[cfg(test)]
+#[test]
+fn trash_test() {
+    let client = Client::new();
+
+    let (jwt, slug, _) = login_create_article(false);
+    let url = format!("http://localhost:6767/api/articles/{}", slug);
+
+    let mut res = client
+        .post(&format!("{}/comments", url))
+        .header(Authorization(Bearer { token: jwt.to_owned() }))
+        .body(r#"{"comment": {"body": "Soon in the trash."}}"#)
+        .send()
+        .unwrap();
+    let mut buffer = String::new();
+    res.read_to_string(&mut buffer).unwrap();
+    let comment_id = serde_json::from_str::<CommentResult>(&buffer).unwrap().comment.id;
+
+    for deleted_url in &[format!("{}/comments/{}", url, comment_id), url.to_owned()] {
+        let res = client
+            .delete(deleted_url)
+            .header(Authorization(Bearer { token: jwt.to_owned() }))
+            .body("")
+            .send()
+            .unwrap();
+        assert_eq!(res.status, hyper::Ok);
+    }
+    assert_eq!(client.get(&url).send().unwrap().status, StatusCode::NotFound);
+
+    let mut res = client
+        .get("http://localhost:6767/api/user/trash")
+        .header(Authorization(Bearer { token: jwt.to_owned() }))
+        .send()
+        .unwrap();
+    let mut buffer = String::new();
+    res.read_to_string(&mut buffer).unwrap();
+    let trash: TrashResult = serde_json::from_str(&buffer).unwrap();
+    let article_id = trash.articles.iter().find(|a| a.slug == slug).unwrap().id;
+    assert!(trash.comments.iter().any(|c| c.id == comment_id));
+
+    let comment_restore_url = format!("http://localhost:6767/api/user/trash/comments/{}/restore", comment_id);
+    let res = client
+        .post(&comment_restore_url)
+        .header(Authorization(Bearer { token: jwt.to_owned() }))
+        .body("")
+        .send()
+        .unwrap();
+    assert_eq!(res.status, StatusCode::UnprocessableEntity);
+
+    let res = client
+        .post(&format!("http://localhost:6767/api/user/trash/articles/{}/restore", article_id))
+        .header(Authorization(Bearer { token: login_other_jacob() }))
+        .body("")
+        .send()
+        .unwrap();
+    assert_eq!(res.status, StatusCode::Forbidden);
+
+    for restore_url in &[format!("http://localhost:6767/api/user/trash/articles/{}/restore", article_id), comment_restore_url] {
+        let res = client
+            .post(restore_url)
+            .header(Authorization(Bearer { token: jwt.to_owned() }))
+            .body("")
+            .send()
+            .unwrap();
+        assert_eq!(res.status, hyper::Ok);
+    }
+
+    let mut res = client.get(&format!("{}/comments", url)).send().unwrap();
+    let mut buffer = String::new();
+    res.read_to_string(&mut buffer).unwrap();
+    assert_eq!(res.status, hyper::Ok);
+    let comments: CommentsResult = serde_json::from_str(&buffer).unwrap();
+    assert_eq!(comments.comments[0].body, "Soon in the trash.");
+}
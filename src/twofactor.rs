@@ -0,0 +1,392 @@
+extern crate hyper;
+
+extern crate serde;
+extern crate serde_json;
+
+extern crate chrono;
+
+extern crate crypto;
+
+extern crate reroute;
+
+use hyper::status::StatusCode;
+
+use hyper::server::{Request, Response};
+use reroute::Captures;
+
+use super::*;
+
+const RECOVERY_CODES_COUNT: usize = 10;
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+#[allow(non_snake_case)]
+pub struct TwoFactorChallenge {
+    pub required: bool,
+    pub preAuthToken: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+#[allow(non_snake_case)]
+pub struct TwoFactorChallengeResult {
+    pub twoFactor: TwoFactorChallenge,
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+#[allow(non_snake_case)]
+pub struct TwoFactorSetup {
+    pub secret: String,
+    pub otpauthUri: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+#[allow(non_snake_case)]
+pub struct TwoFactorSetupResult {
+    pub twoFactor: TwoFactorSetup,
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+#[allow(non_snake_case)]
+pub struct TwoFactorConfirmation {
+    pub enabled: bool,
+    pub recoveryCodes: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+#[allow(non_snake_case)]
+pub struct TwoFactorConfirmationResult {
+    pub twoFactor: TwoFactorConfirmation,
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+#[allow(non_snake_case)]
+struct TwoFactorCodeDetail {
+    code: String,
+    preAuthToken: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+#[allow(non_snake_case)]
+struct TwoFactorCode {
+    twoFactor: TwoFactorCodeDetail,
+}
+
+#[cfg(feature = "diesel")]
+fn get_two_factor(user_id: i32) -> Option<TwoFactor> {
+    use schema::twofactors::dsl::*;
+
+    let connection = establish_connection();
+    twofactors
+        .filter(userid.eq(user_id))
+        .first::<TwoFactor>(&connection)
+        .optional()
+        .expect("Error loading two-factor settings")
+}
+
+#[cfg(feature = "diesel")]
+pub fn is_two_factor_enabled(user_id: i32) -> bool {
+    match get_two_factor(user_id) {
+        Some(two_factor) => two_factor.enabled,
+        None => false,
+    }
+}
+
+#[cfg(feature = "diesel")]
+fn setup_two_factor(user_id: i32) -> Option<TwoFactorSetupResult> {
+    use schema::twofactors;
+    use chrono::prelude::*;
+
+    let connection = establish_connection();
+    let user = get_user_by_id(user_id).unwrap().user;
+
+    // a new setup replaces any enrollment that was never confirmed
+    diesel::delete(twofactors::table.filter(twofactors::userid.eq(user_id)))
+        .execute(&connection)
+        .expect("Error removing pending two-factor settings");
+
+    let secret = totp::generate_secret();
+    let utc: DateTime<Utc> = Utc::now();
+    let new_two_factor = NewTwoFactor {
+        userid: user_id,
+        secret: &secret,
+        enabled: false,
+        createdat: utc.naive_utc(),
+    };
+    let _two_factor: TwoFactor = diesel::insert(&new_two_factor)
+        .into(twofactors::table)
+        .get_result(&connection)
+        .expect("Error saving two-factor settings");
+
+    Some(TwoFactorSetupResult {
+        twoFactor: TwoFactorSetup {
+            otpauthUri: totp::otpauth_uri(&user.username, &secret),
+            secret: secret,
+        },
+    })
+}
+
+/// Takes the TOTP code of `step` for `user_id`, unless a concurrent request took it or a newer one first.
+#[cfg(feature = "diesel")]
+fn use_time_step(user_id: i32, step: i64) -> bool {
+    use schema::twofactors::dsl::*;
+
+    let connection = establish_connection();
+    let updated = diesel::update(
+        twofactors.filter(userid.eq(user_id).and(lastusedstep.is_null().or(lastusedstep.lt(step)))),
+    ).set(lastusedstep.eq(Some(step)))
+        .execute(&connection)
+        .expect("Error saving two-factor time step");
+    updated > 0
+}
+
+#[cfg(feature = "diesel")]
+fn confirm_two_factor(params: (i32, i64)) -> Option<TwoFactorConfirmationResult> {
+    use schema::twofactors;
+    use schema::recoverycodes;
+
+    let (user_id, step) = params;
+    let connection = establish_connection();
+
+    diesel::update(twofactors::table.filter(twofactors::userid.eq(user_id)))
+        .set((twofactors::enabled.eq(true), twofactors::lastusedstep.eq(Some(step))))
+        .execute(&connection)
+        .expect("Error enabling two-factor authentication");
+
+    diesel::delete(recoverycodes::table.filter(recoverycodes::userid.eq(user_id)))
+        .execute(&connection)
+        .expect("Error removing old recovery codes");
+
+    let codes = totp::generate_recovery_codes(RECOVERY_CODES_COUNT);
+    for code in &codes {
+        let code_hash: &str = &crypto::pbkdf2::pbkdf2_simple(code, 10000).unwrap();
+        let new_code = NewRecoveryCode {
+            userid: user_id,
+            codehash: code_hash,
+        };
+        diesel::insert(&new_code)
+            .into(recoverycodes::table)
+            .execute(&connection)
+            .expect("Error saving recovery code");
+    }
+
+    Some(TwoFactorConfirmationResult {
+        twoFactor: TwoFactorConfirmation {
+            enabled: true,
+            recoveryCodes: codes,
+        },
+    })
+}
+
+#[cfg(feature = "diesel")]
+fn use_recovery_code(user_id: i32, code: &str) -> bool {
+    use schema::recoverycodes::dsl::*;
+    use chrono::prelude::*;
+
+    let connection = establish_connection();
+    let unused_codes: Vec<RecoveryCode> = recoverycodes
+        .filter(userid.eq(user_id).and(usedat.is_null()))
+        .load(&connection)
+        .expect("Error loading recovery codes");
+
+    for recovery_code in unused_codes {
+        if let Ok(true) = crypto::pbkdf2::pbkdf2_check(code.trim(), &recovery_code.codehash) {
+            let utc: DateTime<Utc> = Utc::now();
+            // a parallel login redeeming the same code finds it used already
+            let updated = diesel::update(recoverycodes.filter(id.eq(recovery_code.id).and(usedat.is_null())))
+                .set(usedat.eq(Some(utc.naive_utc())))
+                .execute(&connection)
+                .expect("Error marking recovery code as used");
+            return updated > 0;
+        }
+    }
+    false
+}
+
+#[cfg(feature = "diesel")]
+fn verify_second_factor(user_id: i32, code: &str) -> bool {
+    match get_two_factor(user_id) {
+        Some(ref two_factor) if two_factor.enabled => {
+            match totp::verify_code(&two_factor.secret, code, since_the_epoch() / 1000, two_factor.lastusedstep) {
+                Some(step) => use_time_step(user_id, step),
+                None => use_recovery_code(user_id, code),
+            }
+        }
+        _ => false,
+    }
+}
+
+#[cfg(feature = "diesel")]
+pub fn two_factor_setup_handler(req: Request, res: Response, _: Captures) {
+    let (_, logged_id) = prepare_parameters(req);
+
+    if logged_id <= 0 {
+        send_denied(res, Denied::Unauthenticated);
+        return;
+    }
+
+    if is_two_factor_enabled(logged_id) {
+        send_error(
+            res,
+            StatusCode::UnprocessableEntity,
+            "two-factor authentication is already enabled",
+        );
+        return;
+    }
+    process(res, setup_two_factor, logged_id)
+}
+
+#[cfg(feature = "diesel")]
+pub fn two_factor_confirm_handler(req: Request, res: Response, _: Captures) {
+    let (body, logged_id) = prepare_parameters(req);
+
+    if logged_id <= 0 {
+        send_denied(res, Denied::Unauthenticated);
+        return;
+    }
+    let confirmation: TwoFactorCode = serde_json::from_str(&body).unwrap();
+
+    match get_two_factor(logged_id) {
+        Some(ref two_factor) if two_factor.enabled => {
+            send_error(
+                res,
+                StatusCode::UnprocessableEntity,
+                "two-factor authentication is already enabled",
+            )
+        }
+        Some(ref two_factor) => {
+            match totp::verify_code(
+                &two_factor.secret,
+                &confirmation.twoFactor.code,
+                since_the_epoch() / 1000,
+                two_factor.lastusedstep,
+            ) {
+                Some(step) => process(res, confirm_two_factor, (logged_id, step)),
+                None => send_error(res, StatusCode::UnprocessableEntity, "invalid two-factor code"),
+            }
+        }
+        None => {
+            send_error(
+                res,
+                StatusCode::UnprocessableEntity,
+                "two-factor setup has not been started",
+            )
+        }
+    }
+}
+
+pub fn two_factor_login_handler(req: Request, mut res: Response, _: Captures) {
+    let remote_ip = client_ip(&req);
+    let (body, _) = prepare_parameters(req);
+
+    let second_step: TwoFactorCode = serde_json::from_str(&body).unwrap();
+    let user_id = match second_step.twoFactor.preAuthToken.as_ref().and_then(
+        |token| pre_auth_login(token),
+    ) {
+        Some(user_id) => user_id,
+        None => {
+            send_error(res, StatusCode::Unauthorized, "pre-auth token is invalid or expired");
+            return;
+        }
+    };
+
+    #[cfg(feature = "diesel")]
+    {
+        let user_email = get_user_by_id(user_id).unwrap().user.email;
+
+        // second factor guesses count towards the same lockout as passwords
+        if let Some(retry_after) = login_retry_after(&user_email, &remote_ip) {
+            send_too_many_requests(res, retry_after);
+            return;
+        }
+        if !verify_second_factor(user_id, &second_step.twoFactor.code) {
+            record_login_failure(&user_email, &remote_ip);
+            send_error(res, StatusCode::Unauthorized, "invalid two-factor code");
+            return;
+        }
+
+        record_login_success(&user_email);
+        set_authorization_headers(&mut res, user_id);
+        process(res, get_user_by_id, user_id)
+    }
+}
+
+#[cfg(test)]
+fn post_two_factor(url: &str, jwt: Option<String>, body: &str) -> (hyper::status::StatusCode, String, Option<String>) {
+    let client = Client::new();
+
+    let request = client.post(url).body(body);
+    let request = match jwt {
+        Some(jwt) => request.header(Authorization(Bearer { token: jwt })),
+        None => request,
+    };
+    let mut res = request.send().unwrap();
+    let mut buffer = String::new();
+    res.read_to_string(&mut buffer).unwrap();
+
+    let token = res.headers.get::<Authorization<Bearer>>().map(|t| t.0.token.to_owned());
+    (res.status, buffer, token)
+}
+
+#[cfg(test)]
+#[test]
+fn two_factor_login_test() {
+    let (_, email) = register_jacob();
+    let jwt = login_jacob(email.to_owned(), JACOB_PASSWORD.to_string());
+
+    let (status, buffer, _) = post_two_factor("http://localhost:6767/api/user/2fa/setup", Some(jwt.to_owned()), "");
+    assert_eq!(status, hyper::Ok);
+    let setup: TwoFactorSetupResult = serde_json::from_str(&buffer).unwrap();
+    assert!(setup.twoFactor.otpauthUri.starts_with("otpauth://totp/"));
+
+    let key = totp::base32_decode(&setup.twoFactor.secret).unwrap();
+    let code = totp::format_code(totp::totp_at(&key, since_the_epoch() / 1000));
+    let body = format!(r#"{{"twoFactor": {{"code": "{}"}}}}"#, code);
+    let (status, buffer, _) = post_two_factor("http://localhost:6767/api/user/2fa/confirm", Some(jwt), &body);
+    assert_eq!(status, hyper::Ok);
+    let confirmation: TwoFactorConfirmationResult = serde_json::from_str(&buffer).unwrap();
+    assert_eq!(confirmation.twoFactor.enabled, true);
+    assert_eq!(confirmation.twoFactor.recoveryCodes.len(), RECOVERY_CODES_COUNT);
+
+    let body = format!(r#"{{"user":{{"email": "{}","password": "{}"}}}}"#, email, JACOB_PASSWORD);
+    let (status, buffer, token) = post_two_factor("http://localhost:6767/api/users/login", None, &body);
+    assert_eq!(status, hyper::Ok);
+    assert!(token.is_none());
+    let challenge: TwoFactorChallengeResult = serde_json::from_str(&buffer).unwrap();
+    assert_eq!(challenge.twoFactor.required, true);
+
+    let body = format!(
+        r#"{{"twoFactor": {{"code": "{}", "preAuthToken": "{}"}}}}"#,
+        confirmation.twoFactor.recoveryCodes[0],
+        challenge.twoFactor.preAuthToken
+    );
+    let (status, buffer, token) = post_two_factor("http://localhost:6767/api/users/login/2fa", None, &body);
+    assert_eq!(status, hyper::Ok);
+    assert!(token.is_some());
+    let logged: UserResult = serde_json::from_str(&buffer).unwrap();
+    assert_eq!(logged.user.email, email);
+
+    // recovery codes are single use
+    let (status, _, _) = post_two_factor("http://localhost:6767/api/users/login/2fa", None, &body);
+    assert_eq!(status, hyper::status::StatusCode::Unauthorized);
+
+    // and so is the TOTP code the setup was confirmed with
+    let body = format!(r#"{{"twoFactor": {{"code": "{}", "preAuthToken": "{}"}}}}"#, code, challenge.twoFactor.preAuthToken);
+    let (status, _, _) = post_two_factor("http://localhost:6767/api/users/login/2fa", None, &body);
+    assert_eq!(status, hyper::status::StatusCode::Unauthorized);
+}
+
+#[cfg(test)]
+#[test]
+fn two_factor_setup_unlogged_test() {
+    for url in &["http://localhost:6767/api/user/2fa/setup", "http://localhost:6767/api/user/2fa/confirm"] {
+        let (status, _, _) = post_two_factor(url, None, r#"{"twoFactor": {"code": "000000"}}"#);
+        assert_eq!(status, hyper::status::StatusCode::Unauthorized);
+    }
+}
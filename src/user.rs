@@ -45,6 +45,8 @@ use reroute::Captures;
 
 use crypto::sha2::Sha256;
 
+use std::collections::HashMap;
+
 use jwt::{Header, Registered, Token};
 
 use super::*;
@@ -61,11 +63,60 @@ pub fn new_token(user_id: &str, _: &str) -> Option<String> {
     token.signed(b"secret_key", Sha256::new()).ok()
 }
 
+// audience of the short-lived token issued between the password and the two-factor step
+static PRE_AUTH_AUDIENCE: &'static str = "2fa";
+const PRE_AUTH_TOKEN_LIFETIME: u64 = 300;
+const MAX_FOLLOW_LIST_LIMIT: i64 = 100;
+
+pub fn new_pre_auth_token(user_id: &str) -> Option<String> {
+    let header: jwt::Header = Default::default();
+    let claims = jwt::Registered {
+        iss: Some("mikkyang.com".into()),
+        sub: Some(user_id.into()),
+        aud: Some(PRE_AUTH_AUDIENCE.into()),
+        exp: Some(since_the_epoch() / 1000 + PRE_AUTH_TOKEN_LIFETIME),
+        ..Default::default()
+    };
+    let token = Token::new(header, claims);
+
+    token.signed(b"secret_key", Sha256::new()).ok()
+}
+
+pub fn pre_auth_login(token: &str) -> Option<i32> {
+    let token = match Token::<Header, Registered>::parse(token) {
+        Ok(token) => token,
+        Err(_) => return None,
+    };
+
+    if !token.verify(b"secret_key", Sha256::new()) {
+        return None;
+    }
+    if token.claims.aud.as_ref().map(|aud| aud.as_str()) != Some(PRE_AUTH_AUDIENCE) {
+        return None;
+    }
+    match token.claims.exp {
+        Some(exp) if exp >= since_the_epoch() / 1000 => (),
+        _ => return None,
+    }
+    let user_id = match token.claims.sub {
+        Some(sub) => sub.parse::<i32>().ok(),
+        None => None,
+    };
+    #[cfg(feature = "diesel")]
+    let user_id = user_id.filter(|user_id| is_tenant_member(*user_id));
+    user_id
+}
+
 pub fn login(token: &str) -> Option<i32> {
     let token = Token::<Header, Registered>::parse(token).unwrap();
 
+    // pre-auth tokens only grant access to the second login step
+    if token.claims.aud.is_some() {
+        return None;
+    }
+
     if token.verify(b"secret_key", Sha256::new()) {
-        match token.claims.sub {
+        let user_id = match token.claims.sub {
             Some(token) => {
                 match token.parse::<i32>() {
                     Ok(result) => Some(result),
@@ -73,7 +124,10 @@ pub fn login(token: &str) -> Option<i32> {
                 }
             }
             _ => None,
-        }
+        };
+        #[cfg(feature = "diesel")]
+        let user_id = user_id.filter(|user_id| is_tenant_member(*user_id));
+        user_id
 
 
     } else {
@@ -144,6 +198,8 @@ pub fn create_user<'a>(new_user: NewUser) -> Option<UserResult> {
         .into(users::table)
         .get_result(&connection)
         .expect("Error saving new user");
+    record_audit(Some(user.id), "create", "user", user.id, None, audit_value(&user), &connection)
+        .expect("Error recording audit entry");
     Some(UserResult { user: user })
 }
 
@@ -156,6 +212,15 @@ pub fn registration_handler(req: Request, res: Response, _: Captures) {
     let token: &str = &crypto::pbkdf2::pbkdf2_simple(&user.password, 10000).unwrap();
     let user_name: &str = &user.username;
 
+    if user_name == DELETED_USER_NAME {
+        send_error(res, StatusCode::UnprocessableEntity, "username is reserved");
+        return;
+    }
+    if !registration_open() {
+        send_error(res, StatusCode::Forbidden, "registration is closed");
+        return;
+    }
+
     #[cfg(feature = "tiberius")]
     {
         process(
@@ -179,6 +244,7 @@ pub fn registration_handler(req: Request, res: Response, _: Captures) {
             email: email,
             token: token,
             username: user_name,
+            tenantid: current_tenant(),
         };
         process(res, create_user, new_user);
     }
@@ -186,9 +252,14 @@ pub fn registration_handler(req: Request, res: Response, _: Captures) {
 
 #[cfg(feature = "diesel")] 
 fn update_user(updated: UpdatedUser) -> Option<UserResult> {
+    use schema::users;
+
     let conn = establish_connection();
 
+    let previous: User = users::table.find(updated.id).first(&conn).unwrap();
     let result = updated.save_changes::<User>(&conn).unwrap();
+    record_audit(Some(result.id), "update", "user", result.id, audit_value(&previous), audit_value(&result), &conn)
+        .expect("Error recording audit entry");
 
     Some(UserResult { user:result })
 }
@@ -210,7 +281,11 @@ pub fn update_user_handler(req: Request, res: Response, _: Captures) {
     let new_password: &str = &updated_user.user.password.as_ref().map(|x| &**x).unwrap_or("");
 
     let new_token: &str = &crypto::pbkdf2::pbkdf2_simple(new_password, 10000).unwrap();
-         
+
+    if user_name == DELETED_USER_NAME {
+        send_error(res, StatusCode::UnprocessableEntity, "username is reserved");
+        return;
+    }
 
     #[cfg(feature = "diesel")] {
         let updated = UpdatedUser  {
@@ -251,16 +326,15 @@ pub fn update_user_handler(req: Request, res: Response, _: Captures) {
 }
 
 #[cfg(feature = "diesel")]
-fn get_user_by_name(user_name: &str) -> Option<User> {
+pub fn get_user_by_name(user_name: &str) -> Option<User> {
     use schema::users::dsl::*;
 
     let connection = establish_connection();
-    let result: User = 
-        users
-        .filter(username.eq(user_name))
+    users
+        .filter(username.eq(user_name).and(tenantid.eq(current_tenant())))
         .first(&connection)
-        .unwrap();
-    Some(result)
+        .optional()
+        .unwrap()
 }
 
 #[cfg(feature = "diesel")]
@@ -290,8 +364,9 @@ pub fn get_current_user_handler(req: Request, res: Response, _: Captures) {
     process(res, get_user_by_id, logged_in_user_id);
 }
 
-fn get_profile_result(user: User) -> Option<ProfileResult> {
-    let followed = is_followed(user.id);
+fn get_profile_result(params: (User, i32)) -> Option<ProfileResult> {
+    let (user, viewer_id) = params;
+    let followed = is_followed(viewer_id, user.id);
     let result = Profile {
         username : user.username,
         bio : user.bio,
@@ -303,16 +378,22 @@ fn get_profile_result(user: User) -> Option<ProfileResult> {
 }
 
 pub fn get_profile_handler(req: Request, res: Response, c: Captures) {
-    let (_, _) = prepare_parameters(req);
+    let (_, logged_in_user_id) = prepare_parameters(req);
 
     let caps = c.unwrap();
     let profile = &caps[0].replace("/api/profiles/", "");
     println!("profile: {}", profile);
 
     #[cfg(feature = "diesel")] {
-        let user = get_user_by_name(profile).unwrap();
+        let user = match get_user_by_name(profile) {
+            Some(user) => user,
+            None => {
+                send_error(res, StatusCode::NotFound, "profile not found");
+                return;
+            }
+        };
 
-        process(res, get_profile_result, user)
+        process(res, get_profile_result, (user, logged_in_user_id))
     }
 
     #[cfg(feature = "tiberius")]
@@ -333,32 +414,46 @@ pub fn unfollow_handler(req: Request, res: Response, c: Captures) {
     println!("profile: {}", profile);
 
     #[cfg(feature = "diesel")] {
-        let following : User = get_user_by_name(profile).unwrap();
+        if logged_in_user_id <= 0 {
+            send_denied(res, Denied::Unauthenticated);
+            return;
+        }
+        let following = match get_user_by_name(profile) {
+            Some(user) => user,
+            None => {
+                send_error(res, StatusCode::NotFound, "profile not found");
+                return;
+            }
+        };
 
         unfollow_user(logged_in_user_id, following.id);
 
-        let updated_user = get_user_by_name(profile).unwrap();
-
-        process(res, get_profile_result, updated_user)
+        process(res, get_profile_result, (following, logged_in_user_id))
     }
 
     #[cfg(feature = "tiberius")]
     process(
         res,
-        r#"DECLARE @username nvarchar(max) = @P1;DECLARE @logged int = @P2;DELETE TOP (1) from [dbo].[Followings] WHERE [FollowerId] = @P2;"#, PROFILE_SELECT,
+        r#"DECLARE @username nvarchar(max) = @P1;DECLARE @logged int = @P2;
+        DELETE FROM [dbo].[Followings] WHERE [FollowerId] = @P2 AND [FollowingId] = (SELECT TOP (1) [Id] FROM [Users] WHERE UserName = @P1);"#, PROFILE_SELECT,
         get_profile_from_row,
         &[&(profile.as_str()), &logged_in_user_id]
     );
 }
 
+/// Whether `viewer_id` follows `user_id`; anonymous viewers (id 0) follow nobody.
 #[cfg(feature = "diesel")]
-fn is_followed(user_id: i32) -> bool {
+pub fn is_followed(viewer_id: i32, user_id: i32) -> bool {
     use schema::followings::dsl::*;
 
+    if viewer_id <= 0 {
+        return false;
+    }
+
     let connection = establish_connection();
 
     let followers_count: i64 = followings
-        .filter(followingid.eq(user_id))
+        .filter(followingid.eq(user_id).and(followerid.eq(viewer_id)))
         .count()
         .get_result(&connection)
         .unwrap();
@@ -366,15 +461,23 @@ fn is_followed(user_id: i32) -> bool {
 }
 
 #[cfg(feature = "diesel")]
-fn follow_user<'a>(follow: NewFollowing) {  
+fn follow_user<'a>(follow: NewFollowing) -> bool {  
+    use diesel::result::{DatabaseErrorKind, Error};
+
     let connection = establish_connection();
 
     use schema::followings;
 
-    let _relationship: Following = diesel::insert(&follow)
-    .into(followings::table)
-    .get_result(&connection)
-    .expect("Error saving new favorited article relationship");    
+    // following twice is a no-op, the ix_followings unique index keeps a single row
+    match diesel::insert(&follow).into(followings::table).get_result::<Following>(&connection) {
+        Ok(following) => {
+            record_audit(Some(following.followerid), "create", "following", following.id, None, audit_value(&following), &connection)
+                .expect("Error recording audit entry");
+            true
+        }
+        Err(Error::DatabaseError(DatabaseErrorKind::UniqueViolation, _)) => false,
+        Err(e) => panic!("Error saving new following relationship: {}", e),
+    }
 }
 
 #[cfg(feature = "diesel")]
@@ -383,12 +486,13 @@ fn unfollow_user<'a>(follower_id: i32, following_id: i32) {
 
     use schema::followings::dsl::*;
 
-    let relationship: Following = followings
-        .filter(followerid.eq(follower_id).and(followingid.eq(following_id)))
-        .first(&connection)
-        .unwrap();
-
-    diesel::delete(followings.filter(id.eq(relationship.id))).execute(&connection).expect("Failed to unfollow user");   
+    let removed: Vec<Following> = diesel::delete(followings.filter(followerid.eq(follower_id).and(followingid.eq(following_id))))
+        .get_results(&connection)
+        .expect("Failed to unfollow user");
+    for following in removed {
+        record_audit(Some(follower_id), "delete", "following", following.id, audit_value(&following), None, &connection)
+            .expect("Error recording audit entry");
+    }
 }
 
 pub fn follow_handler(req: Request, res: Response, c: Captures) {
@@ -400,75 +504,250 @@ pub fn follow_handler(req: Request, res: Response, c: Captures) {
     println!("profile: {}", profile);
 
     #[cfg(feature = "diesel")] {
-        let followed_user : User = get_user_by_name(profile).unwrap();
+        if logged_in_user_id <= 0 {
+            send_denied(res, Denied::Unauthenticated);
+            return;
+        }
+        let followed_user = match get_user_by_name(profile) {
+            Some(user) => user,
+            None => {
+                send_error(res, StatusCode::NotFound, "profile not found");
+                return;
+            }
+        };
+        if is_blocked(followed_user.id, logged_in_user_id) || is_blocked(logged_in_user_id, followed_user.id) {
+            send_error(res, StatusCode::Forbidden, "you cannot follow this user");
+            return;
+        }
 
         let follow = NewFollowing {
             followerid : logged_in_user_id,
             followingid : followed_user.id,
         };
 
-        follow_user(follow);
+        if follow_user(follow) {
+            notify(followed_user.id, logged_in_user_id, NotificationKind::Follow, None, None);
+            let follower = get_user_by_id(logged_in_user_id).unwrap().user;
+            let followed = FollowedResult {
+                follower: follower.username,
+                following: followed_user.username.clone(),
+            };
+            dispatch_webhooks("user.followed", followed_user.id, &followed);
+        }
 
-        let updated = get_user_by_name(profile).unwrap();
-        process(res, get_profile_result, updated)
+        process(res, get_profile_result, (followed_user, logged_in_user_id))
     }
 
     #[cfg(feature = "tiberius")]
     process(
         res,
         r#"DECLARE @username nvarchar(max) = @P1;DECLARE @logged int = @P2;INSERT INTO [dbo].[Followings] ([FollowingId] ,[FollowerId])
-     SELECT @P2,(SELECT TOP (1) [Id]  FROM [Users] where UserName = @P1) EXCEPT SELECT [FollowingId] ,[FollowerId] from Followings;"#, PROFILE_SELECT,
+     SELECT (SELECT TOP (1) [Id]  FROM [Users] where UserName = @P1),@P2 EXCEPT SELECT [FollowingId] ,[FollowerId] from Followings;"#, PROFILE_SELECT,
         get_profile_from_row,
         &[&(profile.as_str()), &logged_in_user_id]
     );
 }
 
+fn profiles_result(_: ProfilesResult) {}
+
+#[derive(Debug)]
+enum FollowDirection {
+    Followers,
+    Following,
+}
+
+/// One page of the users following `user_id` (or followed by it), flagged for `viewer_id`.
+#[cfg(feature = "diesel")]
+fn get_follow_list(params: (FollowDirection, i32, i32, i64, i64)) -> Vec<Profile> {
+    use schema::followings;
+
+    let (direction, user_id, viewer_id, limit, offset) = params;
+    let connection = establish_connection();
+
+    let page_ids: Vec<i32> = match direction {
+        FollowDirection::Followers => {
+            followings::table
+                .filter(followings::followingid.eq(user_id))
+                .order(followings::id.desc())
+                .select(followings::followerid)
+                .limit(limit)
+                .offset(offset)
+                .load(&connection)
+        }
+        FollowDirection::Following => {
+            followings::table
+                .filter(followings::followerid.eq(user_id))
+                .order(followings::id.desc())
+                .select(followings::followingid)
+                .limit(limit)
+                .offset(offset)
+                .load(&connection)
+        }
+    }.expect("Error loading follow list");
+
+    let mut profiles = get_profiles(&page_ids, viewer_id);
+
+    // keeps the newest relationship first, as paged above
+    page_ids
+        .iter()
+        .filter_map(|page_id| profiles.remove(page_id))
+        .collect()
+}
+
+/// Profiles of `user_ids` as `viewer_id` sees them, loaded with one query for the users and one for the follows.
+#[cfg(feature = "diesel")]
+pub fn get_profiles(user_ids: &[i32], viewer_id: i32) -> HashMap<i32, Profile> {
+    use schema::{followings, users};
+    use diesel::expression::dsl::any;
+
+    let connection = establish_connection();
+    let user_ids: Vec<i32> = user_ids.to_vec();
+
+    let found_users: Vec<User> = users::table
+        .filter(users::id.eq(any(&user_ids)))
+        .load(&connection)
+        .expect("Error loading profiles");
+    let followed_ids: Vec<i32> = followings::table
+        .filter(followings::followerid.eq(viewer_id).and(
+            followings::followingid.eq(any(&user_ids)),
+        ))
+        .select(followings::followingid)
+        .load(&connection)
+        .expect("Error loading viewer follows");
+
+    found_users
+        .into_iter()
+        .map(|user| {
+            let profile = Profile {
+                username: user.username,
+                bio: user.bio,
+                image: user.image,
+                following: followed_ids.contains(&user.id),
+            };
+            (user.id, profile)
+        })
+        .collect()
+}
+
+fn follow_list_handler(req: Request, res: Response, c: Captures, direction: FollowDirection) {
+    let (_, logged_in_user_id) = prepare_parameters(req);
+
+    let caps = c.unwrap();
+    let url = &caps[0];
+    let path = url.split('?').next().unwrap();
+    let profile = path.replace("/api/profiles/", "")
+        .replace("/followers", "")
+        .replace("/following", "");
+    let limit: i64 = get_query_param(url, "limit").and_then(|v| v.parse().ok()).unwrap_or(20);
+    let offset: i64 = get_query_param(url, "offset").and_then(|v| v.parse().ok()).unwrap_or(0);
+    println!("profile: {}", profile);
+
+    #[cfg(feature = "diesel")] {
+        let user = match get_user_by_name(&profile) {
+            Some(user) => user,
+            None => {
+                send_error(res, StatusCode::NotFound, "profile not found");
+                return;
+            }
+        };
+
+        process_container(
+            res,
+            profiles_result,
+            get_follow_list,
+            (
+                direction,
+                user.id,
+                logged_in_user_id,
+                std::cmp::max(std::cmp::min(limit, MAX_FOLLOW_LIST_LIMIT), 0),
+                std::cmp::max(offset, 0),
+            ),
+        )
+    }
+}
+
+pub fn followers_handler(req: Request, res: Response, c: Captures) {
+    follow_list_handler(req, res, c, FollowDirection::Followers)
+}
+
+pub fn following_handler(req: Request, res: Response, c: Captures) {
+    follow_list_handler(req, res, c, FollowDirection::Following)
+}
+
+pub fn set_authorization_headers(res: &mut Response, user_id: i32) {
+    let token = new_token(user_id.to_string().as_ref(), "").unwrap();
+
+    res.headers_mut().set(Authorization(
+        Bearer { token: token.to_owned() },
+    ));
+    res.headers_mut().set(AccessControlAllowOrigin::Any);
+    res.headers_mut().set(AccessControlAllowHeaders(vec![
+        UniCase("content-type".to_owned()),
+        UniCase("authorization".to_owned()),
+    ]));
+    res.headers_mut().set(ContentType(Mime(
+        TopLevel::Application,
+        SubLevel::Json,
+        vec![(Attr::Charset, Value::Utf8)],
+    )));
+
+    *res.status_mut() = StatusCode::Ok;
+}
+
 pub fn authentication_handler(mut req: Request, mut res: Response, _: Captures) {
     let mut body = String::new();
     let _ = req.read_to_string(&mut body);
+    let remote_ip = client_ip(&req);
     let login: Login = serde_json::from_str(&body).unwrap();
     let user_email: &str = &login.user.email;
 
     let mut result: Option<UserResult> = None;
+    let mut challenge: Option<TwoFactorChallengeResult> = None;
     #[cfg(feature = "diesel")]
     {
         use schema::users::dsl::*;
 
+        // checked before hashing, so a locked account costs no PBKDF2 computation
+        if let Some(retry_after) = login_retry_after(user_email, &remote_ip) {
+            send_too_many_requests(res, retry_after);
+            return;
+        }
+
         let connection = establish_connection();
-        let user: User = users
-            .filter(email.eq(user_email))
+        let user: Option<User> = users
+            .filter(email.eq(user_email).and(tenantid.eq(current_tenant())))
             .first(&connection)
+            .optional()
             .unwrap();
-        let stored_hash: &str = &user.token.to_owned();
-        let user_id = user.id;
-        let authenticated_user = crypto::pbkdf2::pbkdf2_check(&login.user.password, &stored_hash);
-        result = Some(UserResult { user: user });
+        let authenticated_user = match user {
+            Some(ref user) => crypto::pbkdf2::pbkdf2_check(&login.user.password, &user.token),
+            None => Ok(false),
+        };
 
         match authenticated_user {
-            Ok(valid) => {
-                if valid {
-                    let token2 = new_token(user_id.to_string().as_ref(), &login.user.password)
-                        .unwrap();
-
-                    res.headers_mut().set(Authorization(
-                        Bearer { token: token2.to_owned() },
-                    ));
-                    res.headers_mut().set(AccessControlAllowOrigin::Any);
-                    res.headers_mut().set(AccessControlAllowHeaders(vec![
-                        UniCase("content-type".to_owned()),
-                        UniCase("authorization".to_owned()),
-                    ]));
-                    res.headers_mut().set(ContentType(Mime(
-                        TopLevel::Application,
-                        SubLevel::Json,
-                        vec![(Attr::Charset, Value::Utf8)],
-                    )));
-
-                    *res.status_mut() = StatusCode::Ok;
+            Ok(true) => {
+                let user = user.unwrap();
+                let user_id = user.id;
+
+                if is_two_factor_enabled(user_id) {
+                    let pre_auth_token = new_pre_auth_token(user_id.to_string().as_ref()).unwrap();
+                    challenge = Some(TwoFactorChallengeResult {
+                        twoFactor: TwoFactorChallenge {
+                            required: true,
+                            preAuthToken: pre_auth_token,
+                        },
+                    });
+                } else {
+                    // with two-factor on, only the second step clears the lockout
+                    record_login_success(user_email);
+                    set_authorization_headers(&mut res, user_id);
+                    result = Some(UserResult { user: user });
                 }
             }
             _ => {
-                result = None;
+                record_login_failure(user_email, &remote_ip);
+                send_error(res, StatusCode::Unauthorized, "email or password is invalid");
+                return;
             }
         }
     }
@@ -517,6 +796,12 @@ pub fn authentication_handler(mut req: Request, mut res: Response, _: Captures)
         sql.run(get_user_cmd).unwrap();
     }
 
+    if challenge.is_some() {
+        let challenge = serde_json::to_string(&challenge.unwrap()).unwrap();
+        res.send(challenge.as_bytes()).unwrap();
+        return;
+    }
+
     if result.is_some() {
         let result = result.unwrap();
         let result = serde_json::to_string(&result).unwrap();
@@ -792,3 +1077,52 @@ fn unfollow_test() {
 
     assert_eq!(res.status, hyper::Ok);
 }
+
+#[cfg(test)]
+fn fetch_profiles(url: &str, jwt: &str) -> ProfilesResult {
+    let client = Client::new();
+
+    let mut res = client
+        .get(url)
+        .header(Authorization(Bearer { token: jwt.to_owned() }))
+        .send()
+        .unwrap();
+    let mut buffer = String::new();
+    res.read_to_string(&mut buffer).unwrap();
+
+    assert_eq!(res.status, hyper::Ok);
+    serde_json::from_str(&buffer).unwrap()
+}
+
+#[cfg(test)]
+#[test]
+fn follow_lists_test() {
+    let client = Client::new();
+
+    let (followed_name, _) = register_jacob();
+    let (follower_name, follower_email) = register_jacob();
+    let jwt = login_jacob(follower_email, JACOB_PASSWORD.to_string());
+    let url = format!("http://localhost:6767/api/profiles/{}/follow", followed_name);
+
+    // following twice must not fail nor duplicate the relationship
+    for _ in 0..2 {
+        let res = client
+            .post(&url)
+            .header(Authorization(Bearer { token: jwt.to_owned() }))
+            .send()
+            .unwrap();
+        assert_eq!(res.status, hyper::Ok);
+    }
+
+    let url = format!("http://localhost:6767/api/profiles/{}/followers?limit=10", followed_name);
+    let followers = fetch_profiles(&url, &jwt).profiles;
+    assert_eq!(followers.len(), 1);
+    assert_eq!(followers[0].username, follower_name);
+    assert_eq!(followers[0].following, false);
+
+    let url = format!("http://localhost:6767/api/profiles/{}/following", follower_name);
+    let following = fetch_profiles(&url, &jwt).profiles;
+    assert_eq!(following.len(), 1);
+    assert_eq!(following[0].username, followed_name);
+    assert_eq!(following[0].following, true);
+}
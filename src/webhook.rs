@@ -0,0 +1,820 @@
+extern crate hyper;
+
+extern crate serde;
+extern crate serde_json;
+
+extern crate chrono;
+
+extern crate crypto;
+
+extern crate rand;
+
+extern crate reroute;
+
+use hyper::status::StatusCode;
+
+use hyper::server::{Request, Response};
+use reroute::Captures;
+
+use crypto::hmac::Hmac;
+use crypto::mac::Mac;
+use crypto::sha2::Sha256;
+
+use rand::Rng;
+use rand::os::OsRng;
+
+use std::net::{IpAddr, ToSocketAddrs};
+use std::time::{Duration, Instant};
+
+use chrono::Duration as ChronoDuration;
+
+use super::*;
+
+pub static WEBHOOK_EVENTS: &'static [&'static str] = &[
+    "article.created",
+    "article.published",
+    "article.updated",
+    "article.deleted",
+    "article.restored",
+    "comment.created",
+    "user.followed",
+];
+
+static PENDING: &'static str = "pending";
+static DELIVERED: &'static str = "delivered";
+static FAILED: &'static str = "failed";
+
+const WEBHOOK_SECRET_LENGTH: usize = 24;
+const DELIVERY_BATCH_SIZE: i64 = 50;
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+struct IncomingWebhookDetail {
+    url: String,
+    events: Vec<String>,
+    global: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+struct IncomingWebhook {
+    webhook: IncomingWebhookDetail,
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+#[allow(non_snake_case)]
+pub struct WebhookDTO {
+    pub id: i32,
+    pub url: String,
+    pub events: Vec<String>,
+    pub global: bool,
+    pub active: bool,
+    pub createdAt: NaiveDateTime,
+    // only shown once, when the webhook is registered
+    pub secret: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+pub struct WebhookResult {
+    pub webhook: WebhookDTO,
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+pub struct WebhooksResult {
+    pub webhooks: Vec<WebhookDTO>,
+}
+
+impl Container<WebhookDTO> for WebhooksResult {
+    fn create_new_with_items(webhooks: Vec<WebhookDTO>) -> WebhooksResult {
+        WebhooksResult { webhooks: webhooks }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+#[allow(non_snake_case)]
+pub struct WebhookAttemptDTO {
+    pub attemptedAt: NaiveDateTime,
+    pub responseStatus: Option<i32>,
+    pub error: Option<String>,
+    pub durationMs: i32,
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+#[allow(non_snake_case)]
+pub struct WebhookDeliveryDTO {
+    pub id: i32,
+    pub event: String,
+    pub status: String,
+    pub attempts: i32,
+    pub nextAttemptAt: Option<NaiveDateTime>,
+    pub createdAt: NaiveDateTime,
+    pub deliveredAt: Option<NaiveDateTime>,
+    pub attemptLog: Vec<WebhookAttemptDTO>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+pub struct WebhookDeliveryResult {
+    pub delivery: WebhookDeliveryDTO,
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+pub struct WebhookDeliveriesResult {
+    pub deliveries: Vec<WebhookDeliveryDTO>,
+}
+
+impl Container<WebhookDeliveryDTO> for WebhookDeliveriesResult {
+    fn create_new_with_items(deliveries: Vec<WebhookDeliveryDTO>) -> WebhookDeliveriesResult {
+        WebhookDeliveriesResult { deliveries: deliveries }
+    }
+}
+
+#[derive(Serialize)]
+#[derive(Debug)]
+#[allow(non_snake_case)]
+struct WebhookEnvelope<'a, T: 'a> {
+    event: &'a str,
+    createdAt: NaiveDateTime,
+    data: &'a T,
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+pub struct DeletedArticleResult {
+    pub article: Article,
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+pub struct FollowedResult {
+    pub follower: String,
+    pub following: String,
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Value of the `X-Conduit-Signature` header: HMAC-SHA256 of the raw body, keyed with the webhook secret.
+pub fn sign_payload(secret: &str, payload: &[u8]) -> String {
+    let mut mac = Hmac::new(Sha256::new(), secret.as_bytes());
+    mac.input(payload);
+    format!("sha256={}", to_hex(mac.result().code()))
+}
+
+/// Wait before the next attempt once `attempts` deliveries failed; doubles every time.
+pub fn retry_delay_seconds(attempts: i32) -> i64 {
+    let exponent = std::cmp::min(std::cmp::max(attempts - 1, 0), 16) as u32;
+    *WEBHOOK_RETRY_SECONDS * 2i64.pow(exponent)
+}
+
+fn generate_webhook_secret() -> String {
+    let mut secret = [0u8; WEBHOOK_SECRET_LENGTH];
+    OsRng::new().expect("Failed to access the OS random number generator").fill_bytes(&mut secret);
+    to_hex(&secret)
+}
+
+fn to_webhook_dto(webhook: Webhook, show_secret: bool) -> WebhookDTO {
+    WebhookDTO {
+        id: webhook.id,
+        url: webhook.url,
+        events: webhook.events.split(',').map(|e| e.to_string()).collect(),
+        global: webhook.global,
+        active: webhook.active,
+        createdAt: webhook.createdat,
+        secret: if show_secret { Some(webhook.secret) } else { None },
+    }
+}
+
+/// Queues `event` for the webhooks of `subject_user_id` and for the global ones of the tenant; the worker thread sends them.
+#[cfg(feature = "diesel")]
+pub fn dispatch_webhooks<T>(event: &str, subject_user_id: i32, data: &T)
+where
+    T: serde::Serialize,
+{
+    use schema::{webhookdeliveries, webhooks};
+
+    let connection = establish_connection();
+    let subscribed: Vec<Webhook> = webhooks::table
+        .filter(webhooks::active.eq(true).and(
+            webhooks::ownerid.eq(subject_user_id).or(webhooks::global.eq(true)),
+        ))
+        .filter(webhooks::tenantid.eq(current_tenant()))
+        .load(&connection)
+        .expect("Error loading webhooks");
+
+    let now = Utc::now().naive_utc();
+    let envelope = WebhookEnvelope {
+        event: event,
+        createdAt: now,
+        data: data,
+    };
+    let payload = serde_json::to_string(&envelope).unwrap();
+
+    for webhook in subscribed.iter().filter(|w| w.events.split(',').any(|e| e == event)) {
+        let delivery = NewWebhookDelivery {
+            webhookid: webhook.id,
+            event: event,
+            payload: &payload,
+            status: PENDING,
+            attempts: 0,
+            nextattemptat: Some(now),
+            createdat: now,
+        };
+        diesel::insert(&delivery)
+            .into(webhookdeliveries::table)
+            .execute(&connection)
+            .expect("Error queueing webhook delivery");
+    }
+}
+
+/// Host and port of an `http://` url, the host without the brackets of an IPv6 literal.
+pub fn endpoint_host_port(url: &str) -> Option<(String, u16)> {
+    if !url.starts_with("http://") {
+        return None;
+    }
+    let authority = url["http://".len()..].split(|c: char| c == '/' || c == '?' || c == '#').next().unwrap();
+    let host_port = authority.rsplit('@').next().unwrap();
+    let (host, port) = if host_port.starts_with('[') {
+        let end = host_port.find(']')?;
+        (&host_port[1..end], &host_port[end + 1..])
+    } else {
+        match host_port.rfind(':') {
+            Some(colon) => (&host_port[..colon], &host_port[colon..]),
+            None => (host_port, ""),
+        }
+    };
+    let port = match port {
+        "" => 80,
+        port if port.starts_with(':') => port[1..].parse().ok()?,
+        _ => return None,
+    };
+    if host.is_empty() {
+        return None;
+    }
+    Some((host.to_lowercase(), port))
+}
+
+/// False for addresses of the server's own network: loopback, link-local, private and unspecified ones.
+pub fn is_public_address(address: &IpAddr) -> bool {
+    match *address {
+        IpAddr::V4(ip) => {
+            let octets = ip.octets();
+            !(ip.is_loopback() || ip.is_private() || ip.is_link_local() || ip.is_unspecified() || ip.is_broadcast() ||
+                octets[0] == 0 || (octets[0] == 100 && octets[1] & 0xc0 == 64))
+        }
+        IpAddr::V6(ip) => {
+            let segments = ip.segments();
+            // ::ffff:a.b.c.d reaches the IPv4 address a.b.c.d
+            if segments[..5].iter().all(|&s| s == 0) && segments[5] == 0xffff {
+                let octets = ip.octets();
+                return is_public_address(&IpAddr::from([octets[12], octets[13], octets[14], octets[15]]));
+            }
+            !(ip.is_loopback() || ip.is_unspecified() || segments[0] & 0xfe00 == 0xfc00 || segments[0] & 0xffc0 == 0xfe80)
+        }
+    }
+}
+
+/// Whether deliveries may go to `url`: every address its host resolves to has to be public, unless the host is allowed in
+/// the `[webhooks]` section.
+pub fn is_allowed_endpoint(url: &str) -> bool {
+    let (host, port) = match endpoint_host_port(url) {
+        Some(host_port) => host_port,
+        None => return false,
+    };
+    if WEBHOOK_ALLOWED_HOSTS.iter().any(|allowed| allowed.eq_ignore_ascii_case(&host)) {
+        return true;
+    }
+    match (host.as_str(), port).to_socket_addrs() {
+        Ok(addresses) => {
+            let addresses: Vec<IpAddr> = addresses.map(|address| address.ip()).collect();
+            !addresses.is_empty() && addresses.iter().all(is_public_address)
+        }
+        Err(_) => false,
+    }
+}
+
+#[cfg(feature = "diesel")]
+fn attempt_delivery(delivery: &WebhookDelivery, webhook: &Webhook, connection: &PgConnection) {
+    use schema::{webhookattempts, webhookdeliveries};
+
+    let timeout = Duration::from_secs(*WEBHOOK_TIMEOUT_SECONDS);
+    let mut client = hyper::Client::new();
+    client.set_read_timeout(Some(timeout));
+    client.set_write_timeout(Some(timeout));
+    // a redirect could lead anywhere, past the checks of is_allowed_endpoint
+    client.set_redirect_policy(hyper::client::RedirectPolicy::FollowNone);
+
+    let mut headers = hyper::header::Headers::new();
+    headers.set(ContentType(Mime(
+        TopLevel::Application,
+        SubLevel::Json,
+        vec![(Attr::Charset, Value::Utf8)],
+    )));
+    headers.set_raw("X-Conduit-Event", vec![delivery.event.as_bytes().to_vec()]);
+    headers.set_raw("X-Conduit-Delivery", vec![delivery.id.to_string().into_bytes()]);
+    headers.set_raw(
+        "X-Conduit-Signature",
+        vec![sign_payload(&webhook.secret, delivery.payload.as_bytes()).into_bytes()],
+    );
+
+    let started = Instant::now();
+    // the host may resolve elsewhere than when the webhook was registered
+    let outcome: Result<u16, String> = if !is_allowed_endpoint(&webhook.url) {
+        Err("endpoint resolves to a non-public address".to_string())
+    } else {
+        client
+            .post(&webhook.url)
+            .headers(headers)
+            .body(delivery.payload.as_str())
+            .send()
+            .map(|res| res.status.to_u16())
+            .map_err(|e| e.to_string())
+    };
+    let elapsed = started.elapsed();
+    let duration_ms = (elapsed.as_secs() * 1000) as i32 + (elapsed.subsec_nanos() / 1_000_000) as i32;
+
+    let now = Utc::now().naive_utc();
+    let error: Option<String> = match outcome {
+        Ok(code) if code >= 200 && code < 300 => None,
+        Ok(code) => Some(format!("endpoint answered {}", code)),
+        Err(ref e) => Some(e.chars().take(500).collect()),
+    };
+    let attempt = NewWebhookAttempt {
+        deliveryid: delivery.id,
+        attemptedat: now,
+        responsestatus: outcome.as_ref().ok().map(|&code| code as i32),
+        error: error.as_ref().map(|e| e.as_str()),
+        durationms: duration_ms,
+    };
+    diesel::insert(&attempt)
+        .into(webhookattempts::table)
+        .execute(connection)
+        .expect("Error saving webhook attempt");
+
+    let attempts = delivery.attempts + 1;
+    let (status, next_attempt_at, delivered_at) = if error.is_none() {
+        (DELIVERED, None, Some(now))
+    } else if attempts >= *WEBHOOK_MAX_ATTEMPTS {
+        (FAILED, None, None)
+    } else {
+        (PENDING, Some(now + ChronoDuration::seconds(retry_delay_seconds(attempts))), None)
+    };
+    diesel::update(webhookdeliveries::table.find(delivery.id))
+        .set((
+            webhookdeliveries::status.eq(status),
+            webhookdeliveries::attempts.eq(attempts),
+            webhookdeliveries::nextattemptat.eq(next_attempt_at),
+            webhookdeliveries::deliveredat.eq(delivered_at),
+        ))
+        .execute(connection)
+        .expect("Error updating webhook delivery");
+}
+
+#[cfg(feature = "diesel")]
+fn deliver_due_webhooks() {
+    use schema::{webhookdeliveries, webhooks};
+
+    let connection = establish_connection();
+    let now = Utc::now().naive_utc();
+
+    let due: Vec<WebhookDelivery> = webhookdeliveries::table
+        .filter(webhookdeliveries::status.eq(PENDING).and(
+            webhookdeliveries::nextattemptat.le(now),
+        ))
+        .order(webhookdeliveries::nextattemptat.asc())
+        .limit(DELIVERY_BATCH_SIZE)
+        .load(&connection)
+        .expect("Error loading due webhook deliveries");
+
+    for delivery in due {
+        // pushing the next attempt out claims the delivery, another worker skips it
+        let lease = now + ChronoDuration::seconds(2 * *WEBHOOK_TIMEOUT_SECONDS as i64);
+        let claimed = diesel::update(webhookdeliveries::table.filter(
+            webhookdeliveries::id.eq(delivery.id).and(
+                webhookdeliveries::nextattemptat.eq(delivery.nextattemptat),
+            ),
+        )).set(webhookdeliveries::nextattemptat.eq(Some(lease)))
+            .execute(&connection)
+            .expect("Error claiming webhook delivery");
+        if claimed == 0 {
+            continue;
+        }
+
+        let webhook: Webhook = webhooks::table
+            .find(delivery.webhookid)
+            .first(&connection)
+            .expect("Error loading webhook");
+        attempt_delivery(&delivery, &webhook, &connection);
+    }
+}
+
+/// Sends queued deliveries in the background for as long as the server runs.
+#[cfg(feature = "diesel")]
+pub fn start_webhook_worker() {
+    use std::panic;
+    use std::thread;
+
+    thread::spawn(|| loop {
+        // a failing round (e.g. the database is restarting) must not stop later ones
+        if panic::catch_unwind(deliver_due_webhooks).is_err() {
+            println!("webhook delivery round failed");
+        }
+        thread::sleep(Duration::from_secs(*WEBHOOK_POLL_SECONDS));
+    });
+}
+
+#[cfg(feature = "diesel")]
+fn create_webhook(params: (i32, IncomingWebhookDetail)) -> Option<WebhookResult> {
+    use schema::webhooks;
+
+    let (owner_id, incoming) = params;
+    let connection = establish_connection();
+
+    let secret = generate_webhook_secret();
+    let events = incoming.events.join(",");
+    let new_webhook = NewWebhook {
+        ownerid: owner_id,
+        url: &incoming.url,
+        secret: &secret,
+        events: &events,
+        global: incoming.global.unwrap_or(false),
+        active: true,
+        createdat: Utc::now().naive_utc(),
+        tenantid: current_tenant(),
+    };
+    let webhook: Webhook = diesel::insert(&new_webhook)
+        .into(webhooks::table)
+        .get_result(&connection)
+        .expect("Error saving webhook");
+
+    Some(WebhookResult { webhook: to_webhook_dto(webhook, true) })
+}
+
+pub fn create_webhook_handler(req: Request, res: Response, _: Captures) {
+    let (body, logged_id) = prepare_parameters(req);
+
+    let incoming: IncomingWebhook = serde_json::from_str(&body).unwrap();
+    let incoming = incoming.webhook;
+
+    #[cfg(feature = "diesel")]
+    {
+        if logged_id <= 0 {
+            send_denied(res, Denied::Unauthenticated);
+            return;
+        }
+        // the client has no TLS support, so endpoints have to be plain http
+        if !incoming.url.starts_with("http://") || incoming.url.len() <= "http://".len() {
+            send_error(res, StatusCode::UnprocessableEntity, "url must be an http:// endpoint");
+            return;
+        }
+        if !is_allowed_endpoint(&incoming.url) {
+            send_error(res, StatusCode::UnprocessableEntity, "url must resolve to a public address");
+            return;
+        }
+        if incoming.events.is_empty() || incoming.events.iter().any(|e| !WEBHOOK_EVENTS.contains(&e.as_str())) {
+            send_error(res, StatusCode::UnprocessableEntity, "events must list known webhook events");
+            return;
+        }
+        // global webhooks see everybody's content
+        if incoming.global == Some(true) {
+            if let Err(denied) = check(logged_id, Action::ManageUsers, None) {
+                send_denied(res, denied);
+                return;
+            }
+        }
+
+        process(res, create_webhook, (logged_id, incoming))
+    }
+}
+
+#[cfg(feature = "diesel")]
+fn list_webhooks(owner_id: i32) -> Vec<WebhookDTO> {
+    use schema::webhooks::dsl::*;
+
+    let connection = establish_connection();
+    webhooks
+        .filter(ownerid.eq(owner_id))
+        .order(id.asc())
+        .load::<Webhook>(&connection)
+        .expect("Error loading webhooks")
+        .into_iter()
+        .map(|webhook| to_webhook_dto(webhook, false))
+        .collect()
+}
+
+fn webhooks_result(_: WebhooksResult) {}
+
+pub fn list_webhooks_handler(req: Request, res: Response, _: Captures) {
+    let (_, logged_id) = prepare_parameters(req);
+
+    #[cfg(feature = "diesel")]
+    {
+        if logged_id <= 0 {
+            send_denied(res, Denied::Unauthenticated);
+            return;
+        }
+        process_container(res, webhooks_result, list_webhooks, logged_id)
+    }
+}
+
+#[cfg(feature = "diesel")]
+fn find_webhook(webhook_id: &str) -> Option<Webhook> {
+    use schema::webhooks::dsl::*;
+
+    let connection = establish_connection();
+    match webhook_id.parse::<i32>() {
+        Ok(parsed_id) => webhooks
+            .find(parsed_id)
+            .filter(tenantid.eq(current_tenant()))
+            .first(&connection)
+            .optional()
+            .expect("Error loading webhook"),
+        Err(_) => None,
+    }
+}
+
+#[cfg(feature = "diesel")]
+fn delete_webhook(webhook: Webhook) -> Option<WebhookResult> {
+    use schema::webhooks::dsl::*;
+
+    let connection = establish_connection();
+    diesel::delete(webhooks.find(webhook.id))
+        .execute(&connection)
+        .expect("Error deleting webhook");
+
+    let mut deleted = to_webhook_dto(webhook, false);
+    deleted.active = false;
+    Some(WebhookResult { webhook: deleted })
+}
+
+pub fn delete_webhook_handler(req: Request, res: Response, c: Captures) {
+    let (_, logged_id) = prepare_parameters(req);
+
+    let caps = c.unwrap();
+    let webhook_id = caps[0].replace("/api/webhooks/", "");
+
+    #[cfg(feature = "diesel")]
+    {
+        let webhook = match find_webhook(&webhook_id) {
+            Some(webhook) => webhook,
+            None => {
+                send_error(res, StatusCode::NotFound, "webhook not found");
+                return;
+            }
+        };
+        if let Err(denied) = check(logged_id, Action::ManageWebhook, Some(webhook.ownerid)) {
+            send_denied(res, denied);
+            return;
+        }
+        process(res, delete_webhook, webhook)
+    }
+}
+
+#[cfg(feature = "diesel")]
+fn to_delivery_dto(delivery: WebhookDelivery, connection: &PgConnection) -> WebhookDeliveryDTO {
+    use schema::webhookattempts::dsl::*;
+
+    let attempt_log: Vec<WebhookAttempt> = webhookattempts
+        .filter(deliveryid.eq(delivery.id))
+        .order(id.asc())
+        .load(connection)
+        .expect("Error loading webhook attempts");
+
+    WebhookDeliveryDTO {
+        id: delivery.id,
+        event: delivery.event,
+        status: delivery.status,
+        attempts: delivery.attempts,
+        nextAttemptAt: delivery.nextattemptat,
+        createdAt: delivery.createdat,
+        deliveredAt: delivery.deliveredat,
+        attemptLog: attempt_log
+            .into_iter()
+            .map(|a| {
+                WebhookAttemptDTO {
+                    attemptedAt: a.attemptedat,
+                    responseStatus: a.responsestatus,
+                    error: a.error,
+                    durationMs: a.durationms,
+                }
+            })
+            .collect(),
+    }
+}
+
+#[cfg(feature = "diesel")]
+fn list_deliveries(params: (i32, i64, i64)) -> Vec<WebhookDeliveryDTO> {
+    use schema::webhookdeliveries::dsl::*;
+
+    let (webhook_id, limit, offset) = params;
+    let connection = establish_connection();
+
+    webhookdeliveries
+        .filter(webhookid.eq(webhook_id))
+        .order(id.desc())
+        .limit(limit)
+        .offset(offset)
+        .load::<WebhookDelivery>(&connection)
+        .expect("Error loading webhook deliveries")
+        .into_iter()
+        .map(|delivery| to_delivery_dto(delivery, &connection))
+        .collect()
+}
+
+fn deliveries_result(_: WebhookDeliveriesResult) {}
+
+pub fn list_deliveries_handler(req: Request, res: Response, c: Captures) {
+    let (_, logged_id) = prepare_parameters(req);
+
+    let caps = c.unwrap();
+    let url = &caps[0];
+    let webhook_id = url.split('?').next().unwrap().replace("/api/webhooks/", "").replace("/deliveries", "");
+    let limit: i64 = get_query_param(url, "limit").and_then(|v| v.parse().ok()).unwrap_or(20);
+    let offset: i64 = get_query_param(url, "offset").and_then(|v| v.parse().ok()).unwrap_or(0);
+
+    #[cfg(feature = "diesel")]
+    {
+        let webhook = match find_webhook(&webhook_id) {
+            Some(webhook) => webhook,
+            None => {
+                send_error(res, StatusCode::NotFound, "webhook not found");
+                return;
+            }
+        };
+        if let Err(denied) = check(logged_id, Action::ManageWebhook, Some(webhook.ownerid)) {
+            send_denied(res, denied);
+            return;
+        }
+        process_container(res, deliveries_result, list_deliveries, (webhook.id, limit, offset))
+    }
+}
+
+#[cfg(feature = "diesel")]
+fn redeliver(delivery_id: i32) -> Option<WebhookDeliveryResult> {
+    use schema::webhookdeliveries::dsl::*;
+
+    let connection = establish_connection();
+    // a redelivery gets the whole retry schedule again, earlier attempts stay in the log
+    let delivery: WebhookDelivery = diesel::update(webhookdeliveries.find(delivery_id))
+        .set((
+            status.eq(PENDING),
+            attempts.eq(0),
+            nextattemptat.eq(Some(Utc::now().naive_utc())),
+            deliveredat.eq(None::<NaiveDateTime>),
+        ))
+        .get_result(&connection)
+        .expect("Error queueing webhook redelivery");
+
+    Some(WebhookDeliveryResult { delivery: to_delivery_dto(delivery, &connection) })
+}
+
+pub fn redeliver_handler(req: Request, res: Response, c: Captures) {
+    let (_, logged_id) = prepare_parameters(req);
+
+    let caps = c.unwrap();
+    let delivery_id = caps[0].replace("/api/webhooks/deliveries/", "").replace("/redeliver", "");
+
+    #[cfg(feature = "diesel")]
+    {
+        use schema::{webhookdeliveries, webhooks};
+
+        let connection = establish_connection();
+        let owner: Option<(i32, i32)> = match delivery_id.parse::<i32>() {
+            Ok(parsed_id) => webhookdeliveries::table
+                .inner_join(webhooks::table)
+                .filter(webhookdeliveries::id.eq(parsed_id).and(webhooks::tenantid.eq(current_tenant())))
+                .select((webhookdeliveries::id, webhooks::ownerid))
+                .first(&connection)
+                .optional()
+                .expect("Error loading webhook delivery"),
+            Err(_) => None,
+        };
+        let (delivery_id, owner_id) = match owner {
+            Some(owner) => owner,
+            None => {
+                send_error(res, StatusCode::NotFound, "delivery not found");
+                return;
+            }
+        };
+        if let Err(denied) = check(logged_id, Action::ManageWebhook, Some(owner_id)) {
+            send_denied(res, denied);
+            return;
+        }
+        process(res, redeliver, delivery_id)
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn sign_payload_test() {
+    // RFC 4231, test case 2
+    assert_eq!(
+        sign_payload("Jefe", b"what do ya want for nothing?"),
+        "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn webhook_delivery_test() {
+    use hyper::server::Server;
+    use std::sync::Mutex;
+    use std::sync::mpsc;
+
+    let client = Client::new();
+    let (sender, received) = mpsc::channel();
+    let sender = Mutex::new(sender);
+    let mut receiver = Server::http("127.0.0.1:0")
+        .unwrap()
+        .handle(move |mut req: Request, res: Response| {
+            let signature = req.headers
+                .get_raw("X-Conduit-Signature")
+                .map(|values| String::from_utf8(values[0].clone()).unwrap());
+            let mut body = String::new();
+            req.read_to_string(&mut body).unwrap();
+            sender.lock().unwrap().send((signature, body)).unwrap();
+            res.send(b"ok").unwrap();
+        })
+        .unwrap();
+
+    let (_, email) = register_jacob();
+    let jwt = login_jacob(email, JACOB_PASSWORD.to_string());
+    let body = format!(
+        r#"{{"webhook": {{"url": "http://127.0.0.1:{}/hook", "events": ["article.created"]}}}}"#,
+        receiver.socket.port()
+    );
+    let mut res = client
+        .post("http://localhost:6767/api/webhooks")
+        .header(Authorization(Bearer { token: jwt.to_owned() }))
+        .body(&body)
+        .send()
+        .unwrap();
+    let mut buffer = String::new();
+    res.read_to_string(&mut buffer).unwrap();
+    assert_eq!(res.status, hyper::Ok);
+    let registered: WebhookResult = serde_json::from_str(&buffer).unwrap();
+    let secret = registered.webhook.secret.unwrap();
+
+    let title = format!("Hooked on dragons {}", since_the_epoch());
+    let body = format!(
+        r#"{{"article": {{"title": "{}","description": "Ever wonder how?","body": "You have to believe","tagList": ["dragons"]}}}}"#,
+        title
+    );
+    let res = client
+        .post("http://localhost:6767/api/articles")
+        .header(Authorization(Bearer { token: jwt }))
+        .body(&body)
+        .send()
+        .unwrap();
+    assert_eq!(res.status, hyper::Ok);
+
+    let (signature, payload) = received.recv_timeout(Duration::from_secs(30)).unwrap();
+    assert_eq!(signature.unwrap(), sign_payload(&secret, payload.as_bytes()));
+    assert!(payload.contains(r#""event":"article.created""#));
+    assert!(payload.contains(&title));
+
+    let _ = receiver.close();
+}
+
+#[cfg(test)]
+#[test]
+fn endpoint_address_test() {
+    assert_eq!(endpoint_host_port("http://Example.com/hook"), Some(("example.com".to_string(), 80)));
+    assert_eq!(endpoint_host_port("http://user@example.com:8080?x=1"), Some(("example.com".to_string(), 8080)));
+    assert_eq!(endpoint_host_port("http://[::1]:81/hook"), Some(("::1".to_string(), 81)));
+    assert_eq!(endpoint_host_port("http:///hook"), None);
+
+    for address in &["127.0.0.1", "10.1.2.3", "192.168.1.1", "169.254.169.254", "0.0.0.0", "::1", "fe80::1", "fd00::1", "::ffff:127.0.0.1"] {
+        assert!(!is_public_address(&address.parse().unwrap()), "{}", address);
+    }
+    for address in &["93.184.216.34", "2606:4700::1111", "::ffff:8.8.8.8"] {
+        assert!(is_public_address(&address.parse().unwrap()), "{}", address);
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn private_webhook_test() {
+    let client = Client::new();
+    let (_, email) = register_jacob();
+    let jwt = login_jacob(email, JACOB_PASSWORD.to_string());
+
+    // the metadata service of most clouds, and the server itself under a name that is not allowed
+    for url in &["http://169.254.169.254/latest/meta-data", "http://localhost:6767/api/user"] {
+        let body = format!(r#"{{"webhook": {{"url": "{}", "events": ["article.created"]}}}}"#, url);
+        let res = client
+            .post("http://localhost:6767/api/webhooks")
+            .header(Authorization(Bearer { token: jwt.to_owned() }))
+            .body(&body)
+            .send()
+            .unwrap();
+        assert_eq!(res.status, StatusCode::UnprocessableEntity);
+    }
+}